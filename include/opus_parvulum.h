@@ -0,0 +1,38 @@
+/* The plugin's codec and network pipeline, built with the "capi" feature.
+ * Results are VST3 tresult codes, 0 is kResultOk. */
+
+#ifndef OPUS_PARVULUM_H
+#define OPUS_PARVULUM_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct OpusParvulum OpusParvulum;
+
+/* NULL when the rate is unsupported. Offline pipelines seed the loss
+ * generators the same way every time. */
+OpusParvulum *opus_parvulum_create(double sample_rate, int32_t max_block, bool offline);
+
+/* Parameter ids and normalized values are the same as the plugin's. */
+int32_t opus_parvulum_set_param(OpusParvulum *pipeline, uint32_t id, double value);
+int32_t opus_parvulum_get_param(OpusParvulum *pipeline, uint32_t id, double *value);
+
+/* Frames the output lags the input, -1 for a NULL pipeline. */
+int32_t opus_parvulum_latency(OpusParvulum *pipeline);
+
+/* Up to max_block frames per call, the output must not alias the input. */
+int32_t opus_parvulum_process(OpusParvulum *pipeline, const float *left_in,
+                              const float *right_in, float *left_out,
+                              float *right_out, int32_t frames);
+
+void opus_parvulum_destroy(OpusParvulum *pipeline);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
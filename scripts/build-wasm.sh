@@ -0,0 +1,35 @@
+#!/bin/sh
+# Build the wasm bindings. audiopus_sys compiles libopus from C, which needs a C
+# compiler and libc for wasm32, so libopus is built here with wasi-sdk and linked
+# prebuilt.
+#
+#   WASI_SDK=/opt/wasi-sdk OPUS_SRC=~/src/opus-1.3.1 scripts/build-wasm.sh
+#
+# Extra arguments go to cargo, e.g. "check" instead of the default "build --release".
+set -eu
+
+: "${WASI_SDK:?set WASI_SDK to a wasi-sdk install}"
+: "${OPUS_SRC:?set OPUS_SRC to the libopus sources}"
+
+out="$(pwd)/target/wasm-opus"
+if [ ! -f "$out/lib/libopus.a" ]; then
+	cmake -S "$OPUS_SRC" -B "$out/build" \
+		-DCMAKE_TOOLCHAIN_FILE="$WASI_SDK/share/cmake/wasi-sdk.cmake" \
+		-DWASI_SDK_PREFIX="$WASI_SDK" \
+		-DCMAKE_BUILD_TYPE=Release \
+		-DCMAKE_INSTALL_PREFIX="$out" \
+		-DOPUS_STACK_PROTECTOR=OFF
+	cmake --build "$out/build" --target install
+fi
+
+# audiopus_sys links $LIBOPUS_LIB_DIR/lib/libopus.a instead of building its own
+export LIBOPUS_LIB_DIR="$out"
+export LIBOPUS_STATIC=1
+export LIBOPUS_NO_PKG=1
+# libopus calls malloc, memcpy, and libm, which wasm32-unknown-unknown doesn't have
+export RUSTFLAGS="${RUSTFLAGS:-} -L $WASI_SDK/share/wasi-sysroot/lib/wasm32-wasi -l static=c"
+
+if [ $# -eq 0 ]; then
+	set -- build --release
+fi
+exec cargo "$@" --target wasm32-unknown-unknown --no-default-features --features wasm
@@ -0,0 +1,62 @@
+#[cfg(feature = "vst3")]
+use log::*;
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
+use vst3_com::ComPtr;
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IMessage};
+
+/// Version of the audiopus bindings, kept in step with Cargo.toml
+const AUDIOPUS_VERSION: &str = "0.2";
+
+/// Version of the libopus actually linked, like "libopus 1.3.1"
+pub fn opus_version() -> String {
+	let version = unsafe { CStr::from_ptr(audiopus::ffi::opus_get_version_string()) };
+	version.to_string_lossy().into_owned()
+}
+
+/// Everything a bug report needs to identify the codec build
+pub fn build_info() -> String {
+	format!(
+		"{} {} ({}, audiopus {}, {}-{})",
+		env!("CARGO_PKG_NAME"),
+		env!("CARGO_PKG_VERSION"),
+		opus_version(),
+		AUDIOPUS_VERSION,
+		std::env::consts::OS,
+		std::env::consts::ARCH,
+	)
+}
+
+const MESSAGE_ID: &[u8] = b"OpusAbout\0";
+const ATTRIBUTE_ID: &[u8] = b"info\0";
+
+/// Fill in an about request, the sender reads the UTF-8 "info" attribute once notify returns
+#[cfg(feature = "vst3")]
+pub unsafe fn answer(message: *mut c_void) -> bool {
+	if message.is_null() {
+		return false;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return false;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		warn!("about: request has no attributes");
+		return false;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let info = build_info();
+	attributes.set_binary(
+		ATTRIBUTE_ID.as_ptr() as *const _,
+		info.as_ptr() as *const c_void,
+		info.len() as u32,
+	);
+	true
+}
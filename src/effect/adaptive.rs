@@ -0,0 +1,148 @@
+use super::duplex::rms_db;
+
+/// What the bitrate follows, in parameter order
+pub const SOURCES: [&str; 3] = ["Off", "Input Energy", "Bandwidth In"];
+
+const OFF: usize = 0;
+const ENERGY: usize = 1;
+
+/// How far a low bitrate narrows the bandwidth, in parameter order
+pub const NARROWINGS: [&str; 3] = ["Off", "Gentle", "Aggressive"];
+
+/// Bitrates under which the bandwidth narrows to narrowband, mediumband, wideband,
+/// and superwideband, in kbps, for each narrowing past Off
+const NARROW_BELOW_KBPS: [[f64; 4]; 2] = [[8.0, 10.0, 14.0, 24.0], [12.0, 16.0, 24.0, 40.0]];
+
+/// Bitrates Opus accepts, in kbps
+pub const MIN_KBPS: f64 = 6.0;
+pub const MAX_KBPS: f64 = 510.0;
+
+/// Slowest attack or release, in ms
+pub const MAX_TIME_MS: f64 = 5000.0;
+
+/// Input levels that get the floor and the ceiling, in dBFS
+const QUIET_DB: f64 = -60.0;
+const LOUD_DB: f64 = -12.0;
+
+/// A congestion-controlled sender, whose target bitrate follows the input level
+/// or the level of a sidechain standing in for the available bandwidth
+pub struct AdaptiveBitrate {
+	/// Index into SOURCES
+	pub source: usize,
+	/// Bitrate for a quiet input or a silent sidechain, in kbps
+	pub min_kbps: f64,
+	/// Bitrate for a loud input or a full scale sidechain, in kbps
+	pub max_kbps: f64,
+	/// Time to climb towards a higher bitrate, in ms
+	pub attack_ms: f64,
+	/// Time to fall towards a lower bitrate, in ms
+	pub release_ms: f64,
+	/// Index into NARROWINGS
+	pub narrowing: usize,
+	/// Peak of the sidechain in the current block, 0 to 1
+	available: f64,
+	/// Smoothed bitrate, in kbps
+	kbps: f64,
+}
+
+impl Default for AdaptiveBitrate {
+	fn default() -> Self {
+		Self {
+			source: OFF,
+			min_kbps: 12.0,
+			max_kbps: 64.0,
+			attack_ms: 50.0,
+			release_ms: 500.0,
+			narrowing: OFF,
+			available: 1.0,
+			kbps: 64.0,
+		}
+	}
+}
+
+impl AdaptiveBitrate {
+	/// A source drives the bitrate
+	pub fn is_enabled(&self) -> bool {
+		self.source != OFF
+	}
+
+	/// Sidechain peak of a block, held until the next block with the bus connected
+	pub fn set_available(&mut self, level: f32) {
+		self.available = f64::from(level).min(1.0);
+	}
+
+	/// Bitrate for the packet about to be encoded, in bits per second
+	pub fn next(&mut self, packet: &[[f32; 2]], packet_ms: f64) -> i32 {
+		// Unlike clamp, max and min keep a NaN in the input out of the smoothed bitrate
+		#[allow(clippy::manual_clamp)]
+		let amount = match self.source {
+			ENERGY => ((rms_db(packet) - QUIET_DB) / (LOUD_DB - QUIET_DB))
+				.max(0.0)
+				.min(1.0),
+			_ => self.available,
+		};
+		let target = self.min_kbps + (self.max_kbps - self.min_kbps) * amount;
+		let ms = if target > self.kbps {
+			self.attack_ms
+		} else {
+			self.release_ms
+		};
+		let coeff = if ms > 0.0 {
+			(-packet_ms / ms).exp()
+		} else {
+			0.0
+		};
+		self.kbps = target + (self.kbps - target) * coeff;
+		(self.kbps.clamp(MIN_KBPS, MAX_KBPS) * 1000.0).round() as i32
+	}
+
+	/// Widest bandwidth the smoothed bitrate allows, counted from narrowband,
+	/// like a real stack collapsing to narrowband under congestion. None leaves it to the user
+	pub fn bandwidth_limit(&self) -> Option<usize> {
+		if !self.is_enabled() || self.narrowing == OFF {
+			return None;
+		}
+		let below = NARROW_BELOW_KBPS.get(self.narrowing - 1)?;
+		let limit = below.iter().position(|kbps| self.kbps < *kbps);
+		Some(limit.unwrap_or(below.len()))
+	}
+
+	/// Start from the ceiling, like a sender that hasn't seen congestion yet
+	pub fn reset(&mut self) {
+		self.kbps = self.max_kbps;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn quiet_input_starves() {
+		let mut adaptive = AdaptiveBitrate {
+			source: ENERGY,
+			release_ms: 0.0,
+			..AdaptiveBitrate::default()
+		};
+		assert_eq!(adaptive.next(&[[0.0; 2]; 960], 20.0), 12000);
+
+		// Climbs part of the way in a packet
+		let bitrate = adaptive.next(&[[0.5; 2]; 960], 20.0);
+		assert!(bitrate > 12000 && bitrate < 64000);
+	}
+
+	#[test]
+	fn congestion_narrows_the_bandwidth() {
+		let mut adaptive = AdaptiveBitrate {
+			source: ENERGY,
+			release_ms: 0.0,
+			min_kbps: 6.0,
+			narrowing: 2,
+			..AdaptiveBitrate::default()
+		};
+		adaptive.reset();
+		assert_eq!(adaptive.bandwidth_limit(), Some(4));
+		adaptive.next(&[[0.0; 2]; 960], 20.0);
+		assert_eq!(adaptive.bandwidth_limit(), Some(0));
+	}
+}
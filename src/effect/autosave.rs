@@ -0,0 +1,210 @@
+use super::params::Parameter;
+use super::state;
+use crate::error::Error;
+use crate::error::Result;
+use enum_map::EnumMap;
+use log::*;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Time between snapshots while parameters are being edited
+const INTERVAL: Duration = Duration::from_secs(30);
+
+static NEXT_INSTANCE: AtomicUsize = AtomicUsize::new(0);
+
+fn directory() -> PathBuf {
+	std::env::temp_dir().join("opus-parvulum")
+}
+
+/// Every writer holds the lock beside its snapshot until it's gone, and the system
+/// lets go of it when the process dies, so a lock that can be taken has no writer
+fn is_orphaned(path: &Path) -> bool {
+	match File::open(path.with_extension("lock")) {
+		Ok(lock) => lock.try_lock().is_ok(),
+		Err(_) => false,
+	}
+}
+
+/// The latest values, written by the thread once the interval is up
+#[derive(Default)]
+struct Pending {
+	values: EnumMap<Parameter, f64>,
+	dirty: bool,
+}
+
+/// Periodic copies of the controller's state, removed on a clean terminate,
+/// so a snapshot left behind means a session crashed
+pub struct Autosave {
+	path: PathBuf,
+	/// Held while this instance can still write its snapshot
+	lock: Option<File>,
+	pending: Arc<Mutex<Pending>>,
+	/// Dropping it stops the writer, None where there's no thread
+	stop: Option<SyncSender<()>>,
+	writer: Option<JoinHandle<()>>,
+	/// Snapshot from a crashed session, claimed by this instance until restored or discarded
+	recovered: Option<PathBuf>,
+}
+
+impl Autosave {
+	/// Claims one orphaned snapshot, when there is one, and starts the writer
+	pub fn new() -> Self {
+		let instance = NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed);
+		let name = format!("{}-{}", std::process::id(), instance);
+		let path = directory().join(format!("{}.state", name));
+
+		// Locked before anything is written, so the snapshot always has a live lock
+		let lock = fs::create_dir_all(directory())
+			.and_then(|_| File::create(path.with_extension("lock")))
+			.and_then(|lock| lock.try_lock().map(|_| lock).map_err(Into::into));
+		let lock = match lock {
+			Ok(lock) => Some(lock),
+			Err(err) => {
+				warn!("autosave: can't lock {} {}", path.display(), err);
+				None
+			}
+		};
+
+		// Without the lock, a snapshot could be claimed while this instance runs
+		let pending = Arc::new(Mutex::new(Pending::default()));
+		let (recovered, (stop, writer)) = if lock.is_some() {
+			(claim(&name), start_writer(&path, &pending))
+		} else {
+			(None, (None, None))
+		};
+
+		Self {
+			path,
+			lock,
+			pending,
+			stop,
+			writer,
+			recovered,
+		}
+	}
+
+	/// Note an edit, the writer saves the latest values once the interval is up
+	pub fn save(&mut self, values: &EnumMap<Parameter, f64>) {
+		if let Ok(mut pending) = self.pending.lock() {
+			pending.values.clone_from(values);
+			pending.dirty = true;
+		}
+	}
+
+	/// A crashed session's snapshot was claimed and can be restored
+	pub fn has_recovered(&self) -> bool {
+		self.recovered.is_some()
+	}
+
+	/// Values from the crashed session, the snapshot is gone afterwards
+	pub fn restore(&mut self) -> Option<Result<state::Values>> {
+		let path = self.recovered.take()?;
+		let values = fs::read(&path)
+			.map_err(|_| Error::Stream("can't read snapshot"))
+			.and_then(|bytes| state::decode(&bytes))
+			.map(|chunk| chunk.values);
+		let _ = fs::remove_file(&path);
+		Some(values)
+	}
+
+	/// A clean exit, nothing to recover from this instance
+	pub fn remove(&mut self) {
+		drop(self.stop.take());
+		if let Some(writer) = self.writer.take() {
+			let _ = writer.join();
+		}
+		let _ = fs::remove_file(&self.path);
+		if let Some(recovered) = self.recovered.take() {
+			let _ = fs::remove_file(recovered);
+		}
+		// Unlocked last, once there's nothing left to claim
+		if self.lock.take().is_some() {
+			let _ = fs::remove_file(self.path.with_extension("lock"));
+		}
+	}
+}
+
+/// Rename one orphaned snapshot to this instance's name
+fn claim(name: &str) -> Option<PathBuf> {
+	let mut orphans = fs::read_dir(directory())
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		// A claimed snapshot whose session crashed again is offered again
+		.filter(|path| {
+			let ext = path.extension();
+			ext.is_some_and(|ext| ext == "state" || ext == "recovered")
+		})
+		.filter(|path| is_orphaned(path));
+
+	// Renaming is atomic, so two instances never claim the same snapshot
+	let claimed = directory().join(format!("{}.recovered", name));
+	orphans
+		.find(|orphan| fs::rename(orphan, &claimed).is_ok())
+		.map(|orphan| {
+			info!("autosave: recovered {}", orphan.display());
+			let _ = fs::remove_file(orphan.with_extension("lock"));
+			claimed
+		})
+}
+
+/// A thread that writes edits every INTERVAL, so hosts only ever wait on a lock
+fn start_writer(
+	path: &Path,
+	pending: &Arc<Mutex<Pending>>,
+) -> (Option<SyncSender<()>>, Option<JoinHandle<()>>) {
+	let (stop, stopped) = sync_channel(0);
+	let (path, pending) = (path.to_path_buf(), pending.clone());
+	let writer = thread::Builder::new()
+		.name("opus-parvulum-autosave".into())
+		.spawn(move || loop {
+			match stopped.recv_timeout(INTERVAL) {
+				Err(RecvTimeoutError::Timeout) => write(&path, &pending),
+				_ => return,
+			}
+		});
+	match writer {
+		Ok(writer) => (Some(stop), Some(writer)),
+		Err(err) => {
+			warn!("autosave: can't start the writer: {}", err);
+			(None, None)
+		}
+	}
+}
+
+/// Write the latest values if there were edits since the last snapshot
+fn write(path: &Path, pending: &Mutex<Pending>) {
+	let bytes = match pending.lock() {
+		Ok(mut pending) if pending.dirty => {
+			pending.dirty = false;
+			state::encode(&pending.values, &[])
+		}
+		_ => return,
+	};
+	if let Err(err) = fs::write(path, bytes) {
+		warn!("autosave: {} {}", path.display(), err);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_unlocked_snapshots_are_orphaned() {
+		let path =
+			std::env::temp_dir().join(format!("opus-parvulum-test-{}.state", std::process::id()));
+		let lock = File::create(path.with_extension("lock")).unwrap();
+		lock.try_lock().unwrap();
+		assert!(!is_orphaned(&path));
+
+		drop(lock);
+		assert!(is_orphaned(&path));
+		let _ = fs::remove_file(path.with_extension("lock"));
+	}
+}
@@ -0,0 +1,132 @@
+#[cfg(feature = "vst3")]
+use log::*;
+#[cfg(feature = "vst3")]
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "vst3")]
+use vst3_com::ComPtr;
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IMessage};
+
+/// Longest run counted on its own, longer runs share the last bucket
+pub const MAX_BURST: usize = 16;
+
+/// Counts of the histogram, read by notify() while process() counts
+#[derive(Default)]
+pub struct BurstRuns([AtomicU32; MAX_BURST]);
+
+impl BurstRuns {
+	/// Runs by length, from one packet up
+	pub fn counts(&self) -> [u32; MAX_BURST] {
+		let mut counts = [0; MAX_BURST];
+		for (count, run) in counts.iter_mut().zip(self.0.iter()) {
+			*count = run.load(Ordering::Relaxed);
+		}
+		counts
+	}
+}
+
+/// How many runs of consecutive lost packets had each length, to compare
+/// against the burst lengths measured on a real network
+#[derive(Default)]
+pub struct BurstHistogram {
+	runs: Arc<BurstRuns>,
+	/// Length of the run still going
+	current: usize,
+}
+
+impl BurstHistogram {
+	/// Count one packet, a run is added when the next packet arrives
+	pub fn record(&mut self, lost: bool) {
+		if lost {
+			self.current += 1;
+		} else if self.current > 0 {
+			let run = &self.runs.0[self.current.min(MAX_BURST) - 1];
+			run.store(
+				run.load(Ordering::Relaxed).saturating_add(1),
+				Ordering::Relaxed,
+			);
+			self.current = 0;
+		}
+	}
+
+	/// Runs by length, from one packet up
+	pub fn runs(&self) -> [u32; MAX_BURST] {
+		self.runs.counts()
+	}
+
+	/// The counts, for reading them without the DSP
+	pub fn shared(&self) -> Arc<BurstRuns> {
+		self.runs.clone()
+	}
+
+	/// Clear the runs and the one in progress
+	pub fn reset(&mut self) {
+		for run in self.runs.0.iter() {
+			run.store(0, Ordering::Relaxed);
+		}
+		self.current = 0;
+	}
+}
+
+const MESSAGE_ID: &[u8] = b"OpusBursts\0";
+const ATTRIBUTE_ID: &[u8] = b"runs\0";
+
+/// A histogram request, which the controller passes on to the processor
+#[cfg(feature = "vst3")]
+pub unsafe fn is_request(message: *mut c_void) -> bool {
+	if message.is_null() {
+		return false;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	!id.is_null() && CStr::from_ptr(id).to_bytes_with_nul() == MESSAGE_ID
+}
+
+/// Fill in a histogram request, the sender reads the "runs" attribute once notify returns,
+/// a little endian u32 per run length. Only works when the processor shares the process
+#[cfg(feature = "vst3")]
+pub unsafe fn answer(message: *mut c_void, runs: &BurstRuns) -> bool {
+	if !is_request(message) {
+		return false;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		warn!("bursts: request has no attributes");
+		return false;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let bytes: Vec<u8> = runs
+		.counts()
+		.iter()
+		.flat_map(|count| count.to_le_bytes())
+		.collect();
+	attributes.set_binary(
+		ATTRIBUTE_ID.as_ptr() as *const _,
+		bytes.as_ptr() as *const c_void,
+		bytes.len() as u32,
+	);
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn runs_are_counted_by_length() {
+		let mut histogram = BurstHistogram::default();
+		let lost = [true, false, true, true, false, false, true, true, true];
+		for lost in lost.iter() {
+			histogram.record(*lost);
+		}
+		histogram.record(false);
+		assert_eq!(histogram.runs()[..4], [1, 1, 1, 0]);
+	}
+}
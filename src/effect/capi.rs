@@ -0,0 +1,177 @@
+use super::dsp::AuxInputs;
+use super::dsp::OpusDSP;
+use super::dsp::ParamQueueMap;
+use super::link::Opener;
+use super::params::Parameter;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sys::{kInternalError, kInvalidArgument, kResultOk, tresult, ProcessSetup};
+use std::convert::TryFrom;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::slice;
+
+/// The codec and network pipeline the plugin runs, without the VST plumbing.
+/// Opaque to C, see include/opus_parvulum.h
+pub struct OpusParvulum {
+	dsp: Box<OpusDSP>,
+	max_block: usize,
+}
+
+/// Panics must not unwind into C, so they become kInternalError
+fn guard(f: impl FnOnce() -> Result<()>) -> tresult {
+	match panic::catch_unwind(AssertUnwindSafe(f)) {
+		Ok(Ok(())) => kResultOk,
+		Ok(Err(err)) => err.result(),
+		Err(_) => kInternalError,
+	}
+}
+
+unsafe fn handle<'a>(handle: *mut OpusParvulum) -> Result<&'a mut OpusParvulum> {
+	handle.as_mut().ok_or(Error::NotInitialized("null handle"))
+}
+
+/// A pipeline at the host rate with default parameters, or null if the rate is unsupported.
+/// Offline runs seed the loss generators the same way every time, for repeatable measurements
+#[no_mangle]
+pub extern "C" fn opus_parvulum_create(
+	sample_rate: f64,
+	max_block: i32,
+	offline: bool,
+) -> *mut OpusParvulum {
+	let result = panic::catch_unwind(|| {
+		let max_block = usize::try_from(max_block).ok().filter(|len| *len > 0)?;
+		let mut dsp = Box::new(OpusDSP::default());
+		// The link keeps the opener running for as long as the pipeline lives
+		if let Some(opener) = Opener::start() {
+			dsp.link.attach(&opener);
+		}
+		dsp.set_offline(offline);
+		dsp.set_rendering(offline);
+		let setup = ProcessSetup {
+			process_mode: if offline { 2 } else { 0 },
+			symbolic_sample_size: 0,
+			max_samples_per_block: max_block as i32,
+			sample_rate,
+		};
+		dsp.setup(&setup).ok()?;
+		Some(Box::new(OpusParvulum { dsp, max_block }))
+	});
+	match result {
+		Ok(Some(pipeline)) => Box::into_raw(pipeline),
+		_ => std::ptr::null_mut(),
+	}
+}
+
+/// Set a parameter by id, with a normalized value like a host would
+///
+/// # Safety
+/// `pipeline` must come from opus_parvulum_create and not be destroyed
+#[no_mangle]
+pub unsafe extern "C" fn opus_parvulum_set_param(
+	pipeline: *mut OpusParvulum,
+	id: u32,
+	value: f64,
+) -> tresult {
+	guard(|| {
+		let pipeline = handle(pipeline)?;
+		let param = Parameter::try_from(id)
+			.map_err(|_| Error::OutOfRange(format!("no parameter {}", id)))?;
+		param.set_to_dsp(&mut pipeline.dsp, value)
+	})
+}
+
+/// Read a parameter's normalized value, including the read-only counters
+///
+/// # Safety
+/// `pipeline` must come from opus_parvulum_create and not be destroyed,
+/// and `value` must point to a double
+#[no_mangle]
+pub unsafe extern "C" fn opus_parvulum_get_param(
+	pipeline: *mut OpusParvulum,
+	id: u32,
+	value: *mut f64,
+) -> tresult {
+	if value.is_null() {
+		return kInvalidArgument;
+	}
+
+	guard(|| {
+		let pipeline = handle(pipeline)?;
+		let param = Parameter::try_from(id)
+			.map_err(|_| Error::OutOfRange(format!("no parameter {}", id)))?;
+		*value = param.get_from_dsp(&pipeline.dsp)?;
+		Ok(())
+	})
+}
+
+/// Frames the output lags the input
+///
+/// # Safety
+/// `pipeline` must come from opus_parvulum_create and not be destroyed
+#[no_mangle]
+pub unsafe extern "C" fn opus_parvulum_latency(pipeline: *mut OpusParvulum) -> i32 {
+	match pipeline.as_ref() {
+		Some(pipeline) => pipeline.dsp.latency() as i32,
+		None => -1,
+	}
+}
+
+/// Process up to max_block frames of stereo audio, the output may not alias the input
+///
+/// # Safety
+/// `pipeline` must come from opus_parvulum_create and not be destroyed,
+/// and every channel must hold `frames` floats
+#[no_mangle]
+pub unsafe extern "C" fn opus_parvulum_process(
+	pipeline: *mut OpusParvulum,
+	left_in: *const f32,
+	right_in: *const f32,
+	left_out: *mut f32,
+	right_out: *mut f32,
+	frames: i32,
+) -> tresult {
+	if [left_in, right_in].iter().any(|ptr| ptr.is_null())
+		|| [left_out, right_out].iter().any(|ptr| ptr.is_null())
+	{
+		return kInvalidArgument;
+	}
+
+	guard(|| {
+		let pipeline = handle(pipeline)?;
+		let frames = usize::try_from(frames)
+			.ok()
+			.filter(|frames| *frames <= pipeline.max_block)
+			.ok_or_else(|| Error::OutOfRange(format!("{} frames", frames)))?;
+
+		let input = [
+			slice::from_raw_parts(left_in, frames),
+			slice::from_raw_parts(right_in, frames),
+		];
+		let output = [
+			slice::from_raw_parts_mut(left_out, frames),
+			slice::from_raw_parts_mut(right_out, frames),
+		];
+
+		// Parameters are set between blocks, so there are never queued changes.
+		// Without spares, the coders are rebuilt here for the settings they changed
+		let params = ParamQueueMap::default();
+		pipeline.dsp.rebuild()?;
+		pipeline.dsp.advance_timeline(None, frames);
+		pipeline
+			.dsp
+			.process(&params, input, AuxInputs::default(), output, false)?;
+		Ok(())
+	})
+}
+
+/// Free a pipeline, null is ignored
+///
+/// # Safety
+/// `pipeline` must come from opus_parvulum_create and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn opus_parvulum_destroy(pipeline: *mut OpusParvulum) {
+	if !pipeline.is_null() {
+		drop(Box::from_raw(pipeline));
+	}
+}
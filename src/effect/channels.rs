@@ -0,0 +1,30 @@
+/// Utility switches on the coded output, for lining it up with the dry signal and null tests
+#[derive(Default)]
+pub struct ChannelTools {
+	pub swap: bool,
+	pub invert_left: bool,
+	pub invert_right: bool,
+	pub mono: bool,
+}
+
+impl ChannelTools {
+	/// Mono sum first, then polarity, then the swap, so the polarity switches
+	/// always name the channel they came in on
+	pub fn process(&self, frames: &mut [[f32; 2]]) {
+		for frame in frames.iter_mut() {
+			if self.mono {
+				let mid = (frame[0] + frame[1]) / 2.0;
+				*frame = [mid, mid];
+			}
+			if self.invert_left {
+				frame[0] = -frame[0];
+			}
+			if self.invert_right {
+				frame[1] = -frame[1];
+			}
+			if self.swap {
+				frame.swap(0, 1);
+			}
+		}
+	}
+}
@@ -0,0 +1,56 @@
+/// Longest delay the line holds, past the latency of the longest packets with FEC
+const MAX_DELAY_SECS: f64 = 0.5;
+
+/// The untouched input, delayed by the plugin's latency so a recording of it
+/// lines up with the coded output
+#[derive(Default)]
+pub struct CleanFeed {
+	line: Vec<[f32; 2]>,
+	write: usize,
+}
+
+impl CleanFeed {
+	/// Room for the longest delay at the host rate, which allocates
+	pub fn set_rate(&mut self, rate: f64) {
+		self.line = vec![[0.0; 2]; (rate * MAX_DELAY_SECS) as usize + 1];
+		self.write = 0;
+	}
+
+	/// Push a block of input and write the frames from `delay` frames ago
+	pub fn process(&mut self, input: [&[f32]; 2], output: [&mut [f32]; 2], delay: usize) {
+		let [in0, in1] = input;
+		let [out0, out1] = output;
+		let num_samples = in0.len().min(in1.len()).min(out0.len()).min(out1.len());
+		let len = self.line.len();
+		if len == 0 {
+			out0[..num_samples].copy_from_slice(&in0[..num_samples]);
+			out1[..num_samples].copy_from_slice(&in1[..num_samples]);
+			return;
+		}
+		let delay = delay.min(len - 1);
+		for i in 0..num_samples {
+			self.line[self.write] = [in0[i], in1[i]];
+			let [l, r] = self.line[(self.write + len - delay) % len];
+			out0[i] = l;
+			out1[i] = r;
+			self.write = (self.write + 1) % len;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn delays_by_the_latency() {
+		let mut clean = CleanFeed::default();
+		clean.set_rate(100.0);
+		let input = [1.0, 2.0, 3.0, 4.0];
+		let mut out0 = [0f32; 4];
+		let mut out1 = [0f32; 4];
+		clean.process([&input, &input], [&mut out0, &mut out1], 2);
+		assert_eq!(out0, [0.0, 0.0, 1.0, 2.0]);
+		assert_eq!(out1, out0);
+	}
+}
@@ -0,0 +1,245 @@
+use super::convolver::Convolver;
+use super::convolver::Impulse;
+use crate::error::Result;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Preset names, in parameter order
+pub const DEVICES: [&str; 5] = ["Off", "Earbud", "Laptop", "Phone", "Impulse"];
+
+/// Device that convolves with the loaded impulse response
+const IMPULSE: usize = 4;
+
+/// Filters in the longest preset
+const MAX_STAGES: usize = 4;
+
+#[derive(Copy, Clone)]
+enum Shape {
+	HighPass { hz: f64, q: f64 },
+	LowPass { hz: f64, q: f64 },
+	Peak { hz: f64, q: f64, db: f64 },
+}
+
+/// Rough responses of small drivers: bass rolloff, a presence bump, and no air
+fn preset(device: usize) -> &'static [Shape] {
+	match device {
+		1 => &[
+			Shape::HighPass { hz: 120.0, q: 0.7 },
+			Shape::Peak {
+				hz: 3000.0,
+				q: 1.5,
+				db: 4.0,
+			},
+			Shape::LowPass {
+				hz: 10000.0,
+				q: 0.7,
+			},
+		],
+		2 => &[
+			Shape::HighPass { hz: 300.0, q: 0.9 },
+			Shape::Peak {
+				hz: 500.0,
+				q: 1.0,
+				db: -4.0,
+			},
+			Shape::Peak {
+				hz: 1500.0,
+				q: 1.0,
+				db: 5.0,
+			},
+			Shape::LowPass { hz: 8000.0, q: 0.7 },
+		],
+		3 => &[
+			Shape::HighPass { hz: 500.0, q: 1.1 },
+			Shape::Peak {
+				hz: 2000.0,
+				q: 1.2,
+				db: 6.0,
+			},
+			Shape::LowPass { hz: 5000.0, q: 0.9 },
+		],
+		_ => &[],
+	}
+}
+
+/// RBJ cookbook biquad, transposed direct form II, one per channel
+#[derive(Copy, Clone, Default)]
+struct Biquad {
+	b: [f64; 3],
+	a: [f64; 2],
+	z: [[f64; 2]; 2],
+}
+
+impl Biquad {
+	fn new(shape: Shape, rate: f64) -> Self {
+		// Keep corners under Nyquist at the lower coder rates
+		let corner = |hz: f64| hz.min(rate * 0.45);
+		let (hz, q) = match shape {
+			Shape::HighPass { hz, q } | Shape::LowPass { hz, q } | Shape::Peak { hz, q, .. } => {
+				(corner(hz), q)
+			}
+		};
+		let w = 2.0 * PI * hz / rate;
+		let (sin, cos) = w.sin_cos();
+		let alpha = sin / (2.0 * q);
+
+		let (b, a0, a) = match shape {
+			Shape::HighPass { .. } => {
+				let b = [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0];
+				(b, 1.0 + alpha, [-2.0 * cos, 1.0 - alpha])
+			}
+			Shape::LowPass { .. } => {
+				let b = [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0];
+				(b, 1.0 + alpha, [-2.0 * cos, 1.0 - alpha])
+			}
+			Shape::Peak { db, .. } => {
+				let gain = 10f64.powf(db / 40.0);
+				let b = [1.0 + alpha * gain, -2.0 * cos, 1.0 - alpha * gain];
+				(b, 1.0 + alpha / gain, [-2.0 * cos, 1.0 - alpha / gain])
+			}
+		};
+
+		Self {
+			b: [b[0] / a0, b[1] / a0, b[2] / a0],
+			a: [a[0] / a0, a[1] / a0],
+			z: [[0.0; 2]; 2],
+		}
+	}
+
+	fn process(&mut self, channel: usize, x: f64) -> f64 {
+		let z = &mut self.z[channel];
+		let y = self.b[0] * x + z[0];
+		z[0] = self.b[1] * x - self.a[0] * y + z[1];
+		z[1] = self.b[2] * x - self.a[1] * y;
+		y
+	}
+}
+
+/// An impulse response file, kept with its path even when it can't be read so it
+/// isn't lost from the project. Shared by the DSPs that play it
+pub struct ImpulseFile {
+	path: String,
+	impulse: Option<Impulse>,
+}
+
+impl ImpulseFile {
+	/// Read the file, which stays off the audio thread. The file is returned either way
+	pub fn open(path: &str) -> (Self, Result<()>) {
+		let (impulse, result) = match Impulse::open(path) {
+			Ok(impulse) => (Some(impulse), Ok(())),
+			Err(err) => (None, Err(err)),
+		};
+		let path = path.to_string();
+		(Self { path, impulse }, result)
+	}
+}
+
+/// Shapes decoded audio like the speaker on the far end would
+pub struct Coloration {
+	device: usize,
+	rate: f64,
+	/// Frames per packet, the convolver works a packet at a time
+	block: usize,
+	stages: [Biquad; MAX_STAGES],
+	len: usize,
+	impulse: Option<Arc<ImpulseFile>>,
+	convolver: Option<Convolver>,
+}
+
+impl Default for Coloration {
+	fn default() -> Self {
+		Self {
+			device: 0,
+			rate: 48000.0,
+			block: 960,
+			stages: Default::default(),
+			len: 0,
+			impulse: None,
+			convolver: None,
+		}
+	}
+}
+
+impl Coloration {
+	/// Device the audio is colored like
+	pub fn device(&self) -> usize {
+		self.device
+	}
+
+	/// Doesn't allocate, so it's safe to call from parameter changes
+	pub fn set_device(&mut self, device: usize) {
+		self.device = device.min(DEVICES.len() - 1);
+		let shapes = preset(self.device);
+		for (stage, shape) in self.stages.iter_mut().zip(shapes) {
+			*stage = Biquad::new(*shape, self.rate);
+		}
+		self.len = shapes.len();
+	}
+
+	/// Rebuild the filters for the rate and packet size the decoder runs at
+	pub fn set_rate(&mut self, rate: f64, block: usize) {
+		let rebuild =
+			self.rate != rate || self.convolver.as_ref().map(Convolver::block) != Some(block);
+		self.rate = rate;
+		self.block = block;
+		self.set_device(self.device);
+		if rebuild {
+			self.prepare_convolver();
+		}
+	}
+
+	/// Path of the loaded impulse response
+	pub fn impulse_path(&self) -> Option<&str> {
+		self.impulse.as_ref().map(|file| file.path.as_str())
+	}
+
+	/// Load a WAV impulse response for the Impulse device, reads the file so stay off the audio thread
+	pub fn load_impulse(&mut self, path: &str) -> Result<()> {
+		let (file, result) = ImpulseFile::open(path);
+		self.set_impulse(Some(Arc::new(file)));
+		result
+	}
+
+	/// Loaded impulse response, for a spare to share
+	pub fn impulse(&self) -> Option<Arc<ImpulseFile>> {
+		self.impulse.clone()
+	}
+
+	/// Use a file another DSP loaded, preparing the convolver allocates
+	pub fn set_impulse(&mut self, file: Option<Arc<ImpulseFile>>) {
+		self.impulse = file;
+		self.prepare_convolver();
+	}
+
+	fn prepare_convolver(&mut self) {
+		self.convolver = self
+			.impulse
+			.as_ref()
+			.and_then(|file| file.impulse.as_ref())
+			.map(|impulse| Convolver::new(impulse, self.rate, self.block));
+	}
+
+	/// Color a decoded packet in place
+	pub fn process(&mut self, frames: &mut [[f32; 2]]) {
+		if self.device == IMPULSE {
+			if let Some(convolver) = &mut self.convolver {
+				convolver.process(frames);
+			}
+			return;
+		}
+
+		let stages = &mut self.stages[..self.len];
+		if stages.is_empty() {
+			return;
+		}
+		for frame in frames.iter_mut() {
+			for (channel, sample) in frame.iter_mut().enumerate() {
+				let mut x = f64::from(*sample);
+				for stage in stages.iter_mut() {
+					x = stage.process(channel, x);
+				}
+				*sample = x as f32;
+			}
+		}
+	}
+}
@@ -1,5 +1,22 @@
+use super::autosave::Autosave;
+use super::bursts;
+use super::curve;
+use super::curve::Curve;
+use super::curve::Curves;
+use super::events;
+use super::events::Recent;
+use super::locale::Locale;
+use super::lock;
+use super::lock::Locks;
 use super::params::Parameter;
 use super::params::Unit;
+use super::preset;
+use super::shared;
+use super::shared::SharedParams;
+use super::snapshot;
+use super::state;
+use super::warnings;
+use super::ConnectionPtr;
 use super::ContextPtr;
 use super::VstClassInfo;
 use crate::vst_result;
@@ -9,10 +26,12 @@ use hex_literal::hex;
 use log::*;
 use num_enum::TryFromPrimitive;
 use std::cell::RefCell;
+use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::mem::size_of;
 use std::os::raw::c_void;
 use std::ptr::null_mut;
+use std::sync::Arc;
 use vst3_com::sys::GUID;
 use vst3_com::ComPtr;
 use vst3_com::IID;
@@ -23,19 +42,40 @@ use vst3_sys::base::{
 	IPluginBase, IUnknown,
 };
 use vst3_sys::utils::VstPtr;
+use vst3_sys::vst::IConnectionPoint;
+use vst3_sys::vst::RestartFlags;
 use vst3_sys::vst::String128;
 use vst3_sys::vst::{
-	IComponentHandler, IEditController, IUnitInfo, ParameterInfo, ProgramListInfo, TChar, UnitInfo,
+	IComponentHandler, IComponentHandler2, IEditController, IUnitHandler, IUnitInfo, ParameterInfo,
+	ProgramListInfo, TChar, UnitInfo,
 };
 use vst3_sys::VST3;
 
 struct ComponentHandler(*mut c_void);
+struct ComponentHandler2(*mut c_void);
 
-#[VST3(implements(IEditController, IUnitInfo))]
+#[VST3(implements(IEditController, IUnitInfo, IConnectionPoint))]
 pub struct OpusController {
 	context: RefCell<ContextPtr>,
 	component_handler: RefCell<ComponentHandler>,
+	component_handler2: RefCell<ComponentHandler2>,
 	parameters: RefCell<EnumMap<Parameter, f64>>,
+	selected_unit: RefCell<Unit>,
+	locale: RefCell<Locale>,
+	peer: RefCell<ConnectionPtr>,
+	shared: RefCell<Option<Arc<SharedParams>>>,
+	autosave: RefCell<Option<Autosave>>,
+	curves: RefCell<Curves>,
+	/// Parameters the processor holds against automation
+	locks: RefCell<Locks>,
+	/// Kept by the processor with the state, shown as the only program
+	preset_name: RefCell<String>,
+	/// Sent by the processor, a line per problem
+	warnings: RefCell<String>,
+	/// Sent by the processor at debug verbosity, the latest last
+	events: RefCell<Recent>,
+	/// The codec class, showing only the encoder's parameters
+	codec_only: bool,
 }
 
 impl OpusController {
@@ -52,16 +92,275 @@ impl OpusController {
 		cardinality: ClassCardinality::kManyInstances as i32,
 	};
 
+	pub const CODEC_CID: IID = GUID {
+		data: hex!("b3a95e07f14d4c2a8e61d7c04f2b9a35"),
+	};
+
+	pub const CODEC_INFO: VstClassInfo = VstClassInfo {
+		cid: Self::CODEC_CID,
+		name: "Opus Parvulum Codec Controller",
+		category: "Component Controller Class",
+		subcategories: "",
+		class_flags: 0,
+		cardinality: ClassCardinality::kManyInstances as i32,
+	};
+
 	pub fn new() -> Box<Self> {
+		Self::build(false)
+	}
+
+	/// Controller for the codec-only class
+	pub fn new_codec() -> Box<Self> {
+		Self::build(true)
+	}
+
+	fn build(codec_only: bool) -> Box<Self> {
 		let context = RefCell::new(ContextPtr(null_mut()));
 		let component_handler = RefCell::new(ComponentHandler(null_mut()));
-		let parameters = RefCell::new(EnumMap::default());
-		OpusController::allocate(context, component_handler, parameters)
+		let component_handler2 = RefCell::new(ComponentHandler2(null_mut()));
+		// Hosts may read values before the first set_component_state
+		let mut defaults = EnumMap::<Parameter, f64>::default();
+		for (param, value) in defaults.iter_mut() {
+			*value = param.default_normalized();
+		}
+		let parameters = RefCell::new(defaults);
+		let selected_unit = RefCell::new(Unit::Root);
+		let locale = RefCell::new(Locale::English);
+		let peer = RefCell::new(ConnectionPtr(null_mut()));
+		let shared = RefCell::new(None);
+		let autosave = RefCell::new(None);
+		let curves = RefCell::new(Curves::default());
+		let locks = RefCell::new(Locks::default());
+		let preset_name = RefCell::new(String::new());
+		let warnings = RefCell::new(String::new());
+		let events = RefCell::new(Recent::new());
+		OpusController::allocate(
+			context,
+			component_handler,
+			component_handler2,
+			parameters,
+			selected_unit,
+			locale,
+			peer,
+			shared,
+			autosave,
+			curves,
+			locks,
+			preset_name,
+			warnings,
+			events,
+			codec_only,
+		)
 	}
 
 	pub fn create_instance() -> *mut c_void {
 		Box::into_raw(Self::new()) as *mut c_void
 	}
+
+	pub fn create_codec_instance() -> *mut c_void {
+		Box::into_raw(Self::new_codec()) as *mut c_void
+	}
+
+	/// Parameters shown to the host, in id order
+	fn exposed(&self) -> impl Iterator<Item = Parameter> {
+		let codec_only = self.codec_only;
+		(0..Parameter::VARIANT_COUNT as u32)
+			.filter_map(|id| Parameter::try_from_primitive(id).ok())
+			.filter(move |param| !codec_only || param.is_codec())
+	}
+
+	/// Write edits straight to the processor's store, or None to go through the host
+	pub fn set_shared_params(&self, shared: Option<Arc<SharedParams>>) {
+		*self.shared.borrow_mut() = shared;
+	}
+
+	unsafe fn restart_component(&self, flags: i32) -> tresult {
+		let handler = self.component_handler.borrow().0;
+		if handler.is_null() {
+			return kResultFalse;
+		}
+
+		let handler = handler as *mut *mut _;
+		let handler: ComPtr<dyn IComponentHandler> = ComPtr::new(handler);
+		handler.restart_component(flags)
+	}
+
+	/// Change a parameter as if the user did, so the host records it and passes it on
+	unsafe fn edit(&self, param: Parameter, value: f64) {
+		let id: u32 = param.into();
+		self.set_param_normalized(id, value);
+
+		let handler = self.component_handler.borrow().0;
+		if !handler.is_null() {
+			let handler: ComPtr<dyn IComponentHandler> = ComPtr::new(handler as *mut *mut _);
+			handler.begin_edit(id);
+			handler.perform_edit(id, value);
+			handler.end_edit(id);
+		}
+	}
+
+	/// Replace the current values with the crashed session's snapshot
+	unsafe fn restore_snapshot(&self) -> tresult {
+		let restored = match self.autosave.borrow_mut().as_mut() {
+			Some(autosave) => autosave.restore(),
+			None => None,
+		};
+
+		match restored {
+			Some(values) => {
+				for (param, value) in vst_result!(values).iter() {
+					if let Some(value) = value {
+						self.edit(param, *value);
+					}
+				}
+				info!("restore_snapshot() => kResultOk");
+			}
+			None => info!("restore_snapshot() nothing to restore"),
+		}
+
+		self.edit(Parameter::RestoreSnapshot, 0.0);
+		kResultOk
+	}
+
+	/// Step a parameter from a nudge button, then let the button spring back
+	unsafe fn nudge(&self, button: Parameter, param: Parameter, steps: f64) -> tresult {
+		let curve = self.curves.borrow()[param];
+		let plain = param.normalized_param_to_plain(self.get_param_normalized(param.into()), curve);
+		let value = param.plain_param_to_normalized(plain + steps, curve);
+		info!(
+			"nudge({:?}) {:?} from {} by {}",
+			button, param, plain, steps
+		);
+
+		self.edit(param, value);
+		self.edit(button, 0.0);
+		kResultOk
+	}
+
+	/// Tell the host the project needs saving, when it supports IComponentHandler2
+	unsafe fn set_dirty(&self) -> tresult {
+		let handler = self.component_handler2.borrow().0;
+		if handler.is_null() {
+			return kResultFalse;
+		}
+
+		let handler = handler as *mut *mut _;
+		let handler: ComPtr<dyn IComponentHandler2> = ComPtr::new(handler);
+		handler.set_dirty(1)
+	}
+
+	/// Bend a parameter, keeping its plain value where it was
+	unsafe fn set_curve(&self, param: Parameter, curve: Curve) -> tresult {
+		if !param.has_curve() {
+			warn!(
+				"set_curve({:?}) only continuous parameters have curves",
+				param
+			);
+			return kInvalidArgument;
+		}
+
+		let id: u32 = param.into();
+		let old = std::mem::replace(&mut vst_result!(self.curves.try_borrow_mut())[param], curve);
+		let plain = param.normalized_param_to_plain(self.get_param_normalized(id), old);
+
+		// The processor needs the curve before the moved value reaches it
+		let result = curve::send(self.context.borrow().0, self.peer.borrow().0, param, curve);
+		info!("set_curve({:?}, {:?}) => {}", param, curve, result);
+
+		self.edit(param, param.plain_param_to_normalized(plain, curve));
+		let flags =
+			RestartFlags::kParamValuesChanged as i32 | RestartFlags::kParamTitlesChanged as i32;
+		self.restart_component(flags);
+		kResultOk
+	}
+
+	/// Lock or unlock a parameter, the processor ignores automation of locked ones
+	unsafe fn set_lock(&self, param: Parameter, locked: bool) -> tresult {
+		vst_result!(self.locks.try_borrow_mut())[param] = locked;
+		let result = lock::send(self.context.borrow().0, self.peer.borrow().0, param, locked);
+		info!("set_lock({:?}, {}) => {}", param, locked, result);
+		kResultOk
+	}
+
+	/// Pass every lock to the processor, which starts without any
+	unsafe fn send_locks(&self) {
+		let locks = *self.locks.borrow();
+		for (param, locked) in locks.iter() {
+			if *locked {
+				lock::send(self.context.borrow().0, self.peer.borrow().0, param, true);
+			}
+		}
+	}
+
+	/// Rename the preset, the processor saves it with the state
+	unsafe fn set_preset_name(&self, name: String) -> tresult {
+		info!("set_preset_name({:?})", name);
+		*vst_result!(self.preset_name.try_borrow_mut()) = name.clone();
+
+		let result = preset::send(self.context.borrow().0, self.peer.borrow().0, &name);
+		info!("set_preset_name() sent to the processor => {}", result);
+
+		self.notify_program_list_change();
+		self.set_dirty();
+		kResultOk
+	}
+
+	/// Fetch what the processor recorded since the last pull
+	unsafe fn pull_events(&self) -> tresult {
+		let pulled = events::pull(self.context.borrow().0, self.peer.borrow().0);
+		let mut recent = vst_result!(self.events.try_borrow_mut());
+		for (time, event) in pulled {
+			debug!("pull_events() event at={:.3}s {:?}", time, event);
+			events::keep(&mut recent, time, event);
+		}
+		kResultOk
+	}
+
+	/// Tell the host the program's name changed, when it supports IUnitHandler
+	unsafe fn notify_program_list_change(&self) -> tresult {
+		let handler = self.component_handler.borrow().0;
+		if handler.is_null() {
+			return kResultFalse;
+		}
+
+		let handler: ComPtr<dyn IComponentHandler> = ComPtr::new(handler as *mut *mut _);
+		let mut iid = <dyn IUnitHandler as vst3_com::ComInterface>::IID;
+		let mut unit_handler = null_mut();
+		if handler.query_interface(&mut iid, &mut unit_handler) != kResultOk {
+			return kResultFalse;
+		}
+
+		let unit_handler: ComPtr<dyn IUnitHandler> = ComPtr::new(unit_handler as *mut *mut _);
+		let result = unit_handler.notify_program_list_change(preset::PROGRAM_LIST_ID, 0);
+		unit_handler.release();
+		result
+	}
+
+	/// Query IComponentHandler2 from the handler once, instead of on every use
+	unsafe fn cache_handler2(&self, handler: *mut c_void) {
+		self.release_handler2();
+		if handler.is_null() {
+			return;
+		}
+
+		let handler: ComPtr<dyn IComponentHandler> = ComPtr::new(handler as *mut *mut _);
+		let mut iid = <dyn IComponentHandler2 as vst3_com::ComInterface>::IID;
+		let mut handler2 = null_mut();
+		if handler.query_interface(&mut iid, &mut handler2) == kResultOk {
+			self.component_handler2.borrow_mut().0 = handler2;
+		} else {
+			info!("set_component_handler() host has no IComponentHandler2");
+		}
+	}
+
+	unsafe fn release_handler2(&self) {
+		let handler = self.component_handler2.borrow().0;
+		if !handler.is_null() {
+			let handler: ComPtr<dyn IComponentHandler2> = ComPtr::new(handler as *mut *mut _);
+			handler.release();
+			self.component_handler2.borrow_mut().0 = null_mut();
+		}
+	}
 }
 
 impl IEditController for OpusController {
@@ -76,41 +375,127 @@ impl IEditController for OpusController {
 
 		let state = state as *mut *mut _;
 		let state: ComPtr<dyn IBStream> = ComPtr::new(state);
-		let mut num_bytes_read = 0;
+		let chunk = vst_result!(state::read(&state));
 
-		for (_param, val) in params.iter_mut() {
-			let mut num = 0.0;
-			let ptr = &mut num as *mut f64 as *mut c_void;
-			state.read(ptr, size_of::<f64>() as i32, &mut num_bytes_read);
-			*val = num;
+		for (param, value) in chunk.values.iter() {
+			if let Some(value) = value {
+				params[param] = *value;
+			}
 		}
 
+		// The processor normalized these values along its curves
+		let curves = curve::decode(chunk.string(curve::STATE_KEY).unwrap_or(""));
+		*vst_result!(self.curves.try_borrow_mut()) = curves;
+
+		let name = preset::clean(chunk.string(preset::STATE_KEY).unwrap_or(""));
+		*vst_result!(self.preset_name.try_borrow_mut()) = name;
+		self.notify_program_list_change();
+
 		kResultOk
 	}
 
-	unsafe fn set_state(&self, _state: *mut c_void) -> tresult {
+	unsafe fn set_state(&self, state: *mut c_void) -> tresult {
 		info!("set_state()");
+
+		if state.is_null() {
+			return kResultFalse;
+		}
+
+		let state = state as *mut *mut _;
+		let state: ComPtr<dyn IBStream> = ComPtr::new(state);
+
+		let mut bytes = [0; size_of::<i32>()];
+		let num_bytes_read = state::read_bytes(&state, &mut bytes);
+
+		// Older sessions have no controller state
+		if num_bytes_read == bytes.len() {
+			match Unit::try_from_primitive(i32::from_ne_bytes(bytes)) {
+				Ok(unit) => *vst_result!(self.selected_unit.try_borrow_mut()) = unit,
+				Err(err) => warn!("set_state() {}", err),
+			}
+		}
+
+		// Sessions before curves end after the unit
+		let mut len = [0; size_of::<u32>()];
+		if state::read_bytes(&state, &mut len) == len.len() {
+			let mut bytes = vec![0; vst_result!(state::string_len(u32::from_ne_bytes(len)))];
+			if state::read_bytes(&state, &mut bytes) == bytes.len() {
+				let curves = curve::decode(&String::from_utf8_lossy(&bytes));
+				*vst_result!(self.curves.try_borrow_mut()) = curves;
+			}
+		}
+
+		// Sessions before locks end after the curves
+		let mut locks = Locks::default();
+		let mut len = [0; size_of::<u32>()];
+		if state::read_bytes(&state, &mut len) == len.len() {
+			let mut bytes = vec![0; vst_result!(state::string_len(u32::from_ne_bytes(len)))];
+			if state::read_bytes(&state, &mut bytes) == bytes.len() {
+				locks = lock::decode(&String::from_utf8_lossy(&bytes));
+			}
+		}
+		let old = std::mem::replace(&mut *vst_result!(self.locks.try_borrow_mut()), locks);
+		for (param, locked) in locks.iter() {
+			if old[param] != *locked {
+				lock::send(
+					self.context.borrow().0,
+					self.peer.borrow().0,
+					param,
+					*locked,
+				);
+			}
+		}
+
 		kResultOk
 	}
 
-	unsafe fn get_state(&self, _state: *mut c_void) -> tresult {
+	unsafe fn get_state(&self, state: *mut c_void) -> tresult {
 		info!("get_state()");
+
+		if state.is_null() {
+			return kResultFalse;
+		}
+
+		let state = state as *mut *mut _;
+		let state: ComPtr<dyn IBStream> = ComPtr::new(state);
+
+		let id: i32 = (*vst_result!(self.selected_unit.try_borrow())).into();
+		vst_result!(state::write_bytes(&state, &id.to_ne_bytes()));
+
+		let curves = curve::encode(&*vst_result!(self.curves.try_borrow()));
+		vst_result!(state::write_bytes(
+			&state,
+			&(curves.len() as u32).to_ne_bytes()
+		));
+		vst_result!(state::write_bytes(&state, curves.as_bytes()));
+
+		let locks = lock::encode(&*vst_result!(self.locks.try_borrow()));
+		vst_result!(state::write_bytes(
+			&state,
+			&(locks.len() as u32).to_ne_bytes()
+		));
+		vst_result!(state::write_bytes(&state, locks.as_bytes()));
+
 		kResultOk
 	}
 
 	unsafe fn get_parameter_count(&self) -> i32 {
 		info!("get_parameter_count()");
-		Parameter::VARIANT_COUNT.try_into().unwrap()
+		self.exposed().count().try_into().unwrap()
 	}
 
-	unsafe fn get_parameter_info(&self, id: i32, info: *mut ParameterInfo) -> tresult {
-		match Parameter::try_from_primitive(id as u32) {
-			Ok(param) => {
-				*info = param.get_parameter_info();
+	unsafe fn get_parameter_info(&self, index: i32, info: *mut ParameterInfo) -> tresult {
+		let param = usize::try_from(index)
+			.ok()
+			.and_then(|index| self.exposed().nth(index));
+		match param {
+			Some(param) => {
+				*info =
+					param.get_parameter_info(*self.locale.borrow(), self.curves.borrow()[param]);
 				kResultTrue
 			}
-			Err(err) => {
-				error!("get_parameter_info({}) {}", id, err);
+			None => {
+				error!("get_parameter_info({}) no such parameter", index);
 				kInvalidArgument
 			}
 		}
@@ -123,7 +508,18 @@ impl IEditController for OpusController {
 		match Parameter::try_from_primitive(id) {
 			Ok(param) => {
 				//
-				match param.get_param_string_by_value(value) {
+				// The flag shows the first warning, so hosts without an editor still show it
+				let warnings = self.warnings.borrow();
+				if let (Parameter::Warning, Some(line)) = (param, warnings.lines().next()) {
+					if value > 0.5 {
+						*string = vst_str::str_16(line);
+						return kResultTrue;
+					}
+				}
+				drop(warnings);
+
+				let curve = self.curves.borrow()[param];
+				match param.get_param_string_by_value(value, *self.locale.borrow(), curve) {
 					Some(new_string) => {
 						*string = vst_str::str_16(&new_string);
 						kResultTrue
@@ -151,7 +547,8 @@ impl IEditController for OpusController {
 		match Parameter::try_from_primitive(id) {
 			Ok(param) => {
 				//
-				match param.get_param_value_by_string(&string) {
+				let curve = self.curves.borrow()[param];
+				match param.get_param_value_by_string(&string, *self.locale.borrow(), curve) {
 					Some(new_value) => {
 						*value = new_value;
 						kResultTrue
@@ -168,14 +565,16 @@ impl IEditController for OpusController {
 
 	unsafe fn normalized_param_to_plain(&self, id: u32, value_normalized: f64) -> f64 {
 		match Parameter::try_from_primitive(id) {
-			Ok(param) => param.normalized_param_to_plain(value_normalized),
+			Ok(param) => {
+				param.normalized_param_to_plain(value_normalized, self.curves.borrow()[param])
+			}
 			_ => value_normalized,
 		}
 	}
 
 	unsafe fn plain_param_to_normalized(&self, id: u32, plain_value: f64) -> f64 {
 		match Parameter::try_from_primitive(id) {
-			Ok(param) => param.plain_param_to_normalized(plain_value),
+			Ok(param) => param.plain_param_to_normalized(plain_value, self.curves.borrow()[param]),
 			_ => plain_value,
 		}
 	}
@@ -199,7 +598,46 @@ impl IEditController for OpusController {
 				//
 				match self.parameters.try_borrow_mut() {
 					Ok(mut params) => {
+						let changed = params[param] != value;
 						params[param] = value;
+						if changed && param.is_saved() {
+							if let Some(autosave) = self.autosave.borrow_mut().as_mut() {
+								autosave.save(&params);
+							}
+						}
+						drop(params);
+
+						if let Some(shared) = &*self.shared.borrow() {
+							shared.set(param, value);
+						}
+
+						// The processor reports latency changes through this output parameter
+						if changed && matches!(param, Parameter::Latency) {
+							info!("set_param_normalized() latency changed");
+							self.restart_component(RestartFlags::kLatencyChanged as i32);
+						}
+
+						// Restoring is an action, the toggle springs back once done
+						if matches!(param, Parameter::RestoreSnapshot) && value > 0.5 {
+							return self.restore_snapshot();
+						}
+
+						// So are nudges
+						if let Some((target, steps)) = param.nudge().filter(|_| value > 0.5) {
+							return self.nudge(param, target, steps);
+						}
+
+						// The processor changed its own parameters, like the governor lowering complexity
+						if changed && matches!(param, Parameter::InternalEdits) {
+							info!("set_param_normalized() internal edit");
+							self.set_dirty();
+						}
+
+						// The processor recorded events, which wait in its ring until pulled
+						if changed && matches!(param, Parameter::RecordedEvents) {
+							return self.pull_events();
+						}
+
 						kResultOk
 					}
 					Err(err) => {
@@ -234,6 +672,7 @@ impl IEditController for OpusController {
 			let component_handler: ComPtr<dyn IComponentHandler> = ComPtr::new(component_handler);
 			component_handler.add_ref();
 		}
+		self.cache_handler2(handler);
 
 		kResultTrue
 	}
@@ -253,6 +692,18 @@ impl IPluginBase for OpusController {
 		}
 		self.context.borrow_mut().0 = context;
 
+		let locale = Locale::detect();
+		info!("initialize() locale {:?}", locale);
+		*self.locale.borrow_mut() = locale;
+
+		if cfg!(feature = "autosave") {
+			let autosave = Autosave::new();
+			if autosave.has_recovered() {
+				warn!("initialize() a crashed session left a snapshot, set Restore Snapshot to load it");
+			}
+			*self.autosave.borrow_mut() = Some(autosave);
+		}
+
 		kResultOk
 	}
 
@@ -265,6 +716,10 @@ impl IPluginBase for OpusController {
 			component_handler.release();
 			self.component_handler.borrow_mut().0 = null_mut();
 		}
+		self.release_handler2();
+		if let Some(mut autosave) = self.autosave.borrow_mut().take() {
+			autosave.remove();
+		}
 		self.context.borrow_mut().0 = null_mut();
 
 		kResultOk
@@ -274,10 +729,17 @@ impl IPluginBase for OpusController {
 impl IUnitInfo for OpusController {
 	unsafe fn get_unit_count(&self) -> i32 {
 		info!("get_unit_count()");
+		if self.codec_only {
+			// Root and Encoder come first
+			return i32::from(Unit::Encoder) + 1;
+		}
 		Unit::VARIANT_COUNT.try_into().unwrap()
 	}
 
 	unsafe fn get_unit_info(&self, unit_index: i32, info: *mut UnitInfo) -> tresult {
+		if unit_index >= self.get_unit_count() {
+			return kInvalidArgument;
+		}
 		match Unit::try_from_primitive(unit_index) {
 			Ok(unit) => {
 				(*info) = unit.get_info();
@@ -289,17 +751,31 @@ impl IUnitInfo for OpusController {
 
 	unsafe fn get_program_list_count(&self) -> i32 {
 		info!("get_program_list_count()");
-		0
+		1
 	}
 
-	unsafe fn get_program_list_info(&self, _list_index: i32, _info: *mut ProgramListInfo) -> i32 {
-		info!("get_program_list_info()");
-		kResultFalse
+	unsafe fn get_program_list_info(&self, list_index: i32, info: *mut ProgramListInfo) -> i32 {
+		info!("get_program_list_info({})", list_index);
+		if list_index != 0 || info.is_null() {
+			return kInvalidArgument;
+		}
+		(*info) = ProgramListInfo {
+			id: preset::PROGRAM_LIST_ID,
+			name: vst_str::str_16("Preset"),
+			program_count: 1,
+		};
+		kResultOk
 	}
 
-	unsafe fn get_program_name(&self, _list_id: i32, _program_index: i32, _name: *mut u16) -> i32 {
-		info!("get_program_name()");
-		kResultFalse
+	unsafe fn get_program_name(&self, list_id: i32, program_index: i32, name: *mut u16) -> i32 {
+		info!("get_program_name({}, {})", list_id, program_index);
+		if list_id != preset::PROGRAM_LIST_ID || program_index != 0 || name.is_null() {
+			return kInvalidArgument;
+		}
+		// Borrow pointer as String128, because that's the actual type in the SDK
+		let name = &mut *(name as *mut String128);
+		*name = vst_str::str_16(preset::display(&self.preset_name.borrow()));
+		kResultOk
 	}
 
 	unsafe fn get_program_info(
@@ -331,12 +807,24 @@ impl IUnitInfo for OpusController {
 
 	unsafe fn get_selected_unit(&self) -> i32 {
 		info!("get_selected_unit()");
-		0
+		match self.selected_unit.try_borrow() {
+			Ok(unit) => (*unit).into(),
+			_ => Unit::Root.into(),
+		}
 	}
 
-	unsafe fn select_unit(&self, _id: i32) -> i32 {
-		info!("select_unit()");
-		kResultFalse
+	unsafe fn select_unit(&self, id: i32) -> i32 {
+		info!("select_unit({})", id);
+		match Unit::try_from_primitive(id) {
+			Ok(unit) => {
+				*vst_result!(self.selected_unit.try_borrow_mut()) = unit;
+				kResultOk
+			}
+			Err(err) => {
+				error!("select_unit({}) {}", id, err);
+				kInvalidArgument
+			}
+		}
 	}
 
 	unsafe fn get_unit_by_bus(
@@ -361,3 +849,87 @@ impl IUnitInfo for OpusController {
 		kResultFalse
 	}
 }
+
+impl IConnectionPoint for OpusController {
+	unsafe fn connect(&self, other: *mut c_void) -> tresult {
+		info!("connect()");
+
+		if other.is_null() {
+			return kInvalidArgument;
+		}
+		self.peer.borrow_mut().0 = other;
+		self.send_locks();
+
+		kResultOk
+	}
+
+	unsafe fn disconnect(&self, other: *mut c_void) -> tresult {
+		info!("disconnect()");
+
+		if self.peer.borrow().0 != other {
+			return kResultFalse;
+		}
+		self.peer.borrow_mut().0 = null_mut();
+		self.set_shared_params(None);
+
+		kResultOk
+	}
+
+	unsafe fn notify(&self, message: *mut c_void) -> tresult {
+		if crate::about::answer(message) {
+			info!("notify() answered an about request");
+			return kResultOk;
+		}
+
+		// From an editor, the controller keeps the curve and passes it on
+		if let Some((param, curve)) = curve::receive(message) {
+			return self.set_curve(param, curve);
+		}
+
+		// From an editor, the controller keeps the lock with its state
+		if let Some((param, locked)) = lock::receive(message) {
+			return self.set_lock(param, locked);
+		}
+
+		if let Some(name) = preset::receive(message) {
+			return self.set_preset_name(name);
+		}
+
+		if let Some(text) = warnings::receive(message) {
+			info!("notify() warnings {:?}", text);
+			*vst_result!(self.warnings.try_borrow_mut()) = text;
+			self.restart_component(RestartFlags::kParamValuesChanged as i32);
+			return kResultOk;
+		}
+
+		// A debug panel or another tool asks for the latest events
+		if events::answer(message, &*vst_result!(self.events.try_borrow())) {
+			return kResultOk;
+		}
+
+		// Only recorded, for whoever collects the log of a render
+		if let Some(text) = snapshot::receive(message) {
+			info!("notify() render settings\n{}", text);
+			return kResultOk;
+		}
+
+		// The histogram lives in the processor, which fills in the request
+		if bursts::is_request(message) {
+			let peer = self.peer.borrow().0;
+			if peer.is_null() {
+				return kResultFalse;
+			}
+			let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+			return peer.notify(message);
+		}
+
+		match shared::receive_handshake(message) {
+			Some(store) => {
+				info!("notify() sharing parameters with the processor");
+				self.set_shared_params(Some(store));
+				kResultOk
+			}
+			None => kResultFalse,
+		}
+	}
+}
@@ -0,0 +1,156 @@
+use super::wav;
+use crate::error::Error;
+use crate::error::Result;
+use rustfft::num_complex::Complex;
+use rustfft::Fft;
+use rustfft::FftPlanner;
+use std::sync::Arc;
+
+/// Longest impulse response kept, in seconds
+pub const MAX_IMPULSE_SECS: f64 = 0.05;
+
+/// Impulse response at the rate of its file
+pub struct Impulse {
+	frames: Vec<[f32; 2]>,
+	rate: f64,
+}
+
+impl Impulse {
+	/// Read a WAV file, mono is used for both channels and channels past the second are ignored
+	pub fn open(path: &str) -> Result<Self> {
+		let error = |err: hound::Error| Error::Impulse(format!("{}: {}", path, err));
+		let wav = wav::read(path, Some(MAX_IMPULSE_SECS)).map_err(error)?;
+		if wav.frames.is_empty() {
+			return Err(Error::Impulse(format!("{}: no samples", path)));
+		}
+
+		Ok(Self {
+			frames: wav.frames,
+			rate: wav.rate,
+		})
+	}
+
+	/// Linear interpolation is plenty for a response this short
+	fn resampled(&self, rate: f64) -> Vec<[f32; 2]> {
+		let ratio = self.rate / rate;
+		let len = ((self.frames.len() as f64 / ratio) as usize).max(1);
+		let len = len.min((rate * MAX_IMPULSE_SECS).ceil() as usize);
+		let last = self.frames.len() - 1;
+		(0..len)
+			.map(|i| {
+				let position = i as f64 * ratio;
+				let index = (position as usize).min(last);
+				let next = (index + 1).min(last);
+				let t = (position - index as f64) as f32;
+				let (a, b) = (self.frames[index], self.frames[next]);
+				[a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+			})
+			.collect()
+	}
+}
+
+/// Uniformly partitioned overlap-save convolution, one block per packet,
+/// so whole packets go through without added latency
+pub struct Convolver {
+	block: usize,
+	forward: Arc<dyn Fft<f32>>,
+	inverse: Arc<dyn Fft<f32>>,
+	scratch: Vec<Complex<f32>>,
+	/// Spectrum of each impulse partition, per channel
+	partitions: [Vec<Vec<Complex<f32>>>; 2],
+	/// Spectra of past input blocks, newest at `head`
+	history: [Vec<Vec<Complex<f32>>>; 2],
+	head: usize,
+	/// Previous and current input block
+	input: [Vec<f32>; 2],
+	buffer: Vec<Complex<f32>>,
+}
+
+impl Convolver {
+	/// Allocates everything up front for blocks of `block` frames at `rate`
+	pub fn new(impulse: &Impulse, rate: f64, block: usize) -> Self {
+		let size = 2 * block;
+		let mut planner = FftPlanner::new();
+		let forward = planner.plan_fft_forward(size);
+		let inverse = planner.plan_fft_inverse(size);
+		let scratch_len = forward
+			.get_inplace_scratch_len()
+			.max(inverse.get_inplace_scratch_len());
+		let mut scratch = vec![Complex::default(); scratch_len];
+
+		let frames = impulse.resampled(rate);
+		let count = frames.len().div_ceil(block);
+		let mut partitions = [Vec::with_capacity(count), Vec::with_capacity(count)];
+		for part in frames.chunks(block) {
+			for (channel, spectra) in partitions.iter_mut().enumerate() {
+				let mut spectrum = vec![Complex::default(); size];
+				for (bin, frame) in spectrum.iter_mut().zip(part) {
+					bin.re = frame[channel];
+				}
+				forward.process_with_scratch(&mut spectrum, &mut scratch);
+				spectra.push(spectrum);
+			}
+		}
+
+		let history = vec![vec![Complex::default(); size]; count];
+		Self {
+			block,
+			forward,
+			inverse,
+			scratch,
+			partitions,
+			history: [history.clone(), history],
+			head: 0,
+			input: [vec![0.0; size], vec![0.0; size]],
+			buffer: vec![Complex::default(); size],
+		}
+	}
+
+	/// Frames convolved at a time
+	pub fn block(&self) -> usize {
+		self.block
+	}
+
+	/// Convolve one block in place, other lengths pass through
+	pub fn process(&mut self, frames: &mut [[f32; 2]]) {
+		let block = self.block;
+		if frames.len() != block || self.history[0].is_empty() {
+			return;
+		}
+		let count = self.history[0].len();
+		self.head = (self.head + count - 1) % count;
+		let head = self.head;
+		let scale = 1.0 / (2 * block) as f32;
+
+		for (channel, input) in self.input.iter_mut().enumerate() {
+			input.copy_within(block.., 0);
+			for (sample, frame) in input[block..].iter_mut().zip(frames.iter()) {
+				*sample = frame[channel];
+			}
+
+			let spectrum = &mut self.history[channel][head];
+			for (bin, sample) in spectrum.iter_mut().zip(input.iter()) {
+				*bin = Complex::new(*sample, 0.0);
+			}
+			self.forward
+				.process_with_scratch(spectrum, &mut self.scratch);
+
+			for bin in self.buffer.iter_mut() {
+				*bin = Complex::default();
+			}
+			for (p, partition) in self.partitions[channel].iter().enumerate() {
+				let past = &self.history[channel][(head + p) % count];
+				for ((out, x), h) in self.buffer.iter_mut().zip(past).zip(partition) {
+					*out += x * h;
+				}
+			}
+			self.inverse
+				.process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+			// The first half wrapped around, the second half is the new block
+			for (frame, bin) in frames.iter_mut().zip(&self.buffer[block..]) {
+				frame[channel] = bin.re * scale;
+			}
+		}
+	}
+}
@@ -0,0 +1,191 @@
+use super::params::Parameter;
+use enum_map::EnumMap;
+use log::*;
+use num_enum::IntoPrimitive;
+use num_enum::TryFromPrimitive;
+use std::convert::TryFrom;
+#[cfg(feature = "vst3")]
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
+use std::ptr::null_mut;
+#[cfg(feature = "vst3")]
+use vst3_com::{ComInterface, ComPtr};
+#[cfg(feature = "vst3")]
+use vst3_sys::base::{kResultFalse, kResultOk, tresult};
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IConnectionPoint, IHostApplication, IMessage};
+
+/// How bent the log and exp curves are, higher spends more travel at one end
+const STEEPNESS: f64 = 15.0;
+
+/// How a continuous parameter travels its range as the normalized value moves
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum Curve {
+	#[default]
+	Linear,
+	/// Fine control at the low end, like a log taper pot
+	Log,
+	/// Fine control at the high end
+	Exp,
+	/// Fine control at both ends
+	Smoothstep,
+}
+
+/// Curve of every parameter, only continuous ones are ever bent
+pub type Curves = EnumMap<Parameter, Curve>;
+
+impl Curve {
+	/// Position in the range for a normalized value. Unlike clamp, max and min land a
+	/// NaN from the host on 0
+	#[allow(clippy::manual_clamp)]
+	pub fn shape(self, x: f64) -> f64 {
+		let x = x.max(0.0).min(1.0);
+		match self {
+			Self::Linear => x,
+			Self::Log => ((1.0 + STEEPNESS).powf(x) - 1.0) / STEEPNESS,
+			Self::Exp => (1.0 + STEEPNESS * x).ln() / (1.0 + STEEPNESS).ln(),
+			Self::Smoothstep => x * x * (3.0 - 2.0 * x),
+		}
+	}
+
+	/// Normalized value for a position in the range, the inverse of shape
+	#[allow(clippy::manual_clamp)]
+	pub fn unshape(self, y: f64) -> f64 {
+		let y = y.max(0.0).min(1.0);
+		match self {
+			Self::Linear => y,
+			Self::Log => (1.0 + STEEPNESS * y).ln() / (1.0 + STEEPNESS).ln(),
+			Self::Exp => ((1.0 + STEEPNESS).powf(y) - 1.0) / STEEPNESS,
+			Self::Smoothstep => 0.5 - ((1.0 - 2.0 * y).asin() / 3.0).sin(),
+		}
+	}
+}
+
+/// Key of the curves in saved state
+pub const STATE_KEY: &str = "curves";
+
+/// Bent curves as "id:curve" pairs, linear ones are left out
+pub fn encode(curves: &Curves) -> String {
+	curves
+		.iter()
+		.filter(|(_, curve)| **curve != Curve::Linear)
+		.map(|(param, curve)| format!("{}:{}", u32::from(param), u8::from(*curve)))
+		.collect::<Vec<_>>()
+		.join(",")
+}
+
+/// Unknown parameters and curves are skipped, so newer sessions still load
+pub fn decode(string: &str) -> Curves {
+	let mut curves = Curves::default();
+	for pair in string.split(',').filter(|pair| !pair.is_empty()) {
+		let mut parts = pair.splitn(2, ':');
+		let param = parts.next().and_then(|id| id.parse::<u32>().ok());
+		let curve = parts.next().and_then(|curve| curve.parse::<u8>().ok());
+		match (param.map(Parameter::try_from), curve.map(Curve::try_from)) {
+			(Some(Ok(param)), Some(Ok(curve))) if param.has_curve() => curves[param] = curve,
+			_ => warn!("curves: skipped {:?}", pair),
+		}
+	}
+	curves
+}
+
+const MESSAGE_ID: &[u8] = b"OpusCurve\0";
+const PARAM_ATTRIBUTE_ID: &[u8] = b"id\0";
+const CURVE_ATTRIBUTE_ID: &[u8] = b"curve\0";
+
+/// Tell the peer a parameter's curve changed
+#[cfg(feature = "vst3")]
+pub unsafe fn send(
+	context: *mut c_void,
+	peer: *mut c_void,
+	param: Parameter,
+	curve: Curve,
+) -> tresult {
+	if context.is_null() || peer.is_null() {
+		return kResultFalse;
+	}
+
+	let host: ComPtr<dyn IHostApplication> = ComPtr::new(context as *mut *mut _);
+	let mut cid = <dyn IMessage as ComInterface>::IID;
+	let mut iid = <dyn IMessage as ComInterface>::IID;
+	let mut message = null_mut();
+	let result = host.create_instance(&mut cid, &mut iid, &mut message);
+	if result != kResultOk || message.is_null() {
+		warn!("curves: host can't create messages");
+		return result;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	message.set_message_id(MESSAGE_ID.as_ptr() as *const _);
+
+	let attributes = message.get_attributes();
+	if !attributes.is_null() {
+		let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+		let id = PARAM_ATTRIBUTE_ID.as_ptr() as *const _;
+		attributes.set_int(id, i64::from(u32::from(param)));
+		let id = CURVE_ATTRIBUTE_ID.as_ptr() as *const _;
+		attributes.set_int(id, i64::from(u8::from(curve)));
+	}
+
+	let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+	let result = peer.notify(message.as_raw() as *mut c_void);
+	message.release();
+	result
+}
+
+/// The parameter and curve from a curve message, None for any other message
+#[cfg(feature = "vst3")]
+pub unsafe fn receive(message: *mut c_void) -> Option<(Parameter, Curve)> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut param = 0;
+	let mut curve = 0;
+	let id = PARAM_ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_int(id, &mut param) != kResultOk {
+		return None;
+	}
+	let id = CURVE_ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_int(id, &mut curve) != kResultOk {
+		return None;
+	}
+
+	let param = Parameter::try_from(u32::try_from(param).ok()?).ok()?;
+	let curve = Curve::try_from(u8::try_from(curve).ok()?).ok()?;
+	Some((param, curve))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn unshape_inverts_shape() {
+		for curve in [Curve::Linear, Curve::Log, Curve::Exp, Curve::Smoothstep].iter() {
+			for i in 0..=20 {
+				let x = i as f64 / 20.0;
+				assert!(
+					(curve.unshape(curve.shape(x)) - x).abs() < 1e-9,
+					"{:?} {}",
+					curve,
+					x
+				);
+			}
+		}
+	}
+}
@@ -1,31 +1,98 @@
+use super::adaptive::AdaptiveBitrate;
+use super::bursts::BurstHistogram;
+use super::channels::ChannelTools;
+use super::clean::CleanFeed;
+use super::coloration::Coloration;
+use super::curve::Curves;
+use super::duplex::HalfDuplex;
+use super::echo::Echo;
+use super::effective::EffectiveConfig;
+use super::events::Event;
+use super::events::EventLog;
+use super::fade::Fade;
+use super::handset::Handset;
+use super::levels::LevelWatch;
+use super::link::Link;
+use super::lock::Locks;
+use super::loss;
+use super::loss::Euclidean;
+use super::loss::LossCause;
+use super::loss::LossCounts;
+use super::loss::RandomStream;
+use super::loss::RoundRobin;
+use super::loss::StepPattern;
+use super::monitor::Monitor;
+use super::monitor::TAP_DECODED;
+use super::monitor::TAP_INPUT;
+use super::noise::NoiseMix;
+use super::null::NullCheck;
 use super::params::Parameter;
-use anyhow::ensure;
-use anyhow::Result;
+use super::params::BANDWIDTHS;
+use super::pool::Packet;
+use super::pool::PacketPool;
+use super::reference::Reference;
+use super::resampler::Resampler;
+use super::stutter::FreezeSync;
+use super::stutter::Stutter;
+use super::timeline::Timeline;
+use super::warnings;
+use super::warnings::Warnings;
+use super::warp::TimeWarp;
+use super::watchdog::Watchdog;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sys::{kResultTrue, IParamValueQueue, ProcessContext, ProcessSetup};
 use audiopus::coder::Decoder;
 use audiopus::coder::Encoder;
 use audiopus::Application;
+use audiopus::Bandwidth;
+use audiopus::Bitrate;
 use audiopus::Channels;
 use audiopus::SampleRate;
+use audiopus::Signal as OpusSignal;
 use dasp::frame::Stereo;
-use dasp::interpolate::linear::Linear;
-use dasp::signal::interpolate::Converter;
 use dasp::Frame;
 use dasp::Signal;
 use enum_map::EnumMap;
 use log::*;
-use rand::prelude::*;
+#[cfg(feature = "vst3")]
 use std::convert::TryFrom;
-use std::slice;
-use vst3_sys::vst::ProcessData;
-use vst3_sys::vst::ProcessSetup;
-use vst3_sys::{
-	base::kResultTrue,
-	utils::VstPtr,
-	vst::{IParamValueQueue, IParameterChanges},
-};
+use std::ops::Range;
+use std::time::Duration;
+#[cfg(feature = "vst3")]
+use vst3_sys::{utils::VstPtr, vst::IParameterChanges};
+
+/// Aux inputs, each only when the host connected something to it
+#[derive(Copy, Clone, Default)]
+pub struct AuxInputs<'a> {
+	/// Mixed into the encoder input
+	pub noise: Option<[&'a [f32]; 2]>,
+	/// Drives the half-duplex gate
+	pub far_end: Option<[&'a [f32]; 2]>,
+	/// Delayed by the latency, for comparing against the output
+	pub reference: Option<[&'a [f32]; 2]>,
+	/// Level stands in for the available bandwidth
+	pub bandwidth: Option<[&'a [f32]; 2]>,
+}
+
+impl<'a> AuxInputs<'a> {
+	/// The frames in `range` of each connected input
+	pub fn slice(self, range: Range<usize>) -> Self {
+		let slice = |bus: Option<[&'a [f32]; 2]>| {
+			bus.map(|[left, right]| [&left[range.clone()], &right[range.clone()]])
+		};
+		Self {
+			noise: slice(self.noise),
+			far_end: slice(self.far_end),
+			reference: slice(self.reference),
+			bandwidth: slice(self.bandwidth),
+		}
+	}
+}
 
 pub type ParamQueueMap = EnumMap<Parameter, Option<Box<dyn IParamValueQueue>>>;
 
+#[cfg(feature = "vst3")]
 pub unsafe fn upgrade_param_changes(ptr: &VstPtr<dyn IParameterChanges>) -> ParamQueueMap {
 	let mut param_changes_map = ParamQueueMap::default();
 
@@ -48,63 +115,348 @@ pub unsafe fn upgrade_param_changes(ptr: &VstPtr<dyn IParameterChanges>) -> Para
 	param_changes_map
 }
 
-mod buffer_signal {
-	use dasp::frame::Stereo;
-	use dasp::interpolate::linear::Linear;
-	use dasp::signal::interpolate::Converter;
-	use dasp::Frame;
-	use dasp::Signal;
-	use std::collections::VecDeque;
-
-	pub struct BufferSignal<F: Frame>(VecDeque<F>);
-
-	impl<F: Frame> BufferSignal<F> {
-		pub fn push(&mut self, elem: F) {
-			self.0.push_back(elem);
-		}
-
-		pub fn push_slice(&mut self, slice: &[F]) {
-			self.0.extend(slice);
-		}
+/// Where the parameter ends up after the block
+pub unsafe fn last_point(queue: &dyn IParamValueQueue) -> Option<f64> {
+	let num_points = queue.get_point_count();
+	let mut offset = 0;
+	let mut value = 0.0;
+	if num_points > 0 && queue.get_point(num_points - 1, &mut offset, &mut value) == kResultTrue {
+		Some(value)
+	} else {
+		None
 	}
+}
 
-	impl<F: Frame> Signal for BufferSignal<F> {
-		type Frame = F;
-
-		fn next(&mut self) -> F {
-			self.0.pop_front().unwrap_or(F::EQUILIBRIUM)
-		}
+/// Any point in the queue turns a button on between the two offsets
+pub unsafe fn pressed_between(queue: &dyn IParamValueQueue, from: usize, to: usize) -> bool {
+	let mut offset = 0;
+	let mut value = 0.0;
+	(0..queue.get_point_count()).any(|i| {
+		queue.get_point(i, &mut offset, &mut value) == kResultTrue
+			&& (from..to).contains(&(offset as usize))
+			&& value > 0.5
+	})
+}
 
-		fn is_exhausted(&self) -> bool {
-			self.0.is_empty()
+#[cfg(feature = "vst3")]
+pub unsafe fn write_output_param(
+	ptr: &VstPtr<dyn IParameterChanges>,
+	param: Parameter,
+	value: f64,
+) {
+	if let Some(param_changes) = ptr.upgrade() {
+		let id: u32 = param.into();
+		let mut index = 0;
+		if let Some(param_queue) = param_changes.add_parameter_data(&id, &mut index).upgrade() {
+			let mut point = 0;
+			param_queue.add_point(0, value, &mut point);
 		}
 	}
-
-	pub fn new(
-		source_hz: f64,
-		target_hz: f64,
-	) -> Converter<BufferSignal<Stereo<f32>>, Linear<Stereo<f32>>> {
-		let buffer = VecDeque::new();
-		let interpolator = Linear::new(Stereo::EQUILIBRIUM, Stereo::EQUILIBRIUM);
-		BufferSignal(buffer).from_hz_to_hz(interpolator, source_hz, target_hz)
-	}
 }
 
 pub struct OpusDSP {
 	sample_rate: f64,
-	insignal: Converter<buffer_signal::BufferSignal<Stereo<f32>>, Linear<Stereo<f32>>>,
-	outsignal: Converter<buffer_signal::BufferSignal<Stereo<f32>>, Linear<Stereo<f32>>>,
-	rng: ThreadRng,
+	reported_latency: usize,
+	insignal: Resampler,
+	/// Noise bus at the coder rate, kept in step with the input
+	noisesignal: Resampler,
+	farsignal: Resampler,
+	outsignal: Resampler,
+	/// Draws for random loss
+	random: RandomStream,
+	round_robin: RoundRobin,
+	/// Seed for this instance when the user leaves the seed at random
+	instance_seed: u64,
+	/// Processing offline, where quality beats speed
+	offline: bool,
+	/// Best resampler and interpolated parameters
+	high_quality: bool,
+	/// Not realtime, offline or prefetch, where the offline complexity applies
+	rendering: bool,
+	/// Random loss uses a fixed seed, when the user left it at random
+	deterministic: bool,
+	stutter: Stutter,
+	/// Snaps freeze to the host's grid
+	pub freeze_sync: FreezeSync,
+	/// Coders failed or need a new layer, a spare or `rebuild()` replaces them
+	coders_stale: bool,
+	scratch: Scratch,
+	/// Rate the coders run at
+	opus_rate: SampleRate,
+	/// Index into FRAME_DURATIONS the packets are cut at
+	duration: usize,
+	/// Index into FRAME_DURATIONS chosen by the user, applied at the start of the next block
+	pub frame_duration: usize,
+	/// Encoder lookahead in frames at the coder rate
+	lookahead: usize,
+	/// Packets held past the block they were encoded in
+	packets: PacketPool,
+	/// Packet waiting to be decoded when decoding FEC
+	previous: Option<Packet>,
+	previous_lost: bool,
+	/// Packets dropped because they didn't fit the packet buffer
+	oversized: u32,
+	/// Failure was logged, stays quiet until the codec recovers
+	failing: bool,
+	pub watchdog: Watchdog,
+	/// Concealed packets by cause
+	pub losses: LossCounts,
+	/// Runs of consecutive concealed packets by length
+	pub bursts: BurstHistogram,
+	/// Input levels the encoder won't handle well
+	pub levels: LevelWatch,
+	/// What the encoder chose, as opposed to what it was asked for
+	pub effective: EffectiveConfig,
+	/// Host time of the current block, for timestamps in the logs
+	timeline: Timeline,
+	/// Counts changes the DSP made to its own parameters, which the project should save
+	pub internal_edits: u32,
+	/// Lower complexity when the watchdog trips
+	pub governor: bool,
+	/// Encoder complexity chosen by the user
+	pub complexity: u8,
+	/// Encoder complexity for rendering, so bounces can be heavier than live monitoring
+	pub offline_complexity: u8,
+	/// Use high quality settings when offline, which can be turned off
+	pub offline_quality: bool,
+	/// Coder rate chosen by the user, or None to follow the host
+	pub internal_rate: Option<SampleRate>,
+	/// Code at 24 kHz when following a host below 16 kHz, instead of refusing it
+	pub low_rate_fallback: bool,
+	/// Bandwidth chosen by the user, the SILK layer may hold the encoder below it
+	pub max_bandwidth: Bandwidth,
+	/// Index into LAYERS
+	layer: usize,
+	/// Index into SIGNALS, the SILK layer holds the encoder to voice
+	signal_hint: usize,
+	/// Index into PREROLLS
+	pub preroll: usize,
+	/// The coders ran since the last reset
+	primed: bool,
+	/// Rebuild lost packets from in-band FEC in the next packet, one packet later
+	pub decode_fec: bool,
+	/// Far-end speaker simulation after the decoder
+	pub coloration: Coloration,
+	/// Decoded audio leaking back into the encoder
+	pub echo: Echo,
+	/// Noise from the aux bus, only into the encoder
+	pub noise: NoiseMix,
+	/// Near end ducked while the far end bus talks
+	pub duplex: HalfDuplex,
+	/// High-pass and emphasis around the codec, like a phone's capture chain
+	pub handset: Handset,
+	/// Stage of the chain to listen to
+	pub monitor: Monitor,
+	/// Swap, polarity and mono on the output
+	pub channels: ChannelTools,
+	/// Aligned track to switch the output to
+	pub reference: Reference,
+	/// Input for the clean output bus
+	pub clean: CleanFeed,
+	/// Declicks the output when processing stops and starts
+	pub fade: Fade,
+	/// Largest packet that gets through, like a path MTU
+	pub max_packet_bytes: usize,
+	/// Index into CAP_MODES
+	pub cap_mode: usize,
+	/// Bitrate following the input or the bandwidth bus
+	pub adaptive: AdaptiveBitrate,
+	/// Stretches the last packet over a lost one
+	pub warp: TimeWarp,
+	/// Output against an earlier render
+	pub null: NullCheck,
+	/// How normalized values map onto continuous parameters
+	pub curves: Curves,
+	/// Parameters whose host automation is ignored
+	pub locks: Locks,
+	/// What happened in the last block, for the controller
+	pub events: EventLog,
+	/// Problems for the controller to show
+	pub warnings: Warnings,
 	pub bypass: bool,
+	/// Loss simulation on, off keeps the codec and the loss settings
+	pub network: bool,
+	/// Only the codec, for the codec class, whatever the other parameters say
+	codec_only: bool,
+	pub freeze: bool,
+	/// Drop Now is held
+	pub drop_now: bool,
+	/// Drop Now was on at some point since the last packet went out
+	drop_latched: bool,
+	pub repeat: u32,
 	pub loss_roundrobin: f64,
 	pub loss_random: f64,
+	/// Packets to or from another instance
+	pub link: Link,
+	/// Random loss seed, 0 is a different seed for every instance
+	pub loss_seed: u32,
+	/// Index in a group of instances sharing the seed, 0 loses the same packets as the group
+	pub loss_instance: u32,
+	pub pattern: StepPattern,
+	pub euclid: Euclidean,
 	pub decoder: Decoder,
 	pub encoder: Encoder,
 }
 
-const OPUS_SR: SampleRate = SampleRate::Hz48000;
-const OPUS_SRF: f64 = OPUS_SR as i32 as f64;
-const OPUS_LEN: usize = 960;
+/// Rates the coders can run at, lowest first
+pub const OPUS_RATES: [SampleRate; 5] = [
+	SampleRate::Hz8000,
+	SampleRate::Hz12000,
+	SampleRate::Hz16000,
+	SampleRate::Hz24000,
+	SampleRate::Hz48000,
+];
+
+/// Packets per second, for the default 20 ms packets
+pub const PACKET_RATE: usize = 50;
+
+/// Packet durations Opus encodes, in parameter order
+pub const FRAME_DURATIONS: [&str; 6] = ["2.5", "5", "10", "20", "40", "60"];
+
+/// The same durations in tenths of a millisecond
+const FRAME_TENTHS: [usize; 6] = [25, 50, 100, 200, 400, 600];
+
+/// 20 ms
+const DEFAULT_DURATION: usize = 3;
+
+/// Frames in the longest packet, 60 ms at 48 kHz
+const OPUS_MAX_LEN: usize = 48 * 60;
+
+/// Packet buffers, allocated once so a packet doesn't put them on the audio thread's stack
+#[derive(Default)]
+struct Scratch {
+	packet: Vec<[f32; 2]>,
+	noise: Vec<[f32; 2]>,
+	far: Vec<[f32; 2]>,
+	coder: CoderScratch,
+}
+
+/// Buffers a packet needs on its way through the coders
+#[derive(Default)]
+struct CoderScratch {
+	input: Vec<[f32; 2]>,
+	decoded: Vec<[f32; 2]>,
+	tapped: Vec<[f32; 2]>,
+	raw: Vec<[f32; 2]>,
+	primer: Vec<[f32; 2]>,
+}
+
+impl Scratch {
+	fn new() -> Self {
+		let buffer = || vec![[0f32; 2]; OPUS_MAX_LEN];
+		Self {
+			packet: buffer(),
+			noise: buffer(),
+			far: buffer(),
+			coder: CoderScratch {
+				input: buffer(),
+				decoded: buffer(),
+				tapped: buffer(),
+				raw: buffer(),
+				primer: buffer(),
+			},
+		}
+	}
+}
+
+/// Highest Opus bitrate, in bits per second
+const MAX_BITRATE: usize = 510_000;
+
+/// Largest 20 ms packet at the bitrate ceiling, where the packet cap stops capping
+pub const MAX_CAP_BYTES: usize = MAX_BITRATE / 8 / PACKET_RATE;
+
+/// Largest packet the encoder should produce, three 20 ms frames and their lengths
+pub const MAX_PACKET_BYTES: usize = 3 * MAX_CAP_BYTES + 8;
+
+/// Smallest packet cap, room for the TOC byte and a little audio
+pub const MIN_PACKET_BYTES: usize = 20;
+
+/// What happens to a packet over the cap, in parameter order
+pub const CAP_MODES: [&str; 2] = ["Drop", "Lower Bitrate"];
+
+/// Oversized packets are lost, like fragments dropped on the way
+const CAP_DROP: usize = 0;
+
+/// The encoder gets a buffer only as large as the cap, and lowers its bitrate to fit
+const CAP_LOWER: usize = 1;
+
+/// Packets the DSP can hold at once
+const PACKET_POOL_LEN: usize = 4;
+
+/// Codec layers the encoder can be held to, in parameter order
+pub const LAYERS: [&str; 3] = ["Auto", "SILK", "CELT"];
+
+/// Encoder picks its own mode
+const LAYER_AUTO: usize = 0;
+
+/// Speech model only, up to wideband
+const LAYER_SILK: usize = 1;
+
+/// Transform model only, the restricted low delay application
+const LAYER_CELT: usize = 2;
+
+/// Encoder rate control, in parameter order
+pub const RATE_CONTROLS: [&str; 3] = ["VBR", "Constrained VBR", "CBR"];
+
+const RATE_VBR: usize = 0;
+const RATE_CVBR: usize = 1;
+const RATE_CBR: usize = 2;
+
+/// What the encoder is told the input is, in parameter order
+pub const SIGNALS: [&str; 3] = ["Auto", "Voice", "Music"];
+
+/// What primes the coders before the first packet after a reset, in parameter order
+pub const PREROLLS: [&str; 3] = ["Off", "Input", "Silence"];
+
+const PREROLL_OFF: usize = 0;
+const PREROLL_SILENCE: usize = 2;
+
+const SIGNAL_VOICE: usize = 1;
+const SIGNAL_MUSIC: usize = 2;
+
+/// Encoder CTLs audiopus has no setter for
+const OPUS_SET_DTX_REQUEST: i32 = 4016;
+const OPUS_GET_DTX_REQUEST: i32 = 4017;
+const OPUS_SET_FORCE_CHANNELS_REQUEST: i32 = 4022;
+const OPUS_GET_FORCE_CHANNELS_REQUEST: i32 = 4023;
+const OPUS_SET_PREDICTION_DISABLED_REQUEST: i32 = 4042;
+const OPUS_GET_PREDICTION_DISABLED_REQUEST: i32 = 4043;
+const OPUS_SET_PHASE_INVERSION_DISABLED_REQUEST: i32 = 4046;
+const OPUS_GET_PHASE_INVERSION_DISABLED_REQUEST: i32 = 4047;
+
+/// Lets the encoder decide, for CTLs that take a count
+const OPUS_AUTO: i32 = -1000;
+
+/// Channels the encoder codes, in parameter order
+pub const FORCE_CHANNELS: [&str; 3] = ["Auto", "Mono", "Stereo"];
+
+/// Low enough that the encoder never leaves SILK for speech at wideband
+const SILK_BITRATE: i32 = 24000;
+
+/// Host rates the resamplers are set up for, outside these the latency or the
+/// filters get out of hand
+const MIN_HOST_RATE: f64 = 8000.0;
+const MAX_HOST_RATE: f64 = 384_000.0;
+
+/// Below this the host rate can't carry wideband speech
+const LOW_HOST_RATE: f64 = 16000.0;
+
+/// Above this the resamplers' delay is worth a warning
+const HIGH_HOST_RATE: f64 = 96000.0;
+
+/// Supported rate closest to the host rate, preferring the higher one
+fn nearest_opus_rate(hz: f64) -> SampleRate {
+	let mut nearest = SampleRate::Hz48000;
+	for rate in OPUS_RATES.iter().rev() {
+		if (*rate as i32 as f64 - hz).abs() < (nearest as i32 as f64 - hz).abs() {
+			nearest = *rate;
+		}
+	}
+	nearest
+}
+
+/// Loss generator seed for deterministic processing
+const DETERMINISTIC_SEED: u64 = 0x4f70_7573;
 
 impl Default for OpusDSP {
 	fn default() -> Self {
@@ -113,144 +465,1027 @@ impl Default for OpusDSP {
 }
 
 impl OpusDSP {
-	///
+	/// DSP at the coder rate, ready once setup() has the host's
 	fn new() -> Self {
-		let sample_rate = OPUS_SRF;
-		let insignal = buffer_signal::new(sample_rate, OPUS_SRF);
-		let outsignal = buffer_signal::new(OPUS_SRF, sample_rate);
-		let encoder = Encoder::new(OPUS_SR, Channels::Stereo, Application::Voip).unwrap();
-		let decoder = Decoder::new(OPUS_SR, Channels::Stereo).unwrap();
+		let opus_rate = SampleRate::Hz48000;
+		let sample_rate = opus_rate as i32 as f64;
+		let insignal = Resampler::new(sample_rate, sample_rate, false);
+		let noisesignal = Resampler::new(sample_rate, sample_rate, false);
+		let farsignal = Resampler::new(sample_rate, sample_rate, false);
+		let outsignal = Resampler::new(sample_rate, sample_rate, false);
+		let encoder = Encoder::new(opus_rate, Channels::Stereo, Application::Voip).unwrap();
+		let decoder = Decoder::new(opus_rate, Channels::Stereo).unwrap();
 
 		Self {
 			sample_rate,
+			reported_latency: 0,
+			watchdog: Watchdog::default(),
+			losses: LossCounts::default(),
+			bursts: BurstHistogram::default(),
+			levels: LevelWatch::default(),
+			effective: EffectiveConfig::default(),
+			timeline: Timeline::default(),
+			internal_edits: 0,
+			governor: false,
+			complexity: 9,
+			offline_complexity: 10,
+			offline_quality: true,
 			bypass: false,
+			network: true,
+			codec_only: false,
+			freeze: false,
+			drop_now: false,
+			drop_latched: false,
+			repeat: 0,
+			stutter: Stutter::default(),
+			freeze_sync: FreezeSync::default(),
+			coders_stale: false,
+			scratch: Scratch::new(),
+			opus_rate,
+			duration: DEFAULT_DURATION,
+			frame_duration: DEFAULT_DURATION,
+			lookahead: 0,
+			packets: PacketPool::new(PACKET_POOL_LEN),
+			previous: None,
+			previous_lost: true,
+			oversized: 0,
+			internal_rate: None,
+			low_rate_fallback: true,
+			max_bandwidth: Bandwidth::Fullband,
+			layer: LAYER_AUTO,
+			signal_hint: 0,
+			preroll: PREROLL_OFF,
+			primed: false,
+			decode_fec: false,
+			coloration: Coloration::default(),
+			echo: Echo::default(),
+			noise: NoiseMix::default(),
+			duplex: HalfDuplex::default(),
+			handset: Handset::default(),
+			monitor: Monitor::default(),
+			channels: ChannelTools::default(),
+			reference: Reference::default(),
+			clean: CleanFeed::default(),
+			fade: Fade::default(),
+			max_packet_bytes: MAX_CAP_BYTES,
+			cap_mode: CAP_DROP,
+			adaptive: AdaptiveBitrate::default(),
+			warp: TimeWarp::default(),
+			null: NullCheck::default(),
+			curves: Curves::default(),
+			locks: Locks::default(),
+			events: EventLog::default(),
+			warnings: Warnings::default(),
+			failing: false,
 			loss_roundrobin: 0.0,
 			loss_random: 0.0,
-			rng: thread_rng(),
+			loss_seed: 0,
+			loss_instance: 0,
+			link: Link::default(),
+			pattern: StepPattern::default(),
+			euclid: Euclidean::default(),
+			random: RandomStream::default(),
+			round_robin: RoundRobin::default(),
+			instance_seed: rand::random(),
+			offline: false,
+			high_quality: false,
+			rendering: false,
+			deterministic: false,
 			insignal,
+			noisesignal,
+			farsignal,
 			outsignal,
 			encoder,
 			decoder,
 		}
 	}
 
-	///
+	/// Take the host's rate and block size, then rebuild everything that depends on them
 	pub fn setup(&mut self, setup: &ProcessSetup) -> Result<()> {
+		self.check_host_rate(setup.sample_rate)?;
 		self.sample_rate = setup.sample_rate;
-		self.encoder = Encoder::new(OPUS_SR, Channels::Stereo, Application::Voip)?;
-		self.decoder = Decoder::new(OPUS_SR, Channels::Stereo)?;
+		self.timeline.set_rate(setup.sample_rate);
+		self.clean.set_rate(setup.sample_rate);
+		self.fade.set_rate(setup.sample_rate);
+		self.opus_rate = self.target_rate();
+		self.duration = self.frame_duration;
 		self.reset();
+		self.rebuild_coders()?;
+		self.coders_stale = false;
+		self.reported_latency = self.latency();
+		self.warn_about_rate();
+		if let Err(err) = self.null.set_host_rate(self.sample_rate) {
+			self.warnings.raise(warnings::NULL, err.to_string());
+		} else if self.null.is_active() {
+			self.warnings.resolve(warnings::NULL);
+		}
+		self.watchdog.reset();
+		self.losses.reset();
+		self.bursts.reset();
+		self.effective.reset();
 		Ok(())
 	}
 
-	///
+	/// Load a render to null against, reads the file so stay off the audio thread
+	pub fn load_null_reference(&mut self, path: &str) -> Result<()> {
+		let result = self.null.load(path, self.sample_rate);
+		match &result {
+			Ok(()) => self.warnings.resolve(warnings::NULL),
+			Err(err) => self.warnings.raise(warnings::NULL, err.to_string()),
+		}
+		result
+	}
+
+	/// Rates the codec handles, but not the way the user might expect
+	fn warn_about_rate(&mut self) {
+		let khz = self.sample_rate / 1000.0;
+		if self.sample_rate > HIGH_HOST_RATE {
+			let ms = self.latency() as f64 * 1000.0 / self.sample_rate;
+			let line = format!(
+				"sample rate {} kHz: resampling to {} kHz, {:.1} ms latency",
+				khz,
+				self.opus_hz() / 1000.0,
+				ms
+			);
+			self.warnings.raise(warnings::RATE, line);
+		} else if self.sample_rate < LOW_HOST_RATE && self.opus_hz() > self.sample_rate {
+			let line = format!(
+				"sample rate {} kHz: coding at {} kHz, above the host rate",
+				khz,
+				self.opus_hz() / 1000.0
+			);
+			self.warnings.raise(warnings::RATE, line);
+		} else {
+			self.warnings.resolve(warnings::RATE);
+		}
+	}
+
+	/// Rebuild the resamplers and coders and clear every buffer, as if nothing was played
 	pub fn reset(&mut self) {
-		self.insignal = buffer_signal::new(self.sample_rate, OPUS_SRF);
-		self.outsignal = buffer_signal::new(OPUS_SRF, self.sample_rate);
+		self.high_quality = self.offline && self.offline_quality;
+		let opus_hz = self.opus_hz();
+		self.insignal = Resampler::new(self.sample_rate, opus_hz, self.high_quality);
+		self.noisesignal = Resampler::new(self.sample_rate, opus_hz, self.high_quality);
+		self.noise.reset();
+		self.farsignal = Resampler::new(self.sample_rate, opus_hz, self.high_quality);
+		self.duplex.reset();
+		self.adaptive.reset();
+		self.reference.set_rate(self.sample_rate);
+		self.outsignal = Resampler::new(opus_hz, self.sample_rate, self.high_quality);
+		self.coloration.set_rate(opus_hz, self.packet_len());
+		self.warp.set_len(self.packet_len());
+		self.echo.set_rate(opus_hz);
+		self.handset.set_rate(opus_hz);
+		// Lookahead is at most a few milliseconds, a packet more covers FEC
+		self.monitor
+			.set_len(2 * self.packet_len() + opus_hz as usize / 100);
+		self.random.reset();
+		self.round_robin.reset();
+		self.stutter.reset();
+		self.freeze_sync.reset();
+		self.levels.reset();
+		self.pattern.reset();
+		self.euclid.reset();
+		self.clear_previous();
+		self.primed = false;
+	}
+
+	/// Forget the packet held back for FEC, the next one starts the pipeline over
+	fn clear_previous(&mut self) {
+		if let Some(previous) = self.previous.take() {
+			self.packets.release(previous);
+		}
+		self.previous_lost = true;
 	}
 
-	///
+	/// Decoding with FEC runs a packet behind, so toggling it restarts that pipeline
+	/// and moves the latency, which the next block reports
+	pub fn set_decode_fec(&mut self, on: bool) {
+		if on != self.decode_fec {
+			self.decode_fec = on;
+			self.clear_previous();
+		}
+	}
+
+	/// Follow the host's transport, a start or loop puts the loss generators where the
+	/// project position says, so a loop drops the same packets every time around
+	pub fn advance_timeline(&mut self, context: Option<&ProcessContext>, frames: usize) {
+		let frame = match self.timeline.advance(context, frames) {
+			Some(frame) => frame,
+			None => return,
+		};
+		let packets = self
+			.timeline
+			.packets_before(frame, self.packet_ms() / 1000.0);
+		self.pattern.seek(packets);
+		self.euclid.seek(packets);
+		self.random.seek(packets);
+		self.round_robin.seek(packets);
+		self.null.restart();
+		self.stutter.reset();
+		self.freeze_sync.reset();
+		debug!("transport at packet {}, loss generators moved", packets);
+	}
+
+	/// Seed for random loss, the user's, or a fixed one offline so renders repeat.
+	/// Either is split per instance of a group
+	fn random_seed(&self) -> u64 {
+		match self.loss_seed {
+			0 if self.deterministic => loss::sub_seed(DETERMINISTIC_SEED, self.loss_instance),
+			0 => self.instance_seed,
+			seed => loss::sub_seed(DETERMINISTIC_SEED ^ u64::from(seed), self.loss_instance),
+		}
+	}
+
+	/// Offline processing trades speed for quality and reproducible loss,
+	/// takes effect on the next reset
+	pub fn set_offline(&mut self, offline: bool) {
+		self.offline = offline;
+		self.deterministic = offline;
+	}
+
+	/// Rendering uses the offline complexity, takes effect on the next setup
+	pub fn set_rendering(&mut self, rendering: bool) {
+		self.rendering = rendering;
+	}
+
+	/// Processing offline
+	pub fn is_rendering(&self) -> bool {
+		self.rendering
+	}
+
+	/// Set the encoder to the user's complexity for the process mode
+	pub fn apply_complexity(&mut self) -> Result<()> {
+		let complexity = if self.rendering {
+			self.offline_complexity
+		} else {
+			self.complexity
+		};
+		Ok(self.encoder.set_complexity(complexity)?)
+	}
+
+	/// Read back from the encoder, which holds it across rebuilds via the parameter
+	pub fn rate_control(&self) -> Result<usize> {
+		Ok(
+			match (self.encoder.vbr()?, self.encoder.vbr_constraint()?) {
+				(false, _) => RATE_CBR,
+				(true, true) => RATE_CVBR,
+				(true, false) => RATE_VBR,
+			},
+		)
+	}
+
+	/// CBR pads every packet to the bitrate, constrained VBR keeps the buffer of a CBR link
+	pub fn set_rate_control(&mut self, mode: usize) -> Result<()> {
+		let mode = mode.min(RATE_CONTROLS.len() - 1);
+		self.encoder.set_vbr(mode != RATE_CBR)?;
+		self.encoder.set_vbr_constraint(mode == RATE_CVBR)?;
+		Ok(())
+	}
+
+	/// The chosen internal rate, or the supported rate nearest the host
+	fn target_rate(&self) -> SampleRate {
+		self.internal_rate.unwrap_or_else(|| {
+			if self.sample_rate < LOW_HOST_RATE && self.low_rate_fallback {
+				SampleRate::Hz24000
+			} else {
+				nearest_opus_rate(self.sample_rate)
+			}
+		})
+	}
+
+	/// Host rates the DSP can run at with the current settings
+	pub fn check_host_rate(&self, rate: f64) -> Result<()> {
+		if !rate.is_finite() || rate <= 0.0 {
+			return Err(Error::Resample(rate, self.opus_hz()));
+		}
+		if !(MIN_HOST_RATE..=MAX_HOST_RATE).contains(&rate) {
+			return Err(Error::OutOfRange(format!(
+				"host rate {} Hz, supported from {} to {} Hz",
+				rate, MIN_HOST_RATE, MAX_HOST_RATE
+			)));
+		}
+		if rate < LOW_HOST_RATE && self.internal_rate.is_none() && !self.low_rate_fallback {
+			return Err(Error::OutOfRange(format!(
+				"host rate {} Hz, below {} Hz needs the fallback or an internal rate",
+				rate, LOW_HOST_RATE
+			)));
+		}
+		Ok(())
+	}
+
+	/// Coder rate in Hz
+	fn opus_hz(&self) -> f64 {
+		self.opus_rate as i32 as f64
+	}
+
+	/// Frames in one packet at the coder rate
+	fn packet_len(&self) -> usize {
+		self.opus_rate as i32 as usize * FRAME_TENTHS[self.duration] / 10000
+	}
+
+	/// Duration of one packet
+	fn packet_ms(&self) -> f64 {
+		FRAME_TENTHS[self.duration] as f64 / 10.0
+	}
+
+	/// Frames at the host rate for frames at the coder rate
 	fn outer_frames(&self, inner_frames: usize) -> usize {
-		(inner_frames as f64 * self.sample_rate / OPUS_SRF) as usize
+		(inner_frames as f64 * self.sample_rate / self.opus_hz()) as usize
 	}
 
-	///
+	/// Frames the output lags the input, at the host rate
 	pub fn latency(&self) -> usize {
-		self.outer_frames(OPUS_LEN)
-	}
-
-	///
-	pub unsafe fn process(&mut self, data: &ProcessData) -> Result<()> {
-		let num_samples = data.num_samples as usize;
-
-		let (in_bus, in0, in1) = {
-			let buses = slice::from_raw_parts(data.inputs, data.num_inputs as usize);
-			ensure!(!buses.is_empty(), "requires at least 1 input bus");
-			let bus = &buses[0];
-			let num_channels = bus.num_channels as usize;
-			let buffers = slice::from_raw_parts(bus.buffers as *const *const f32, num_channels);
-			ensure!(buffers.len() >= 2, "requires at least 2 output channels");
-			let c0 = slice::from_raw_parts(buffers[0], num_samples);
-			let c1 = slice::from_raw_parts(buffers[1], num_samples);
-			(bus, c0, c1)
+		let fec_delay = if self.decode_fec {
+			self.packet_len()
+		} else {
+			0
+		};
+		let inner_frames =
+			self.packet_len() + fec_delay + self.lookahead + self.outsignal.latency();
+		self.insignal.latency() + self.outer_frames(inner_frames)
+	}
+
+	/// Latency in frames if it moved since it was last reported to the host
+	pub fn take_latency_change(&mut self) -> Option<usize> {
+		let latency = self.latency();
+		if latency == self.reported_latency {
+			return None;
+		}
+		self.reported_latency = latency;
+		self.events
+			.record(self.timeline.seconds(0), Event::Latency(latency));
+		Some(latency)
+	}
+
+	/// The coders need rebuilding, for a new internal rate, duration or layer, or after a
+	/// failure. That allocates, so the plugin builds a spare instead
+	pub fn needs_rebuild(&self) -> bool {
+		self.coders_stale
+			|| self.target_rate() != self.opus_rate
+			|| self.frame_duration != self.duration
+	}
+
+	/// Rebuild the coders when they need it, for pipelines without spares and never
+	/// in process(). A new rate or duration resamples and cuts packets anew
+	pub fn rebuild(&mut self) -> Result<()> {
+		if !self.needs_rebuild() {
+			return Ok(());
+		}
+		if self.target_rate() != self.opus_rate || self.frame_duration != self.duration {
+			self.opus_rate = self.target_rate();
+			self.duration = self.frame_duration;
+			self.reset();
+		}
+		self.rebuild_coders()?;
+		self.coders_stale = false;
+		Ok(())
+	}
+
+	/// New coders configured like the old ones, after a codec failure
+	fn rebuild_coders(&mut self) -> Result<()> {
+		let mut values = EnumMap::<Parameter, Option<f64>>::default();
+		for (param, value) in values.iter_mut() {
+			*value = param.get_from_dsp(self).ok();
+		}
+
+		self.encoder = Encoder::new(self.opus_rate, Channels::Stereo, self.application())?;
+		self.decoder = Decoder::new(self.opus_rate, Channels::Stereo)?;
+		self.primed = false;
+
+		for (param, value) in values.iter() {
+			if let Some(value) = value {
+				param.set_to_dsp(self, *value)?;
+			}
+		}
+
+		self.apply_signal()?;
+		if self.layer == LAYER_SILK {
+			self.encoder
+				.set_bitrate(Bitrate::BitsPerSecond(SILK_BITRATE))?;
+		}
+
+		self.update_lookahead()
+	}
+
+	/// Only CELT has its own application, the others are held by settings
+	fn application(&self) -> Application {
+		match self.layer {
+			LAYER_CELT => Application::LowDelay,
+			_ => Application::Voip,
+		}
+	}
+
+	/// Index into LAYERS the coders were built for
+	pub fn layer(&self) -> usize {
+		self.layer
+	}
+
+	/// The application can't change after the first packet, so a new layer rebuilds the coders
+	pub fn set_layer(&mut self, layer: usize) -> Result<()> {
+		let layer = layer.min(LAYERS.len() - 1);
+		if layer != self.layer {
+			self.layer = layer;
+			self.coders_stale = true;
+		}
+		self.apply_bandwidth()
+	}
+
+	/// Set the encoder to the user's bandwidth, or narrower while the adaptive bitrate
+	/// is low. SILK stops at wideband
+	pub fn apply_bandwidth(&mut self) -> Result<()> {
+		let mut bandwidth = self.max_bandwidth;
+		if let Some(limit) = self.adaptive.bandwidth_limit() {
+			let chosen = BANDWIDTHS.iter().position(|x| *x == bandwidth);
+			bandwidth = BANDWIDTHS[chosen.unwrap_or(limit).min(limit)];
+		}
+		let bandwidth = match (self.layer, bandwidth) {
+			(LAYER_SILK, Bandwidth::Superwideband) | (LAYER_SILK, Bandwidth::Fullband) => {
+				Bandwidth::Wideband
+			}
+			(_, bandwidth) => bandwidth,
 		};
+		Ok(self.encoder.set_max_bandwidth(bandwidth)?)
+	}
+
+	/// Index into SIGNALS
+	pub fn signal_hint(&self) -> usize {
+		self.signal_hint
+	}
+
+	/// Voice leans the encoder towards SILK, music towards CELT
+	pub fn set_signal_hint(&mut self, hint: usize) -> Result<()> {
+		self.signal_hint = hint.min(SIGNALS.len() - 1);
+		self.apply_signal()
+	}
 
-		let (out_bus, out0, out1) = {
-			let buses = slice::from_raw_parts_mut(data.outputs, data.num_outputs as usize);
-			ensure!(!buses.is_empty(), "requires at least 1 output bus");
-			let bus = &mut buses[0];
-			let num_channels = bus.num_channels as usize;
-			let buffers = slice::from_raw_parts(bus.buffers as *const *mut f32, num_channels);
-			ensure!(buffers.len() >= 2, "requires at least 2 output channels");
-			let c0 = slice::from_raw_parts_mut(buffers[0], num_samples);
-			let c1 = slice::from_raw_parts_mut(buffers[1], num_samples);
-			(bus, c0, c1)
+	/// Set the encoder to the user's hint, unless the SILK layer holds it to voice
+	fn apply_signal(&mut self) -> Result<()> {
+		let signal = match (self.layer, self.signal_hint) {
+			(LAYER_SILK, _) | (_, SIGNAL_VOICE) => OpusSignal::Voice,
+			(_, SIGNAL_MUSIC) => OpusSignal::Music,
+			_ => OpusSignal::Auto,
 		};
+		Ok(self.encoder.set_signal(signal)?)
+	}
 
-		let params = upgrade_param_changes(&data.input_param_changes);
+	/// Discontinuous transmission, the encoder stops sending audio during silence
+	pub fn dtx(&self) -> Result<bool> {
+		Ok(self.encoder.encoder_ctl_request(OPUS_GET_DTX_REQUEST)? != 0)
+	}
 
-		let is_silent = in_bus.silence_flags & 0b11 == 0b11;
+	/// Let the encoder stop sending packets during silence
+	pub fn set_dtx(&mut self, dtx: bool) -> Result<()> {
+		self.encoder
+			.set_encoder_ctl_request(OPUS_SET_DTX_REQUEST, dtx as i32)?;
+		Ok(())
+	}
 
-		if is_silent && self.insignal.is_exhausted() {
+	/// Every frame coded on its own, so a loss doesn't carry into the frames after it
+	pub fn prediction_disabled(&self) -> Result<bool> {
+		let disabled = self
+			.encoder
+			.encoder_ctl_request(OPUS_GET_PREDICTION_DISABLED_REQUEST)?;
+		Ok(disabled != 0)
+	}
+
+	/// Make every packet decodable on its own, without inter-frame prediction
+	pub fn set_prediction_disabled(&mut self, disabled: bool) -> Result<()> {
+		self.encoder
+			.set_encoder_ctl_request(OPUS_SET_PREDICTION_DISABLED_REQUEST, disabled as i32)?;
+		Ok(())
+	}
+
+	/// Intensity stereo without inverting a channel, which cancels in a mono downmix
+	pub fn phase_inversion_disabled(&self) -> Result<bool> {
+		let disabled = self
+			.encoder
+			.encoder_ctl_request(OPUS_GET_PHASE_INVERSION_DISABLED_REQUEST)?;
+		Ok(disabled != 0)
+	}
+
+	/// Keep the stereo intensity coding from inverting phase
+	pub fn set_phase_inversion_disabled(&mut self, disabled: bool) -> Result<()> {
+		self.encoder
+			.set_encoder_ctl_request(OPUS_SET_PHASE_INVERSION_DISABLED_REQUEST, disabled as i32)?;
+		Ok(())
+	}
+
+	/// Index into FORCE_CHANNELS
+	pub fn force_channels(&self) -> Result<usize> {
+		let channels = self
+			.encoder
+			.encoder_ctl_request(OPUS_GET_FORCE_CHANNELS_REQUEST)?;
+		Ok(match channels {
+			1 | 2 => channels as usize,
+			_ => 0,
+		})
+	}
+
+	/// Mono codes the stereo bus as one channel, however wide it is
+	pub fn set_force_channels(&mut self, index: usize) -> Result<()> {
+		let channels = match index.min(FORCE_CHANNELS.len() - 1) {
+			0 => OPUS_AUTO,
+			index => index as i32,
+		};
+		self.encoder
+			.set_encoder_ctl_request(OPUS_SET_FORCE_CHANNELS_REQUEST, channels)?;
+		Ok(())
+	}
+
+	/// Hand the bitrate back to the encoder, or to the SILK layer, once adapting stops
+	pub fn apply_bitrate(&mut self) -> Result<()> {
+		let bitrate = match self.layer {
+			LAYER_SILK => Bitrate::BitsPerSecond(SILK_BITRATE),
+			_ => Bitrate::Auto,
+		};
+		Ok(self.encoder.set_bitrate(bitrate)?)
+	}
+
+	/// Encoder settings like FEC, DTX, and application mode move the lookahead
+	fn update_lookahead(&mut self) -> Result<()> {
+		self.lookahead = self.encoder.lookahead()? as usize;
+		Ok(())
+	}
+
+	/// Leave out the network, glitches, and far end, for the codec class
+	pub fn set_codec_only(&mut self) {
+		self.codec_only = true;
+	}
+
+	/// Run a packet through both coders and throw it away, so the first packet heard
+	/// doesn't carry the encoder's warm-up. The decoder stays in step with the encoder
+	fn prime_coders(&mut self, input: &[[f32; 2]], primer: &mut [[f32; 2]]) -> Result<()> {
+		self.primed = true;
+		if self.preroll == PREROLL_OFF {
+			return Ok(());
+		}
+		let primer = &mut primer[..input.len()];
+		if self.preroll != PREROLL_SILENCE {
+			primer.copy_from_slice(input);
+		}
+		let mut bytes = [0u8; MAX_PACKET_BYTES];
+		let signals = dasp::slice::to_sample_slice(&primer[..]);
+		let len = self.encoder.encode_float(signals, &mut bytes[..])?;
+		let signals = dasp::slice::to_sample_slice_mut(&mut primer[..]);
+		self.decoder
+			.decode_float(Some(&bytes[..len]), signals, false)?;
+		Ok(())
+	}
+
+	/// Encode, lose, and decode one packet in place, leaving it untouched on failure
+	fn transmit(
+		&mut self,
+		scratch: &mut CoderScratch,
+		packet_audio: &mut [[f32; 2]],
+		noise: Option<&[[f32; 2]]>,
+		far_end: Option<&[[f32; 2]]>,
+		time: f64,
+	) -> Result<()> {
+		let mut packet_bytes = [0u8; MAX_PACKET_BYTES];
+		let mut link_bytes = [0u8; MAX_PACKET_BYTES];
+		let frames = packet_audio.len();
+		let input = &mut scratch.input[..frames];
+		let decoded = &mut scratch.decoded[..frames];
+
+		// The far end's echo joins the input, a copy keeps the packet untouched on failure
+		input.copy_from_slice(packet_audio);
+		if !self.codec_only {
+			self.echo.feed(input);
+			if let Some(noise) = noise {
+				self.noise.mix(input, noise);
+			}
+			self.handset.filter_input(input);
+			self.duplex
+				.gate(packet_audio, far_end, input, self.packet_ms());
+		}
+		self.levels.record(input);
+
+		// Held back like the decoded audio, the codec delays it by its lookahead and FEC a packet
+		let tapped = &mut scratch.tapped[..frames];
+		let fec_delay = if self.decode_fec {
+			packet_audio.len()
+		} else {
+			0
+		};
+		self.monitor
+			.delay_input(input, self.lookahead + fec_delay, tapped);
+
+		if self.adaptive.is_enabled() {
+			let bitrate = self.adaptive.next(input, self.packet_ms());
+			self.encoder.set_bitrate(Bitrate::BitsPerSecond(bitrate))?;
+			if self.adaptive.bandwidth_limit().is_some() {
+				self.apply_bandwidth()?;
+			}
+		}
+
+		if !self.primed {
+			self.prime_coders(input, &mut scratch.primer)?;
+		}
+
+		// Reslice
+		let signals = dasp::slice::to_sample_slice(&input[..]);
+		let decoded_signals = dasp::slice::to_sample_slice_mut(&mut decoded[..]);
+
+		// Encode, a packet too large for the buffer or over the cap is dropped like a lost one.
+		// The cap at its top leaves room for long packets
+		let cap = match self.max_packet_bytes {
+			bytes if bytes >= MAX_CAP_BYTES => MAX_PACKET_BYTES,
+			bytes => bytes.max(MIN_PACKET_BYTES),
+		};
+		let buffer = match self.cap_mode {
+			CAP_LOWER => &mut packet_bytes[..cap],
+			_ => &mut packet_bytes[..],
+		};
+		let len = match self.encoder.encode_float(signals, buffer) {
+			Ok(len) if len <= cap => Some(len),
+			Ok(_) | Err(audiopus::Error::Opus(audiopus::ErrorCode::BufferTooSmall)) => {
+				self.oversized = self.oversized.saturating_add(1);
+				if self.oversized.is_power_of_two() {
+					warn!(
+						"at={:.3}s dropped {} oversized packets",
+						time, self.oversized
+					);
+				}
+				None
+			}
+			Err(err) => return Err(err.into()),
+		};
+		if let Some(len) = len {
+			self.effective.record(&packet_bytes[..len]);
+			self.events.record(time, Event::Encoded(len));
+		}
+
+		// Freeze replaces new packets with a captured one
+		let (freeze, repeat) = if self.codec_only {
+			(false, 0)
+		} else {
+			(self.freeze_sync.frozen(), self.repeat)
+		};
+		// Drawn before the stutter holds on to the packet
+		let seed = self.random_seed();
+		let stutter = &mut self.stutter;
+		let packet = len.map(|len| stutter.next(&packet_bytes[..len], freeze, repeat));
+
+		// Decode, the loss generators keep running with the network off so they stay in step
+		let lost_pattern = self.pattern.next();
+		let lost_euclid = self.euclid.next();
+		let lost_random = self.random.next(seed) < self.loss_random;
+		let lost_round_robin = self.round_robin.next(self.loss_roundrobin);
+		let network = self.network && !self.codec_only;
+		// Drop Now works with the network off too, it's a performance control
+		let dropped = self.drop_latched && !self.codec_only;
+		self.drop_latched = self.drop_now;
+		let lost = (lost_pattern, lost_euclid, lost_random, lost_round_robin);
+		let cause = match (packet, lost) {
+			(None, _) => Some(LossCause::Oversized),
+			_ if dropped => Some(LossCause::Manual),
+			_ if !network => None,
+			(_, (true, ..)) => Some(LossCause::Pattern),
+			(_, (_, true, ..)) => Some(LossCause::Euclidean),
+			(_, (_, _, true, _)) => Some(LossCause::Random),
+			(_, (.., true)) => Some(LossCause::RoundRobin),
+			_ => None,
+		};
+
+		// The sender's impairments decide what goes over the link, the receiver plays
+		// what arrived in place of its own packets
+		let (packet, cause) = if self.codec_only {
+			(packet, cause)
+		} else if self.link.is_sender() {
+			if let (Some(packet), None) = (packet, cause) {
+				self.link.send(packet);
+			}
+			(packet, cause)
+		} else if self.link.is_receiver() {
+			match self.link.receive(&mut link_bytes) {
+				Some(len) => (Some(&link_bytes[..len]), None),
+				None => (None, Some(LossCause::Link)),
+			}
+		} else {
+			(packet, cause)
+		};
+		let lost = cause.is_some();
+		self.bursts.record(lost);
+		if let Some(cause) = cause {
+			self.losses.record(cause);
+			self.events.record(time, Event::Lost(cause));
+		}
+		trace!(
+			"packet at={:.3}s bytes={} lost={:?}",
+			time,
+			packet.map_or(0, <[u8]>::len),
+			cause
+		);
+		let none: Option<&[u8]> = None;
+		let concealed = if self.decode_fec {
+			self.previous_lost && lost
+		} else {
+			lost
+		};
+		if self.decode_fec {
+			// One packet behind, so a lost packet is rebuilt from the FEC data in the next
+			match (self.previous_lost, lost) {
+				(true, false) => self.decoder.decode_float(packet, decoded_signals, true)?,
+				(true, true) => self.decoder.decode_float(none, decoded_signals, true)?,
+				(false, _) => {
+					let packets = &self.packets;
+					let previous = self.previous.as_ref().map(|previous| packets.get(previous));
+					self.decoder
+						.decode_float(previous, decoded_signals, false)?
+				}
+			};
+			self.previous_lost = lost;
+			let packets = &mut self.packets;
+			if let Some(previous) = self.previous.take() {
+				packets.release(previous);
+			}
+			self.previous = packet.and_then(|packet| packets.store(packet));
+		} else if lost {
+			self.decoder.decode_float(none, decoded_signals, true)?;
+		} else {
+			self.decoder.decode_float(packet, decoded_signals, false)?;
+		}
+
+		// The decoder still concealed the packet, so it carries on from the right state
+		if !self.codec_only {
+			if concealed && self.warp.is_enabled() {
+				self.warp.conceal(decoded);
+			}
+			self.warp.record(decoded);
+		}
+
+		let raw = &mut scratch.raw[..frames];
+		raw.copy_from_slice(decoded);
+
+		if !self.codec_only {
+			self.handset.filter_output(decoded);
+			self.coloration.process(decoded);
+			self.echo.capture(decoded);
+		}
+		packet_audio.copy_from_slice(match self.monitor.tap {
+			TAP_INPUT => tapped,
+			TAP_DECODED => raw,
+			_ => decoded,
+		});
+		self.channels.process(packet_audio);
+		Ok(())
+	}
+
+	/// Check the time a block took against its real-time budget
+	pub fn check_load(&mut self, elapsed: Duration, num_samples: usize) -> Result<()> {
+		let budget = Duration::from_secs_f64(num_samples as f64 / self.sample_rate);
+		if !self.watchdog.record(elapsed, budget) {
+			return Ok(());
+		}
+
+		let complexity = self.complexity;
+		warn!(
+			"watchdog: at={:.3}s xruns={} elapsed_us={} budget_us={} complexity={} governor={}",
+			self.timeline.seconds(0),
+			self.watchdog.xruns,
+			elapsed.as_micros(),
+			budget.as_micros(),
+			complexity,
+			self.governor
+		);
+
+		if self.governor && complexity > 0 {
+			self.complexity = complexity - 1;
+			self.internal_edits = self.internal_edits.wrapping_add(1);
+			self.apply_complexity()?;
+			self.events
+				.record(self.timeline.seconds(0), Event::Governor(self.complexity));
+		}
+
+		Ok(())
+	}
+
+	/// Apply the block's parameter changes, then run the audio through the coders
+	pub fn process(
+		&mut self,
+		params: &ParamQueueMap,
+		input: [&[f32]; 2],
+		aux: AuxInputs,
+		output: [&mut [f32]; 2],
+		is_silent: bool,
+	) -> Result<bool> {
+		let [in0, in1] = input;
+		let [out0, out1] = output;
+		let num_samples = in0.len().min(in1.len()).min(out0.len()).min(out1.len());
+
+		// The reference plays whether the input is silent or not
+		let silent_output =
+			is_silent && self.insignal.is_exhausted() && !self.reference.is_selected();
+		let mut pressed_from = 0;
+		let reference_delay = self.latency();
+		if let Some([b0, b1]) = aux.bandwidth {
+			let peak = b0.iter().chain(b1).fold(0f32, |peak, x| peak.max(x.abs()));
+			self.adaptive.set_available(peak);
+		}
+
+		if silent_output {
 			// silence
-			out_bus.silence_flags = 0b11;
 			out0.fill(Stereo::EQUILIBRIUM[0]);
 			out1.fill(Stereo::EQUILIBRIUM[1]);
+			if self.null.is_active() {
+				for i in 0..num_samples {
+					let frame = self.timeline.project_frame(i as i64);
+					self.null.check(frame, [0.0; 2]);
+				}
+			}
 		} else {
 			// process
 			for i in 0..num_samples {
 				if self.outsignal.is_exhausted() {
-					let mut packet_audio = [[0f32; 2]; OPUS_LEN];
-					let mut packet_bytes = [0u8; 1024];
+					let mut scratch = std::mem::take(&mut self.scratch);
+					let packet_audio = &mut scratch.packet[..self.packet_len()];
 
 					// Read 1 packet of input
 					packet_audio.fill_with(|| self.insignal.next());
 
-					// Reslice
-					let signals = dasp::slice::to_sample_slice_mut(&mut packet_audio[..]);
+					let noise_audio = &mut scratch.noise[..self.packet_len()];
+					let noise_packet = match aux.noise {
+						Some(_) => {
+							noise_audio.fill_with(|| self.noisesignal.next());
+							Some(&noise_audio[..])
+						}
+						None => None,
+					};
+
+					let far_audio = &mut scratch.far[..self.packet_len()];
+					let far_packet = match aux.far_end {
+						Some(_) => {
+							far_audio.fill_with(|| self.farsignal.next());
+							Some(&far_audio[..])
+						}
+						None => None,
+					};
 
 					// Apply params up to this frame
-					self.apply_parameter_changes(&params, i)?;
-
-					// Encode
-					let len = self.encoder.encode_float(signals, &mut packet_bytes)?;
-					let packet = Some(&packet_bytes[..len]);
-
-					// Decode
-					if self.rng.gen::<f64>() < self.loss_random {
-						let lost: Option<&[u8]> = None;
-						self.decoder.decode_float(lost, signals, true)?;
-					} else {
-						self.decoder.decode_float(packet, signals, false)?;
+					self.latch_drop_now(params, pressed_from, i);
+					pressed_from = i;
+					if let Err(err) = self.apply_parameter_changes(params, i) {
+						self.scratch = scratch;
+						return Err(err);
+					}
+
+					// Stamped with the host time the packet's first frame went in
+					let delay = self.outer_frames(self.packet_len()) + self.insignal.latency();
+					let time = self.timeline.seconds(i as i64 - delay as i64);
+
+					// Freeze follows the grid from where the packet's audio starts
+					let frames = self.outer_frames(self.packet_len());
+					let span = self.timeline.quarters(i as i64 - delay as i64, frames);
+					self.freeze_sync.update(self.freeze, span);
+
+					// A failed packet plays dry, at the same latency, instead of stopping the block
+					match self.transmit(
+						&mut scratch.coder,
+						packet_audio,
+						noise_packet,
+						far_packet,
+						time,
+					) {
+						Ok(()) if self.failing => {
+							info!("at={:.3}s codec recovered", time);
+							self.failing = false;
+						}
+						Ok(()) => {}
+						Err(err) => {
+							if !self.failing {
+								error!(
+									"at={:.3}s {}, passing dry signal until the coders are rebuilt",
+									time, err
+								);
+								self.failing = true;
+							}
+							self.coders_stale = true;
+						}
 					}
 
 					// Cache output
-					self.outsignal.source_mut().push_slice(&packet_audio);
+					self.outsignal.source_mut().push_slice(packet_audio);
+					self.scratch = scratch;
 				}
 
 				if !is_silent {
 					self.insignal.source_mut().push([in0[i], in1[i]]);
 				}
+				if let Some([n0, n1]) = aux.noise {
+					self.noisesignal.source_mut().push([n0[i], n1[i]]);
+				}
+				if let Some([f0, f1]) = aux.far_end {
+					self.farsignal.source_mut().push([f0[i], f1[i]]);
+				}
 
-				let [s0, s1] = self.outsignal.next();
+				let coded = self.outsignal.next();
+				let reference = aux.reference.map_or([0.0; 2], |[r0, r1]| [r0[i], r1[i]]);
+				let reference = self.reference.delay(reference, reference_delay);
+				let [s0, s1] = if self.reference.is_selected() {
+					reference
+				} else {
+					coded
+				};
 				out0[i] = s0;
 				out1[i] = s1;
+				self.null
+					.check(self.timeline.project_frame(i as i64), [s0, s1]);
+			}
+		}
+
+		self.latch_drop_now(params, pressed_from, usize::MAX);
+		self.apply_parameter_changes(params, usize::MAX)?;
+
+		Ok(silent_output)
+	}
+
+	/// Replace this DSP with one built for new settings, still reporting latency changes
+	/// relative to what the host was last told
+	pub fn take_over(&mut self, next: &mut OpusDSP) {
+		std::mem::swap(self, next);
+		self.reported_latency = next.reported_latency;
+		// The clean feed and a stop in progress don't change with the settings, so they carry on
+		std::mem::swap(&mut self.clean, &mut next.clean);
+		std::mem::swap(&mut self.fade, &mut next.fade);
+		// The processor reads the playing DSP's histogram and events, so they stay with it
+		std::mem::swap(&mut self.bursts, &mut next.bursts);
+		std::mem::swap(&mut self.events, &mut next.events);
+		// A spare built from a template has no reference, the playing one keeps comparing
+		if self.null.path().is_none() {
+			std::mem::swap(&mut self.null, &mut next.null);
+		}
+	}
+
+	/// Set Drop Now, a press always drops at least the next packet
+	pub fn set_drop_now(&mut self, on: bool) {
+		self.drop_now = on;
+		self.drop_latched |= on;
+	}
+
+	/// A tap on Drop Now between two packets still drops one, though only the
+	/// last point before a packet gets applied
+	fn latch_drop_now(&mut self, params: &ParamQueueMap, from: usize, to: usize) {
+		if let Some(queue) = &params[Parameter::DropNow] {
+			if unsafe { pressed_between(queue.as_ref(), from, to) } {
+				self.drop_latched = true;
+			}
+		}
+	}
+
+	/// Apply every queued point in sample order, for a flush that carries no audio.
+	/// Merges the queues through a cursor per parameter, so nothing is allocated
+	pub fn flush_parameter_changes(&mut self, map: &ParamQueueMap) -> Result<()> {
+		let mut cursors = EnumMap::<Parameter, i32>::default();
+		let mut applied = false;
+		loop {
+			// Earliest next point, the first parameter wins a tie like a stable sort
+			let mut next: Option<(i32, Parameter, f64)> = None;
+			for (param, option) in map.iter() {
+				if let Some(queue) = option {
+					let num_points = unsafe { queue.get_point_count() };
+					while cursors[param] < num_points {
+						let mut offset = 0;
+						let mut value = 0.0;
+						let result =
+							unsafe { queue.get_point(cursors[param], &mut offset, &mut value) };
+						if result == kResultTrue {
+							if next.is_none_or(|(earliest, _, _)| offset < earliest) {
+								next = Some((offset, param, value));
+							}
+							break;
+						}
+						cursors[param] += 1;
+					}
+				}
+			}
+
+			match next {
+				Some((_, param, value)) => {
+					cursors[param] += 1;
+					param.set_to_dsp(self, value)?;
+					applied = true;
+				}
+				None => break,
 			}
 		}
 
-		self.apply_parameter_changes(&params, usize::MAX)?;
+		if applied {
+			self.update_lookahead()?;
+		}
 
 		Ok(())
 	}
 
-	///
+	/// Set each parameter to its value at `limit` frames into the block, interpolated
+	/// between points in high quality
 	pub fn apply_parameter_changes(&mut self, map: &ParamQueueMap, limit: usize) -> Result<()> {
 		let mut changes = EnumMap::<Parameter, Option<f64>>::default();
 
 		for (param, option) in map.iter() {
 			if let Some(queue) = option {
 				let mut a = None;
-				// let mut b = None;
+				let mut b = None;
 				let num_points = unsafe { queue.get_point_count() };
 				let mut offset = 0;
 				let mut value = 0.0;
@@ -259,23 +1494,179 @@ impl OpusDSP {
 					if result == kResultTrue {
 						if (offset as usize) < limit {
 							// Found next point within sample range
-							a = Some(value);
+							a = Some((offset, value));
 						} else {
-							// TODO Found point after allowed range, use as target for interpolation
+							// Found point after allowed range, use as target for interpolation
+							b = Some((offset, value));
 							break;
 						}
 					}
 				}
-				changes[param] = a;
+				changes[param] = match (a, b) {
+					(Some((a_offset, a_value)), Some((b_offset, b_value)))
+						if self.high_quality && b_offset > a_offset =>
+					{
+						let t = (limit - a_offset as usize) as f64 / (b_offset - a_offset) as f64;
+						Some(a_value + (b_value - a_value) * t)
+					}
+					(Some((_, a_value)), _) => Some(a_value),
+					(None, _) => None,
+				};
 			}
 		}
 
+		let mut changed = false;
 		for (param, value) in changes.iter() {
 			if let Some(value) = value {
 				param.set_to_dsp(self, *value)?;
+				changed = true;
 			}
 		}
 
+		if changed {
+			self.update_lookahead()?;
+		}
+
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sys::{kInvalidArgument, kResultFalse, tresult};
+	#[cfg(feature = "vst3")]
+	use vst3_sys::VST3;
+
+	/// Parameter queue with fixed points, like a host's flush
+	#[cfg_attr(feature = "vst3", VST3(implements(IParamValueQueue)))]
+	struct Points {
+		id: u32,
+		points: Vec<(i32, f64)>,
+	}
+
+	impl IParamValueQueue for Points {
+		unsafe fn get_parameter_id(&self) -> u32 {
+			self.id
+		}
+
+		unsafe fn get_point_count(&self) -> i32 {
+			self.points.len() as i32
+		}
+
+		unsafe fn get_point(&self, index: i32, offset: *mut i32, value: *mut f64) -> tresult {
+			match self.points.get(index as usize) {
+				Some((point_offset, point_value)) => {
+					*offset = *point_offset;
+					*value = *point_value;
+					kResultTrue
+				}
+				None => kInvalidArgument,
+			}
+		}
+
+		unsafe fn add_point(&self, _offset: i32, _value: f64, _index: *mut i32) -> tresult {
+			kResultFalse
+		}
+	}
+
+	#[cfg(feature = "vst3")]
+	fn queue(param: Parameter, points: &[(i32, f64)]) -> Option<Box<dyn IParamValueQueue>> {
+		Some(Points::allocate(param.into(), points.to_vec()))
+	}
+
+	#[cfg(not(feature = "vst3"))]
+	fn queue(param: Parameter, points: &[(i32, f64)]) -> Option<Box<dyn IParamValueQueue>> {
+		let points = points.to_vec();
+		Some(Box::new(Points {
+			id: param.into(),
+			points,
+		}))
+	}
+
+	#[test]
+	fn flush_applies_every_point() {
+		let mut dsp = OpusDSP::default();
+		let mut map = ParamQueueMap::default();
+		map[Parameter::Complexity] = queue(Parameter::Complexity, &[(0, 0.2), (10, 1.0)]);
+		map[Parameter::RandomLoss] = queue(Parameter::RandomLoss, &[(3, 0.5)]);
+
+		dsp.flush_parameter_changes(&map).unwrap();
+
+		assert_eq!(dsp.complexity, 10);
+		assert!((dsp.loss_random - 0.5).abs() < 1e-9);
+	}
+
+	#[test]
+	fn flush_leaves_audio_alone() {
+		let mut dsp = OpusDSP::default();
+		let setup = ProcessSetup {
+			process_mode: 0,
+			symbolic_sample_size: 0,
+			max_samples_per_block: 64,
+			sample_rate: 48000.0,
+		};
+		dsp.setup(&setup).unwrap();
+		let latency = dsp.latency();
+		let mut map = ParamQueueMap::default();
+		map[Parameter::InternalRate] = queue(Parameter::InternalRate, &[(0, 0.2)]);
+
+		dsp.flush_parameter_changes(&map).unwrap();
+
+		// The new rate waits for the next block with audio
+		assert_eq!(dsp.opus_rate, SampleRate::Hz48000);
+		assert!(!dsp.coders_stale);
+		assert!(dsp.outsignal.source_mut().is_exhausted());
+		assert_eq!(dsp.latency(), latency);
+	}
+
+	#[test]
+	fn process_leaves_the_rebuild_to_a_spare() {
+		let mut dsp = OpusDSP::default();
+		let setup = ProcessSetup {
+			process_mode: 0,
+			symbolic_sample_size: 0,
+			max_samples_per_block: 64,
+			sample_rate: 48000.0,
+		};
+		dsp.setup(&setup).unwrap();
+		let mut map = ParamQueueMap::default();
+		map[Parameter::InternalRate] = queue(Parameter::InternalRate, &[(0, 0.2)]);
+		dsp.flush_parameter_changes(&map).unwrap();
+
+		let input = [0f32; 64];
+		let (mut left, mut right) = ([0f32; 64], [0f32; 64]);
+		let params = ParamQueueMap::default();
+		let output = [&mut left[..], &mut right[..]];
+		dsp.process(
+			&params,
+			[&input, &input],
+			AuxInputs::default(),
+			output,
+			false,
+		)
+		.unwrap();
+		assert_eq!(dsp.opus_rate, SampleRate::Hz48000);
+		assert!(dsp.needs_rebuild());
+
+		dsp.rebuild().unwrap();
+		assert_ne!(dsp.opus_rate, SampleRate::Hz48000);
+		assert!(!dsp.needs_rebuild());
+	}
+
+	#[test]
+	fn toggling_decode_fec_restarts_the_pipeline() {
+		let mut dsp = OpusDSP::default();
+		let latency = dsp.latency();
+		dsp.previous_lost = false;
+
+		dsp.set_decode_fec(true);
+		assert!(dsp.previous.is_none() && dsp.previous_lost);
+		assert_eq!(dsp.latency(), latency + dsp.outer_frames(dsp.packet_len()));
+
+		dsp.previous_lost = false;
+		dsp.set_decode_fec(false);
+		assert!(dsp.previous_lost);
+		assert_eq!(dsp.latency(), latency);
+	}
+}
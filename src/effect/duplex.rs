@@ -0,0 +1,152 @@
+/// Who the half-duplex gate hears talking, in parameter order
+pub const TALKERS: [&str; 3] = ["Nobody", "Near End", "Far End"];
+
+const NOBODY: u8 = 0;
+const NEAR_END: u8 = 1;
+const FAR_END: u8 = 2;
+
+/// Range of the talk threshold, in dBFS
+pub const MIN_THRESHOLD_DB: f64 = -70.0;
+pub const MAX_THRESHOLD_DB: f64 = 0.0;
+
+/// Longest the far end keeps the line after going quiet, in ms
+pub const MAX_HOLD_MS: f64 = 1000.0;
+
+/// At this depth the near end is gated completely instead of ducked
+pub const MIN_DEPTH_DB: f64 = -60.0;
+
+/// Level of both channels, in dBFS
+pub fn rms_db(frames: &[[f32; 2]]) -> f64 {
+	let sum: f32 = frames.iter().map(|[l, r]| (l * l + r * r) / 2.0).sum();
+	let rms = (sum / frames.len().max(1) as f32).sqrt();
+	20.0 * f64::from(rms.max(1e-9)).log10()
+}
+
+/// A speakerphone that can't talk and listen at once, ducking the near end
+/// into the encoder while the far end talks
+pub struct HalfDuplex {
+	pub enabled: bool,
+	/// Level either end must reach to count as talking, in dBFS
+	pub threshold_db: f64,
+	/// How long the far end keeps the line after going quiet, in ms
+	pub hold_ms: f64,
+	/// Near end gain while the far end has the line, in dB
+	pub depth_db: f64,
+	/// Milliseconds of hold left
+	held: f64,
+	/// Gain at the end of the last packet, ramped from so the ducking doesn't click
+	gain: f32,
+	talker: u8,
+	reported: u8,
+}
+
+impl Default for HalfDuplex {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			threshold_db: -40.0,
+			hold_ms: 200.0,
+			depth_db: MIN_DEPTH_DB,
+			held: 0.0,
+			gain: 1.0,
+			talker: NOBODY,
+			reported: NOBODY,
+		}
+	}
+}
+
+impl HalfDuplex {
+	/// Decide who has the line from the dry near end and the far end packets,
+	/// then duck the packet about to be encoded
+	pub fn gate(
+		&mut self,
+		near: &[[f32; 2]],
+		far: Option<&[[f32; 2]]>,
+		packet: &mut [[f32; 2]],
+		packet_ms: f64,
+	) {
+		let far_talking = far.is_some_and(|far| rms_db(far) > self.threshold_db);
+		if far_talking {
+			self.held = self.hold_ms;
+		} else {
+			self.held = (self.held - packet_ms).max(0.0);
+		}
+		let far_has_line = far_talking || self.held > 0.0;
+
+		self.talker = if far_has_line {
+			FAR_END
+		} else if rms_db(near) > self.threshold_db {
+			NEAR_END
+		} else {
+			NOBODY
+		};
+
+		let target = if !self.enabled || !far_has_line {
+			1.0
+		} else if self.depth_db <= MIN_DEPTH_DB {
+			0.0
+		} else {
+			10f32.powf(self.depth_db as f32 / 20.0)
+		};
+		let start = self.gain;
+		let len = packet.len().max(1) as f32;
+		for (i, frame) in packet.iter_mut().enumerate() {
+			let gain = start + (target - start) * (i + 1) as f32 / len;
+			frame[0] *= gain;
+			frame[1] *= gain;
+		}
+		self.gain = target;
+	}
+
+	/// Index into TALKERS
+	pub fn talker(&self) -> u8 {
+		self.talker
+	}
+
+	/// Talker if it changed since it was last reported
+	pub fn take_talker_change(&mut self) -> Option<u8> {
+		if self.talker == self.reported {
+			return None;
+		}
+		self.reported = self.talker;
+		Some(self.talker)
+	}
+
+	/// Open the line, nobody is talking
+	pub fn reset(&mut self) {
+		self.held = 0.0;
+		self.gain = 1.0;
+		self.talker = NOBODY;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn far_end_gates_near_end() {
+		let mut duplex = HalfDuplex {
+			enabled: true,
+			..HalfDuplex::default()
+		};
+		let near = [[0.5f32; 2]; 960];
+		let far = [[0.5f32; 2]; 960];
+		let mut packet = near;
+		duplex.gate(&near, Some(&far), &mut packet, 20.0);
+		assert_eq!(duplex.talker(), FAR_END);
+		assert_eq!(packet[959], [0.0; 2]);
+
+		// The hold keeps the gate shut for a packet after the far end stops
+		let quiet = [[0.0f32; 2]; 960];
+		let mut packet = near;
+		duplex.gate(&near, Some(&quiet), &mut packet, 20.0);
+		assert_eq!(packet[0], [0.0; 2]);
+		for _ in 0..10 {
+			packet = near;
+			duplex.gate(&near, Some(&quiet), &mut packet, 20.0);
+		}
+		assert_eq!(duplex.talker(), NEAR_END);
+		assert_eq!(packet[959], near[959]);
+	}
+}
@@ -0,0 +1,78 @@
+/// Longest echo delay, in seconds
+pub const MAX_DELAY_SECS: f64 = 0.5;
+
+/// Decoded audio fed back into the encoder input, like a far-end speakerphone
+/// leaking into its own microphone
+pub struct Echo {
+	/// Gain of the returned signal, 0 turns the path off
+	pub level: f32,
+	/// Milliseconds between leaving the decoder and reaching the encoder
+	pub delay_ms: f64,
+	/// How much the return path dulls high frequencies, 0 to 1
+	pub damping: f32,
+	rate: f64,
+	line: Vec<[f32; 2]>,
+	/// Where the next decoded frame goes
+	write: usize,
+	lowpass: [f32; 2],
+}
+
+impl Default for Echo {
+	fn default() -> Self {
+		Self {
+			level: 0.0,
+			delay_ms: 120.0,
+			damping: 0.5,
+			rate: 48000.0,
+			line: Vec::new(),
+			write: 0,
+			lowpass: [0.0; 2],
+		}
+	}
+}
+
+impl Echo {
+	/// Size the delay line for the coder rate, which allocates
+	pub fn set_rate(&mut self, rate: f64) {
+		self.rate = rate;
+		self.line = vec![[0.0; 2]; (rate * MAX_DELAY_SECS) as usize + 1];
+		self.write = 0;
+		self.lowpass = [0.0; 2];
+	}
+
+	/// A packet is decoded after it's encoded, so the echo is at least one packet late
+	fn delay_frames(&self, packet_len: usize) -> usize {
+		let frames = (self.delay_ms * self.rate / 1000.0) as usize;
+		frames.max(packet_len).min(self.line.len() - 1)
+	}
+
+	/// Mix the delayed output into a packet about to be encoded
+	pub fn feed(&self, frames: &mut [[f32; 2]]) {
+		if self.level <= 0.0 || self.line.is_empty() {
+			return;
+		}
+		let len = self.line.len();
+		let delay = self.delay_frames(frames.len());
+		for (i, frame) in frames.iter_mut().enumerate() {
+			let echo = self.line[(self.write + len + i - delay) % len];
+			frame[0] += echo[0] * self.level;
+			frame[1] += echo[1] * self.level;
+		}
+	}
+
+	/// Record a decoded packet for the return path
+	pub fn capture(&mut self, frames: &[[f32; 2]]) {
+		if self.line.is_empty() {
+			return;
+		}
+		let len = self.line.len();
+		let coefficient = 1.0 - self.damping.clamp(0.0, 0.95);
+		for frame in frames {
+			for (state, sample) in self.lowpass.iter_mut().zip(frame) {
+				*state += (sample - *state) * coefficient;
+			}
+			self.line[self.write] = self.lowpass;
+			self.write = (self.write + 1) % len;
+		}
+	}
+}
@@ -0,0 +1,169 @@
+use super::dsp::PACKET_RATE;
+
+/// Coding modes, in parameter order, None until a packet went out
+pub const MODES: [&str; 4] = ["None", "SILK", "Hybrid", "CELT"];
+
+/// Audio bandwidths, in parameter order
+pub const BANDWIDTH_NAMES: [&str; 6] = ["None", "NB", "MB", "WB", "SWB", "FB"];
+
+/// Most frames a packet can hold, 120 ms of 2.5 ms frames
+pub const MAX_FRAMES: u8 = 48;
+
+/// Packets this short only carry the table of contents, there is nothing to send during DTX
+const DTX_BYTES: usize = 2;
+
+/// What the encoder actually chose for a packet, which VBR, DTX, the layer
+/// and the governor can move away from the knobs
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+	/// Index into MODES
+	pub mode: u8,
+	/// Index into BANDWIDTH_NAMES
+	pub bandwidth: u8,
+	pub frames: u8,
+}
+
+impl Config {
+	/// Read from the packet's table of contents byte, RFC 6716 section 3.1
+	pub fn parse(packet: &[u8]) -> Option<Self> {
+		let toc = *packet.first()?;
+		let config = toc >> 3;
+		let (mode, bandwidth) = match config {
+			0..=11 => (1, 1 + config / 4),
+			12..=15 => (2, 4 + (config - 12) / 2),
+			_ => (3, [1, 3, 4, 5][usize::from(config - 16) / 4]),
+		};
+		let frames = match toc & 0b11 {
+			0 => 1,
+			1 | 2 => 2,
+			_ => packet.get(1)? & 0b11_1111,
+		};
+		Some(Self {
+			mode,
+			bandwidth,
+			frames,
+		})
+	}
+}
+
+impl std::fmt::Display for Config {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} {} x{}",
+			MODES[usize::from(self.mode)],
+			BANDWIDTH_NAMES[usize::from(self.bandwidth)],
+			self.frames
+		)
+	}
+}
+
+/// The encoder's latest choices, published about once a second
+#[derive(Default)]
+pub struct EffectiveConfig {
+	latest: Config,
+	published: Config,
+	reported: Config,
+	packets: usize,
+	/// The last packet was a DTX packet
+	in_dtx: bool,
+	reported_dtx: bool,
+}
+
+impl EffectiveConfig {
+	/// Look at a packet on its way out of the encoder
+	pub fn record(&mut self, packet: &[u8]) {
+		self.in_dtx = packet.len() <= DTX_BYTES;
+		if let Some(config) = Config::parse(packet) {
+			self.latest = config;
+		}
+		self.packets += 1;
+		if self.packets >= PACKET_RATE {
+			self.packets = 0;
+			self.published = self.latest;
+		}
+	}
+
+	/// Config last published
+	pub fn get(&self) -> Config {
+		self.published
+	}
+
+	/// Config if it changed since it was last reported
+	pub fn take_change(&mut self) -> Option<Config> {
+		if self.published == self.reported {
+			return None;
+		}
+		self.reported = self.published;
+		Some(self.published)
+	}
+
+	/// The encoder is in discontinuous transmission
+	pub fn in_dtx(&self) -> bool {
+		self.in_dtx
+	}
+
+	/// DTX state if it changed since it was last reported, right away unlike the config
+	pub fn take_dtx_change(&mut self) -> Option<bool> {
+		if self.in_dtx == self.reported_dtx {
+			return None;
+		}
+		self.reported_dtx = self.in_dtx;
+		Some(self.in_dtx)
+	}
+
+	/// Forget what the packets showed
+	pub fn reset(&mut self) {
+		self.in_dtx = false;
+		self.latest = Config::default();
+		self.published = Config::default();
+		self.packets = 0;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_reads_the_toc() {
+		// SILK wideband, one frame
+		assert_eq!(
+			Config::parse(&[9 << 3]),
+			Some(Config {
+				mode: 1,
+				bandwidth: 3,
+				frames: 1
+			})
+		);
+		// Hybrid fullband, two frames
+		assert_eq!(
+			Config::parse(&[15 << 3 | 1]),
+			Some(Config {
+				mode: 2,
+				bandwidth: 5,
+				frames: 2
+			})
+		);
+		// CELT wideband, frame count in the second byte
+		assert_eq!(
+			Config::parse(&[21 << 3 | 3, 6]),
+			Some(Config {
+				mode: 3,
+				bandwidth: 3,
+				frames: 6
+			})
+		);
+		assert_eq!(Config::parse(&[]), None);
+	}
+
+	#[test]
+	fn short_packets_are_dtx() {
+		let mut effective = EffectiveConfig::default();
+		effective.record(&[9 << 3]);
+		assert_eq!(effective.take_dtx_change(), Some(true));
+		effective.record(&[9 << 3, 0, 0]);
+		assert_eq!(effective.take_dtx_change(), Some(false));
+		assert_eq!(effective.take_dtx_change(), None);
+	}
+}
@@ -0,0 +1,330 @@
+use super::loss::LossCause;
+use log::*;
+use ringbuf::{Consumer, Producer, RingBuffer};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+#[cfg(feature = "vst3")]
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
+use std::ptr::null_mut;
+#[cfg(feature = "vst3")]
+use std::slice;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "vst3")]
+use vst3_com::{ComInterface, ComPtr};
+#[cfg(feature = "vst3")]
+use vst3_sys::base::kResultOk;
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IConnectionPoint, IHostApplication, IMessage};
+
+/// Something the DSP did, for tools that follow a session closer than the log
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+	/// A packet came out of the encoder, in bytes
+	Encoded(usize),
+	/// A packet was concealed
+	Lost(LossCause),
+	/// The latency the host is told, in frames
+	Latency(usize),
+	/// The governor lowered complexity to this
+	Governor(u8),
+}
+
+impl Event {
+	/// The "kind" attribute
+	fn kind(self) -> i64 {
+		match self {
+			Self::Encoded(_) => 0,
+			Self::Lost(_) => 1,
+			Self::Latency(_) => 2,
+			Self::Governor(_) => 3,
+		}
+	}
+
+	/// The "value" attribute, a loss is its index in LossCause::ALL
+	fn value(self) -> i64 {
+		match self {
+			Self::Encoded(bytes) => bytes as i64,
+			Self::Lost(cause) => {
+				LossCause::ALL.iter().position(|x| *x == cause).unwrap_or(0) as i64
+			}
+			Self::Latency(frames) => frames as i64,
+			Self::Governor(complexity) => i64::from(complexity),
+		}
+	}
+
+	fn from_attributes(kind: i64, value: i64) -> Option<Self> {
+		Some(match kind {
+			0 => Self::Encoded(usize::try_from(value).ok()?),
+			1 => Self::Lost(*LossCause::ALL.get(usize::try_from(value).ok()?)?),
+			2 => Self::Latency(usize::try_from(value).ok()?),
+			3 => Self::Governor(u8::try_from(value).ok()?),
+			_ => return None,
+		})
+	}
+}
+
+/// Events kept between pulls, and by the controller
+pub const CAPACITY: usize = 256;
+
+/// Events waiting for the controller, in a ring allocated up front so the audio
+/// thread records without allocating or locking. Only recorded at debug verbosity
+pub struct EventLog {
+	events: Producer<(f64, Event)>,
+	/// The other end, until the processor takes it
+	reader: Option<EventReader>,
+	/// Events that didn't fit since the last pull
+	dropped: Arc<AtomicU32>,
+	/// Events recorded so far, the output parameter that has the controller pull them
+	recorded: u32,
+	reported: u32,
+}
+
+impl Default for EventLog {
+	fn default() -> Self {
+		let (events, consumer) = RingBuffer::new(CAPACITY).split();
+		let dropped = Arc::new(AtomicU32::new(0));
+		Self {
+			events,
+			reader: Some(EventReader {
+				events: Mutex::new(consumer),
+				dropped: dropped.clone(),
+			}),
+			dropped,
+			recorded: 0,
+			reported: 0,
+		}
+	}
+}
+
+impl EventLog {
+	/// Events are only worth recording when debug logging is on
+	pub fn is_enabled() -> bool {
+		log::max_level() >= LevelFilter::Debug
+	}
+
+	/// Keep an event, stamped with the project time in seconds
+	pub fn record(&mut self, time: f64, event: Event) {
+		if !Self::is_enabled() {
+			return;
+		}
+		self.recorded = self.recorded.wrapping_add(1);
+		if self.events.push((time, event)).is_err() {
+			self.dropped.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	/// Events recorded so far, wrapping
+	pub fn recorded(&self) -> u32 {
+		self.recorded
+	}
+
+	/// The count if events were recorded since it was last reported
+	pub fn take_change(&mut self) -> Option<u32> {
+		if self.recorded == self.reported {
+			return None;
+		}
+		self.reported = self.recorded;
+		Some(self.recorded)
+	}
+
+	/// The end notify() drains, once
+	pub fn take_reader(&mut self) -> Option<EventReader> {
+		self.reader.take()
+	}
+}
+
+/// Drains an EventLog off the audio thread. Only notify() pops, so the lock is never contended
+pub struct EventReader {
+	events: Mutex<Consumer<(f64, Event)>>,
+	dropped: Arc<AtomicU32>,
+}
+
+impl EventReader {
+	/// Events in the order they happened
+	pub fn drain(&self) -> Recent {
+		let mut recent = Recent::with_capacity(CAPACITY);
+		if let Ok(mut events) = self.events.lock() {
+			while let Some((time, event)) = events.pop() {
+				recent.push_back((time, event));
+			}
+		}
+		let dropped = self.dropped.swap(0, Ordering::Relaxed);
+		if dropped > 0 {
+			debug!("events: dropped {} events", dropped);
+		}
+		recent
+	}
+}
+
+/// The latest events, as the controller keeps them
+pub type Recent = VecDeque<(f64, Event)>;
+
+/// Keep an event, forgetting the oldest past CAPACITY
+pub fn keep(recent: &mut Recent, time: f64, event: Event) {
+	if recent.len() >= CAPACITY {
+		recent.pop_front();
+	}
+	recent.push_back((time, event));
+}
+
+const REQUEST_ID: &[u8] = b"OpusEvents\0";
+const PULL_ID: &[u8] = b"OpusEventPull\0";
+const EVENTS_ATTRIBUTE_ID: &[u8] = b"events\0";
+
+/// Bytes per event in an answer
+pub const RECORD_BYTES: usize = 24;
+
+/// Ask the processor for the events recorded since the last pull. From the controller,
+/// when the recorded count it's sent as an output parameter moves
+#[cfg(feature = "vst3")]
+pub unsafe fn pull(context: *mut c_void, peer: *mut c_void) -> Recent {
+	if context.is_null() || peer.is_null() {
+		return Recent::new();
+	}
+
+	let host: ComPtr<dyn IHostApplication> = ComPtr::new(context as *mut *mut _);
+	let mut cid = <dyn IMessage as ComInterface>::IID;
+	let mut iid = <dyn IMessage as ComInterface>::IID;
+	let mut message = null_mut();
+	let result = host.create_instance(&mut cid, &mut iid, &mut message);
+	if result != kResultOk || message.is_null() {
+		warn!("events: host can't create messages");
+		return Recent::new();
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	message.set_message_id(PULL_ID.as_ptr() as *const _);
+
+	let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+	let mut recent = Recent::new();
+	if peer.notify(message.as_raw() as *mut c_void) == kResultOk {
+		let attributes = message.get_attributes();
+		if !attributes.is_null() {
+			let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+			let mut data = std::ptr::null();
+			let mut size = 0;
+			let id = EVENTS_ATTRIBUTE_ID.as_ptr() as *const _;
+			if attributes.get_binary(id, &mut data, &mut size) == kResultOk && !data.is_null() {
+				recent = decode(slice::from_raw_parts(data as *const u8, size as usize));
+			}
+		}
+	}
+	message.release();
+	recent
+}
+
+/// Fill in a pull from the controller with what the DSP recorded since the last one
+#[cfg(feature = "vst3")]
+pub unsafe fn answer_pull(message: *mut c_void, reader: &EventReader) -> bool {
+	fill(message, PULL_ID, || reader.drain())
+}
+
+/// Fill in a request for the latest events, the sender reads the "events" attribute
+/// once notify returns: per event a little endian f64 time, i64 kind, and i64 value
+#[cfg(feature = "vst3")]
+pub unsafe fn answer(message: *mut c_void, recent: &Recent) -> bool {
+	fill(message, REQUEST_ID, || recent.clone())
+}
+
+#[cfg(feature = "vst3")]
+unsafe fn fill(message: *mut c_void, request_id: &[u8], events: impl FnOnce() -> Recent) -> bool {
+	if message.is_null() {
+		return false;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != request_id {
+		return false;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		warn!("events: request has no attributes");
+		return false;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let bytes = encode(&events());
+	attributes.set_binary(
+		EVENTS_ATTRIBUTE_ID.as_ptr() as *const _,
+		bytes.as_ptr() as *const c_void,
+		bytes.len() as u32,
+	);
+	true
+}
+
+fn encode(recent: &Recent) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(recent.len() * RECORD_BYTES);
+	for (time, event) in recent.iter() {
+		bytes.extend_from_slice(&time.to_le_bytes());
+		bytes.extend_from_slice(&event.kind().to_le_bytes());
+		bytes.extend_from_slice(&event.value().to_le_bytes());
+	}
+	bytes
+}
+
+fn decode(bytes: &[u8]) -> Recent {
+	let field = |record: &[u8], at: usize| {
+		let mut field = [0; 8];
+		field.copy_from_slice(&record[at..at + 8]);
+		field
+	};
+	bytes
+		.chunks_exact(RECORD_BYTES)
+		.filter_map(|record| {
+			let time = f64::from_le_bytes(field(record, 0));
+			let kind = i64::from_le_bytes(field(record, 8));
+			let value = i64::from_le_bytes(field(record, 16));
+			Some((time, Event::from_attributes(kind, value)?))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn attributes_round_trip() {
+		let events = [
+			Event::Encoded(160),
+			Event::Lost(LossCause::Manual),
+			Event::Latency(1024),
+			Event::Governor(4),
+		];
+		for event in events.iter() {
+			assert_eq!(
+				Event::from_attributes(event.kind(), event.value()),
+				Some(*event)
+			);
+		}
+
+		let mut recent = Recent::new();
+		for i in 0..CAPACITY + 1 {
+			keep(&mut recent, i as f64, Event::Encoded(i));
+		}
+		assert_eq!(recent.front(), Some(&(1.0, Event::Encoded(1))));
+		assert_eq!(encode(&recent).len(), CAPACITY * RECORD_BYTES);
+		assert_eq!(decode(&encode(&recent)), recent);
+	}
+	#[test]
+	fn the_reader_drains_what_was_recorded() {
+		log::set_max_level(LevelFilter::Debug);
+		let mut log = EventLog::default();
+		let reader = log.take_reader().unwrap();
+		for i in 0..CAPACITY + 2 {
+			log.record(i as f64, Event::Encoded(i));
+		}
+		assert_eq!(log.take_change(), Some((CAPACITY + 2) as u32));
+		assert_eq!(log.take_change(), None);
+
+		let recent = reader.drain();
+		assert_eq!(recent.len(), CAPACITY);
+		assert_eq!(recent.front(), Some(&(0.0, Event::Encoded(0))));
+		assert!(reader.drain().is_empty());
+	}
+}
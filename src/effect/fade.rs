@@ -0,0 +1,90 @@
+/// Length of a fade, in ms
+const FADE_MS: f64 = 5.0;
+
+/// Gain ramp on the output when processing stops and starts again, so the last
+/// samples before a stop and the first after it don't click
+pub struct Fade {
+	gain: f32,
+	target: f32,
+	/// Gain change per frame
+	step: f32,
+}
+
+impl Default for Fade {
+	fn default() -> Self {
+		Self {
+			gain: 1.0,
+			target: 1.0,
+			step: 1.0,
+		}
+	}
+}
+
+impl Fade {
+	/// Fade steps for the host rate
+	pub fn set_rate(&mut self, rate: f64) {
+		self.step = (1000.0 / (FADE_MS * rate)).min(1.0) as f32;
+	}
+
+	/// Ramp down over the next blocks, if the host still sends any
+	pub fn fade_out(&mut self) {
+		self.target = 0.0;
+	}
+
+	/// Ramp up from silence
+	pub fn fade_in(&mut self) {
+		self.gain = 0.0;
+		self.target = 1.0;
+	}
+
+	/// Skip what's left of a fade out
+	pub fn mute(&mut self) {
+		self.gain = 0.0;
+		self.target = 0.0;
+	}
+
+	/// Fading out or already silent
+	pub fn is_stopping(&self) -> bool {
+		self.target == 0.0
+	}
+
+	/// Faded all the way out, the output is silent
+	pub fn is_muted(&self) -> bool {
+		self.gain == 0.0 && self.target == 0.0
+	}
+
+	/// Apply the gain to a block, moving it towards the target
+	pub fn apply(&mut self, output: [&mut [f32]; 2]) {
+		let [out0, out1] = output;
+		if self.gain == self.target && self.gain == 1.0 {
+			return;
+		}
+		for (l, r) in out0.iter_mut().zip(out1.iter_mut()) {
+			if self.gain < self.target {
+				self.gain = (self.gain + self.step).min(self.target);
+			} else if self.gain > self.target {
+				self.gain = (self.gain - self.step).max(self.target);
+			}
+			*l *= self.gain;
+			*r *= self.gain;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fades_out_to_silence() {
+		let mut fade = Fade::default();
+		fade.set_rate(1000.0);
+		fade.fade_out();
+		let mut out0 = [1f32; 8];
+		let mut out1 = [1f32; 8];
+		fade.apply([&mut out0, &mut out1]);
+		assert!(out0[0] > 0.0 && out0[0] < 1.0);
+		assert_eq!(out0[7], 0.0);
+		assert!(fade.is_muted());
+	}
+}
@@ -0,0 +1,159 @@
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+/// High-pass slopes, in parameter order
+pub const SLOPES: [&str; 3] = ["Off", "12 dB/oct", "24 dB/oct"];
+
+/// Range of the high-pass cutoff, in Hz
+pub const MIN_CUTOFF: f64 = 50.0;
+pub const MAX_CUTOFF: f64 = 300.0;
+
+/// First order emphasis coefficient, as in the AMR-WB front end
+const EMPHASIS: f32 = 0.68;
+
+/// Q of each stage, one stage is a 2nd order Butterworth, two make a 4th order one
+const Q: [[f64; 2]; 2] = [[FRAC_1_SQRT_2, 0.0], [0.5412, 1.3066]];
+
+/// Second order section, transposed direct form II, per channel state
+#[derive(Copy, Clone, Default)]
+struct Biquad {
+	b: [f32; 3],
+	a: [f32; 2],
+	z: [[f32; 2]; 2],
+}
+
+impl Biquad {
+	/// Coefficients from the Audio EQ Cookbook, the state is kept so moving the cutoff doesn't click
+	fn design_highpass(&mut self, rate: f64, cutoff: f64, q: f64) {
+		let w = 2.0 * PI * cutoff / rate;
+		let alpha = w.sin() / (2.0 * q);
+		let cos = w.cos();
+		let a0 = 1.0 + alpha;
+		self.b = [
+			((1.0 + cos) / 2.0 / a0) as f32,
+			(-(1.0 + cos) / a0) as f32,
+			((1.0 + cos) / 2.0 / a0) as f32,
+		];
+		self.a = [(-2.0 * cos / a0) as f32, ((1.0 - alpha) / a0) as f32];
+	}
+
+	fn process(&mut self, channel: usize, x: f32) -> f32 {
+		let z = &mut self.z[channel];
+		let y = self.b[0] * x + z[0];
+		z[0] = self.b[1] * x - self.a[0] * y + z[1];
+		z[1] = self.b[2] * x - self.a[1] * y;
+		y
+	}
+}
+
+/// Filtering a handset applies around its codec, a high-pass and pre-emphasis before
+/// encoding, and de-emphasis after decoding
+pub struct Handset {
+	/// High-pass cutoff in Hz
+	pub cutoff: f64,
+	/// Index into SLOPES
+	pub slope: usize,
+	pub emphasis: bool,
+	rate: f64,
+	stages: [Biquad; 2],
+	/// Rate, cutoff and slope the stages were designed for
+	designed: (f64, f64, usize),
+	/// Last input frame, for pre-emphasis
+	previous_input: [f32; 2],
+	/// Last output frame, for de-emphasis
+	previous_output: [f32; 2],
+}
+
+impl Default for Handset {
+	fn default() -> Self {
+		Self {
+			cutoff: 100.0,
+			slope: 0,
+			emphasis: false,
+			rate: 48000.0,
+			stages: [Biquad::default(); 2],
+			designed: (0.0, 0.0, 0),
+			previous_input: [0.0; 2],
+			previous_output: [0.0; 2],
+		}
+	}
+}
+
+impl Handset {
+	/// Rate the filters are designed for
+	pub fn set_rate(&mut self, rate: f64) {
+		self.rate = rate;
+		self.reset();
+	}
+
+	/// Clear the filter state, they are designed again on the next packet
+	pub fn reset(&mut self) {
+		self.stages = [Biquad::default(); 2];
+		self.designed = (0.0, 0.0, 0);
+		self.previous_input = [0.0; 2];
+		self.previous_output = [0.0; 2];
+	}
+
+	/// High-pass then pre-emphasis, on a packet about to be encoded
+	pub fn filter_input(&mut self, frames: &mut [[f32; 2]]) {
+		let stages = self.slope.min(SLOPES.len() - 1);
+		if stages > 0 {
+			if self.designed != (self.rate, self.cutoff, stages) {
+				let cutoff = self.cutoff.clamp(MIN_CUTOFF, MAX_CUTOFF);
+				for (stage, q) in self.stages.iter_mut().zip(Q[stages - 1].iter()) {
+					stage.design_highpass(self.rate, cutoff, *q);
+				}
+				self.designed = (self.rate, self.cutoff, stages);
+			}
+			for frame in frames.iter_mut() {
+				for (channel, sample) in frame.iter_mut().enumerate() {
+					for stage in self.stages[..stages].iter_mut() {
+						*sample = stage.process(channel, *sample);
+					}
+				}
+			}
+		}
+
+		if self.emphasis {
+			for frame in frames.iter_mut() {
+				for (sample, previous) in frame.iter_mut().zip(self.previous_input.iter_mut()) {
+					let x = *sample;
+					*sample = x - EMPHASIS * *previous;
+					*previous = x;
+				}
+			}
+		}
+	}
+
+	/// De-emphasis, on a decoded packet, undoing the pre-emphasis tilt
+	pub fn filter_output(&mut self, frames: &mut [[f32; 2]]) {
+		if !self.emphasis {
+			return;
+		}
+		for frame in frames.iter_mut() {
+			for (sample, previous) in frame.iter_mut().zip(self.previous_output.iter_mut()) {
+				*sample += EMPHASIS * *previous;
+				*previous = *sample;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn emphasis_round_trips() {
+		let mut handset = Handset {
+			emphasis: true,
+			..Handset::default()
+		};
+		let input: Vec<[f32; 2]> = (0..64).map(|i| [(i as f32 * 0.3).sin(), 0.5]).collect();
+		let mut frames = input.clone();
+		handset.filter_input(&mut frames);
+		handset.filter_output(&mut frames);
+		for (a, b) in input.iter().zip(frames.iter()) {
+			assert!((a[0] - b[0]).abs() < 1e-5 && (a[1] - b[1]).abs() < 1e-5);
+		}
+	}
+}
@@ -0,0 +1,189 @@
+use super::coloration::ImpulseFile;
+use super::curve::Curve;
+use super::dsp::OpusDSP;
+use super::null::NullCheck;
+use super::params::Parameter;
+use super::warnings;
+use enum_map::EnumMap;
+use ringbuf::{Consumer, Producer, RingBuffer};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Files in flight to process(). Every delivery comes back once and notify() collects
+/// what came back before it sends another, so the way back never fills either
+const DELIVERIES: usize = 4;
+
+/// Loaded by notify(), off the audio thread, and swapped into the DSP whole.
+/// What it replaced goes back the same way, to be dropped off the audio thread
+pub enum Delivery {
+	/// The reference and the warning it raises, None resolves it
+	Null(Box<NullCheck>, Option<String>),
+	/// Needs a convolver for the DSP's rate, so it waits for a spare
+	Impulse(Option<Arc<ImpulseFile>>, Option<String>),
+}
+
+/// What the controller sends through notify(), which runs beside process() on another
+/// thread, so it waits here for process() instead of being written into the DSP.
+/// One atomic per parameter, like SharedParams, and ring buffers for files. Each end
+/// of a ring is only used from one side, so its lock is never contended
+pub struct Inbox {
+	curves: EnumMap<Parameter, AtomicU8>,
+	curve_changed: EnumMap<Parameter, AtomicBool>,
+	locks: EnumMap<Parameter, AtomicBool>,
+	lock_changed: EnumMap<Parameter, AtomicBool>,
+	sent: Mutex<Producer<Delivery>>,
+	received: Mutex<Consumer<Delivery>>,
+	returned: Mutex<Producer<Delivery>>,
+	collected: Mutex<Consumer<Delivery>>,
+}
+
+impl Default for Inbox {
+	fn default() -> Self {
+		let (sent, received) = RingBuffer::new(DELIVERIES).split();
+		let (returned, collected) = RingBuffer::new(DELIVERIES).split();
+		Self {
+			curves: Default::default(),
+			curve_changed: Default::default(),
+			locks: Default::default(),
+			lock_changed: Default::default(),
+			sent: Mutex::new(sent),
+			received: Mutex::new(received),
+			returned: Mutex::new(returned),
+			collected: Mutex::new(collected),
+		}
+	}
+}
+
+impl Inbox {
+	/// Send a curve to process(), the latest per parameter wins
+	pub fn set_curve(&self, param: Parameter, curve: Curve) {
+		self.curves[param].store(curve.into(), Ordering::Release);
+		self.curve_changed[param].store(true, Ordering::Release);
+	}
+
+	/// Send a lock to process(), the latest per parameter wins
+	pub fn set_lock(&self, param: Parameter, locked: bool) {
+		self.locks[param].store(locked, Ordering::Release);
+		self.lock_changed[param].store(true, Ordering::Release);
+	}
+
+	/// Send a file to process(), false when too many are in flight. Not for the audio thread
+	pub fn deliver(&self, delivery: Delivery) -> bool {
+		if let Ok(mut collected) = self.collected.lock() {
+			while let Some(old) = collected.pop() {
+				drop(old);
+			}
+		}
+		match self.sent.lock() {
+			Ok(mut sent) => sent.push(delivery).is_ok(),
+			Err(_) => false,
+		}
+	}
+
+	/// Hand what arrived since the last call to the playing DSP and its spare. An impulse
+	/// response replaces the one in `impulse`, which waits for a spare to be built with it
+	pub fn apply(
+		&self,
+		dsp: &mut OpusDSP,
+		mut spare: Option<&mut OpusDSP>,
+		impulse: &mut Option<Arc<ImpulseFile>>,
+	) {
+		for (param, changed) in self.curve_changed.iter() {
+			if changed.swap(false, Ordering::AcqRel) {
+				let curve = self.curves[param].load(Ordering::Acquire);
+				let curve = Curve::try_from(curve).unwrap_or_default();
+				dsp.curves[param] = curve;
+				if let Some(spare) = spare.as_deref_mut() {
+					spare.curves[param] = curve;
+				}
+			}
+		}
+		for (param, changed) in self.lock_changed.iter() {
+			if changed.swap(false, Ordering::AcqRel) {
+				let locked = self.locks[param].load(Ordering::Acquire);
+				dsp.locks[param] = locked;
+				if let Some(spare) = spare.as_deref_mut() {
+					spare.locks[param] = locked;
+				}
+			}
+		}
+
+		let (mut received, mut returned) =
+			match (self.received.try_lock(), self.returned.try_lock()) {
+				(Ok(received), Ok(returned)) => (received, returned),
+				_ => return,
+			};
+		while let Some(delivery) = received.pop() {
+			let old = match delivery {
+				// The spare has no reference, the playing DSP keeps its own when it takes over
+				Delivery::Null(mut null, line) => {
+					std::mem::swap(&mut dsp.null, &mut *null);
+					let line = dsp.warnings.replace(warnings::NULL, line);
+					Delivery::Null(null, line)
+				}
+				Delivery::Impulse(mut file, line) => {
+					std::mem::swap(impulse, &mut file);
+					let line = dsp.warnings.replace(warnings::IMPULSE, line);
+					Delivery::Impulse(file, line)
+				}
+			};
+			// Can't happen, see DELIVERIES
+			let _ = returned.push(old);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn curves_wait_for_apply() {
+		let inbox = Inbox::default();
+		let mut dsp = OpusDSP::default();
+		inbox.set_curve(Parameter::RandomLoss, Curve::Log);
+		assert_eq!(dsp.curves[Parameter::RandomLoss], Curve::Linear);
+
+		inbox.apply(&mut dsp, None, &mut None);
+		assert_eq!(dsp.curves[Parameter::RandomLoss], Curve::Log);
+	}
+
+	#[test]
+	fn locks_reach_the_spare() {
+		let inbox = Inbox::default();
+		let (mut dsp, mut spare) = (OpusDSP::default(), OpusDSP::default());
+		inbox.set_lock(Parameter::RandomLoss, true);
+
+		inbox.apply(&mut dsp, Some(&mut spare), &mut None);
+		assert!(dsp.locks[Parameter::RandomLoss]);
+		assert!(spare.locks[Parameter::RandomLoss]);
+	}
+
+	#[test]
+	fn files_come_back_to_be_dropped() {
+		let inbox = Inbox::default();
+		let mut dsp = OpusDSP::default();
+		for _ in 0..2 * DELIVERIES {
+			let line = Some("null reference: missing".to_string());
+			assert!(inbox.deliver(Delivery::Null(Box::new(NullCheck::default()), line)));
+			inbox.apply(&mut dsp, None, &mut None);
+		}
+		assert!(dsp.warnings.is_active());
+		assert_eq!(inbox.collected.lock().unwrap().len(), 1);
+	}
+	#[test]
+	fn impulse_responses_wait_for_a_spare() {
+		let inbox = Inbox::default();
+		let mut dsp = OpusDSP::default();
+		let (file, result) = ImpulseFile::open("no such impulse.wav");
+		let line = result.err().map(|err| err.to_string());
+		assert!(inbox.deliver(Delivery::Impulse(Some(Arc::new(file)), line)));
+
+		let mut waiting = None;
+		inbox.apply(&mut dsp, None, &mut waiting);
+		assert!(waiting.is_some());
+		assert_eq!(dsp.coloration.impulse_path(), None);
+		assert!(dsp.warnings.is_active());
+	}
+}
@@ -0,0 +1,83 @@
+/// Warning names, in parameter order
+pub const WARNINGS: [&str; 3] = ["OK", "Too Hot", "Too Quiet"];
+
+const OK: u8 = 0;
+const HOT: u8 = 1;
+const QUIET: u8 = 2;
+
+/// Samples this close to full scale count as clipping
+const CLIP: f32 = 0.99;
+
+/// Below this a packet is speech quiet enough for SILK's voice detection to gate,
+/// 20 * log10(0.0018) is about -55 dBFS
+const QUIET_RMS: f32 = 0.0018;
+
+/// Below this a packet is silence, which isn't a level problem
+const SILENT_RMS: f32 = 0.00001;
+
+/// Packets the averages run over, about two seconds
+const AVERAGE: f32 = 100.0;
+
+/// Share of recent packets that must clip, or sit quiet, to warn
+const TRIGGER: f32 = 0.25;
+
+/// Watches the encoder input for levels the codec can't handle well
+#[derive(Default)]
+pub struct LevelWatch {
+	hot: f32,
+	quiet: f32,
+	warning: u8,
+	reported: u8,
+}
+
+impl LevelWatch {
+	/// Measure one packet on its way into the encoder
+	pub fn record(&mut self, frames: &[[f32; 2]]) {
+		let mut peak = 0f32;
+		let mut sum = 0f32;
+		for frame in frames {
+			for sample in frame {
+				peak = peak.max(sample.abs());
+				sum += sample * sample;
+			}
+		}
+		let rms = (sum / (2 * frames.len()).max(1) as f32).sqrt();
+
+		let hot = (peak >= CLIP) as u8 as f32;
+		self.hot += (hot - self.hot) / AVERAGE;
+		// Silence leaves the quiet average alone, pauses between words are normal
+		if rms > SILENT_RMS {
+			let quiet = (rms < QUIET_RMS) as u8 as f32;
+			self.quiet += (quiet - self.quiet) / AVERAGE;
+		}
+
+		self.warning = if self.hot > TRIGGER {
+			HOT
+		} else if self.quiet > TRIGGER {
+			QUIET
+		} else {
+			OK
+		};
+	}
+
+	/// Current level warning
+	pub fn warning(&self) -> u8 {
+		self.warning
+	}
+
+	/// Warning if it changed since it was last reported
+	pub fn take_warning_change(&mut self) -> Option<u8> {
+		if self.warning == self.reported {
+			return None;
+		}
+		self.reported = self.warning;
+		Some(self.warning)
+	}
+
+	/// Forget the measured levels and clear the warning
+	pub fn reset(&mut self) {
+		self.hot = 0.0;
+		self.quiet = 0.0;
+		self.warning = OK;
+	}
+}
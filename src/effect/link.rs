@@ -0,0 +1,485 @@
+use super::dsp::MAX_PACKET_BYTES;
+use log::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::Ipv4Addr;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+/// What the instance does with the link, in parameter order
+pub const MODES: [&str; 3] = ["Off", "Sender", "Receiver"];
+
+const OFF: usize = 0;
+const SENDER: usize = 1;
+const RECEIVER: usize = 2;
+
+/// Ports the link can use, below this needs privileges
+pub const MIN_PORT: u16 = 1024;
+pub const MAX_PORT: u16 = 65535;
+pub const DEFAULT_PORT: u16 = 47000;
+
+/// Sequence number in front of each packet
+const HEADER: usize = 4;
+
+/// Packets the receiver holds to put reordered ones back in order
+const QUEUE_LEN: usize = 4;
+
+/// Packets waited for one that's missing, before playing on without it
+const HOLD: usize = 1;
+
+/// Time between attempts to open a socket that failed
+const RETRY: Duration = Duration::from_secs(1);
+
+/// Jobs and sockets in flight between the audio thread and the opener
+const JOBS: usize = 8;
+
+/// A sequence number this far behind means the sender started over
+const RESTART_GAP: u32 = 50;
+
+/// A packet waiting to be played
+#[derive(Copy, Clone)]
+struct Slot {
+	sequence: Option<u32>,
+	len: usize,
+	bytes: [u8; MAX_PACKET_BYTES],
+}
+
+/// Numbers the links, so the opener knows whose socket it binds
+static NEXT_LINK: AtomicU64 = AtomicU64::new(0);
+
+/// Work for the opener thread, binding and closing block so neither happens in process()
+enum Job {
+	/// A link and where its sockets go
+	Attach(u64, SyncSender<Opened>),
+	Detach(u64),
+	Open(u64, usize, u16),
+	Close(UdpSocket),
+}
+
+/// A socket the opener bound for a mode and port
+struct Opened {
+	mode: usize,
+	port: u16,
+	socket: UdpSocket,
+}
+
+/// A thread that opens and closes sockets for all the links of a processor, the playing
+/// DSP's and its spares', so a DSP doesn't need a thread of its own
+#[derive(Clone)]
+pub struct Opener {
+	jobs: SyncSender<Job>,
+}
+
+impl Opener {
+	/// Start the thread, which allocates. None where there are no threads
+	pub fn start() -> Option<Self> {
+		let (jobs, job_receiver) = sync_channel(JOBS);
+		let thread = thread::Builder::new()
+			.name("opus-parvulum-link".into())
+			.spawn(move || run_opener(job_receiver));
+		match thread {
+			Ok(_) => Some(Self { jobs }),
+			Err(err) => {
+				warn!("link: can't start the opener: {}", err);
+				None
+			}
+		}
+	}
+}
+
+/// Experimental pairing of two instances over UDP. The sender broadcasts the packets
+/// its impairments let through, the receiver decodes those instead of its own
+pub struct Link {
+	/// Who the opener's sockets are for
+	id: u64,
+	mode: usize,
+	port: u16,
+	socket: Option<UdpSocket>,
+	/// Jobs for the opener thread, None until attached or where there are no threads
+	jobs: Option<SyncSender<Job>>,
+	opened: Option<Receiver<Opened>>,
+	/// Mode and port the opener was last asked for
+	requested: Option<(usize, u16)>,
+	/// Next sequence number to send
+	sent: u32,
+	/// Last sequence number played, older packets arrived too late
+	played: Option<u32>,
+	queue: [Slot; QUEUE_LEN],
+	/// Failed sends, logged sparingly
+	errors: u32,
+}
+
+impl Default for Link {
+	fn default() -> Self {
+		Self {
+			id: NEXT_LINK.fetch_add(1, AtomicOrdering::Relaxed),
+			mode: OFF,
+			port: DEFAULT_PORT,
+			socket: None,
+			jobs: None,
+			opened: None,
+			requested: None,
+			sent: 0,
+			played: None,
+			queue: [Slot {
+				sequence: None,
+				len: 0,
+				bytes: [0; MAX_PACKET_BYTES],
+			}; QUEUE_LEN],
+			errors: 0,
+		}
+	}
+}
+
+impl Link {
+	/// Index into MODES
+	pub fn mode(&self) -> usize {
+		self.mode
+	}
+
+	/// Switch mode, which closes the sockets for the opener to reopen
+	pub fn set_mode(&mut self, mode: usize) {
+		let mode = mode.min(MODES.len() - 1);
+		if mode != self.mode {
+			self.mode = mode;
+			self.close();
+		}
+	}
+
+	/// Port the link sends to or listens on
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+
+	/// Switch port, which closes the sockets for the opener to reopen
+	pub fn set_port(&mut self, port: u16) {
+		if port != self.port {
+			self.port = port;
+			self.close();
+		}
+	}
+
+	/// Packets go out over the link
+	pub fn is_sender(&self) -> bool {
+		self.mode == SENDER
+	}
+
+	/// Packets come in over the link
+	pub fn is_receiver(&self) -> bool {
+		self.mode == RECEIVER
+	}
+
+	/// Have `opener` open and close this link's sockets, which allocates and may wait
+	/// for the opener, so it's not for the audio thread
+	pub fn attach(&mut self, opener: &Opener) {
+		if self.jobs.is_some() {
+			return;
+		}
+		let (opened_sender, opened) = sync_channel(JOBS);
+		if opener
+			.jobs
+			.send(Job::Attach(self.id, opened_sender))
+			.is_ok()
+		{
+			self.jobs = Some(opener.jobs.clone());
+			self.opened = Some(opened);
+		}
+	}
+
+	/// Hand the socket to the opener to close, and forget what was queued for it
+	fn close(&mut self) {
+		self.requested = None;
+		self.played = None;
+		for slot in self.queue.iter_mut() {
+			slot.sequence = None;
+		}
+		if let Some(socket) = self.socket.take() {
+			self.retire(socket);
+		}
+	}
+
+	/// Closing a socket can block, so it's closed on the audio thread only when the
+	/// opener is gone or falling behind
+	fn retire(&self, socket: UdpSocket) {
+		if let Some(jobs) = &self.jobs {
+			if let Err(TrySendError::Full(_)) = jobs.try_send(Job::Close(socket)) {
+				debug!("link: opener is busy, closed a socket in process()");
+			}
+		}
+	}
+
+	/// Take a socket the opener finished, and ask it for one when the mode or port changed.
+	/// Neither waits, a full channel is tried again on the next packet
+	fn poll_opener(&mut self) {
+		let wanted = (self.mode, self.port);
+		if let Some(opened) = &self.opened {
+			while let Ok(next) = opened.try_recv() {
+				if self.socket.is_none() && (next.mode, next.port) == wanted {
+					info!("link: {} on port {}", MODES[next.mode], next.port);
+					self.socket = Some(next.socket);
+				} else {
+					self.retire(next.socket);
+				}
+			}
+		}
+
+		if self.requested != Some(wanted) {
+			if let Some(jobs) = &self.jobs {
+				if jobs
+					.try_send(Job::Open(self.id, self.mode, self.port))
+					.is_ok()
+				{
+					self.requested = Some(wanted);
+				}
+			}
+		}
+	}
+
+	/// Broadcast a packet to receivers on the link port
+	pub fn send(&mut self, packet: &[u8]) {
+		self.poll_opener();
+		let socket = match &self.socket {
+			Some(socket) => socket,
+			None => return,
+		};
+
+		let mut datagram = [0u8; HEADER + MAX_PACKET_BYTES];
+		let len = packet.len().min(MAX_PACKET_BYTES);
+		datagram[..HEADER].copy_from_slice(&self.sent.to_le_bytes());
+		datagram[HEADER..HEADER + len].copy_from_slice(&packet[..len]);
+		self.sent = self.sent.wrapping_add(1);
+
+		let address = (Ipv4Addr::BROADCAST, self.port);
+		if let Err(err) = socket.send_to(&datagram[..HEADER + len], address) {
+			self.errors = self.errors.saturating_add(1);
+			if self.errors.is_power_of_two() {
+				warn!("link: {} sends failed, last: {}", self.errors, err);
+			}
+		}
+	}
+
+	/// The next packet from the sender into `bytes`, None when it hasn't arrived.
+	/// While one is missing, up to HOLD later packets wait for it before it's skipped
+	pub fn receive(&mut self, bytes: &mut [u8; MAX_PACKET_BYTES]) -> Option<usize> {
+		self.poll_opener();
+		self.drain();
+
+		let queued = self
+			.queue
+			.iter()
+			.filter(|slot| slot.sequence.is_some())
+			.count();
+		let played = self.played;
+		let (sequence, slot) = self
+			.queue
+			.iter_mut()
+			.filter_map(|slot| slot.sequence.map(|sequence| (sequence, slot)))
+			.min_by(|(a, _), (b, _)| compare(*a, *b))?;
+		let is_next = played.is_none_or(|played| sequence == played.wrapping_add(1));
+		if !is_next && queued <= HOLD {
+			return None;
+		}
+		self.played = slot.sequence.take();
+		bytes[..slot.len].copy_from_slice(&slot.bytes[..slot.len]);
+		Some(slot.len)
+	}
+
+	/// Queue whatever arrived, a full queue drops its oldest packet
+	fn drain(&mut self) {
+		let socket = match &self.socket {
+			Some(socket) => socket,
+			None => return,
+		};
+
+		let mut datagram = [0u8; HEADER + MAX_PACKET_BYTES];
+		loop {
+			let len = match socket.recv(&mut datagram) {
+				Ok(len) if len > HEADER => len,
+				Ok(_) => continue,
+				Err(err) if err.kind() == ErrorKind::WouldBlock => return,
+				Err(err) => {
+					debug!("link: receive failed: {}", err);
+					return;
+				}
+			};
+			let mut header = [0u8; HEADER];
+			header.copy_from_slice(&datagram[..HEADER]);
+			let sequence = u32::from_le_bytes(header);
+			match self.played {
+				Some(played) if played.wrapping_sub(sequence) < RESTART_GAP => continue,
+				Some(played) if compare(sequence, played) != Ordering::Greater => {
+					self.played = None
+				}
+				_ => {}
+			}
+
+			let slot = match self.queue.iter().position(|slot| slot.sequence.is_none()) {
+				Some(free) => free,
+				None => (0..QUEUE_LEN)
+					.filter_map(|i| self.queue[i].sequence.map(|sequence| (i, sequence)))
+					.min_by(|(_, a), (_, b)| compare(*a, *b))
+					.map_or(0, |(i, _)| i),
+			};
+			let slot = &mut self.queue[slot];
+			slot.sequence = Some(sequence);
+			slot.len = len - HEADER;
+			slot.bytes[..slot.len].copy_from_slice(&datagram[HEADER..len]);
+		}
+	}
+}
+
+impl Drop for Link {
+	fn drop(&mut self) {
+		if let Some(socket) = self.socket.take() {
+			self.retire(socket);
+		}
+		if let Some(jobs) = &self.jobs {
+			let _ = jobs.try_send(Job::Detach(self.id));
+		}
+	}
+}
+
+/// Order of two sequence numbers, across the wrap from u32::MAX back to 0
+fn compare(a: u32, b: u32) -> Ordering {
+	(a.wrapping_sub(b) as i32).cmp(&0)
+}
+
+/// Bind for a mode, the sender to any port, the receiver to the link port
+fn open(mode: usize, port: u16) -> std::io::Result<UdpSocket> {
+	let port = if mode == SENDER { 0 } else { port };
+	let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+	socket.set_nonblocking(true)?;
+	socket.set_broadcast(mode == SENDER)?;
+	Ok(socket)
+}
+
+/// What the opener knows about a link
+struct Served {
+	opened: SyncSender<Opened>,
+	/// Mode and port to open, until a socket for them was handed over
+	wanted: Option<(usize, u16)>,
+	failures: u32,
+}
+
+/// Opens the sockets the links last asked for, retrying while another instance may
+/// still hold the port, like the DSP a spare is replacing. Ends when the processor
+/// and all its links are dropped
+fn run_opener(jobs: Receiver<Job>) {
+	let mut links = HashMap::<u64, Served>::new();
+	loop {
+		let job = if links.values().any(|link| link.wanted.is_some()) {
+			jobs.recv_timeout(RETRY)
+		} else {
+			jobs.recv().map_err(|_| RecvTimeoutError::Disconnected)
+		};
+		match job {
+			Ok(Job::Attach(id, opened)) => {
+				let link = Served {
+					opened,
+					wanted: None,
+					failures: 0,
+				};
+				links.insert(id, link);
+			}
+			Ok(Job::Detach(id)) => {
+				links.remove(&id);
+			}
+			Ok(Job::Open(id, mode, port)) => {
+				if let Some(link) = links.get_mut(&id) {
+					link.wanted = if mode == OFF {
+						None
+					} else {
+						Some((mode, port))
+					};
+					link.failures = 0;
+				}
+			}
+			Ok(Job::Close(socket)) => drop(socket),
+			Err(RecvTimeoutError::Timeout) => {}
+			Err(RecvTimeoutError::Disconnected) => return,
+		}
+
+		// A link whose DSP was dropped stops being served
+		links.retain(|_, link| open_wanted(link));
+	}
+}
+
+/// Try to open what the link wants, false when the link is gone
+fn open_wanted(link: &mut Served) -> bool {
+	let (mode, port) = match link.wanted {
+		Some(wanted) => wanted,
+		None => return true,
+	};
+	match open(mode, port) {
+		Ok(socket) => {
+			link.wanted = None;
+			// A link that stopped polling doesn't need it anymore
+			let opened = Opened { mode, port, socket };
+			if let Err(TrySendError::Disconnected(_)) = link.opened.try_send(opened) {
+				return false;
+			}
+		}
+		Err(err) => {
+			link.failures = link.failures.saturating_add(1);
+			if link.failures.is_power_of_two() {
+				warn!(
+					"link: can't open a {} on port {}, {} tries: {}",
+					MODES[mode], port, link.failures, err
+				);
+			}
+		}
+	}
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn queue(link: &mut Link, sequence: u32) {
+		let slot = link.queue.iter_mut().find(|slot| slot.sequence.is_none());
+		let slot = slot.unwrap();
+		slot.sequence = Some(sequence);
+		slot.len = 1;
+		slot.bytes[0] = sequence as u8;
+	}
+
+	#[test]
+	fn holds_a_packet_while_one_is_missing() {
+		let mut link = Link::default();
+		let mut bytes = [0u8; MAX_PACKET_BYTES];
+		queue(&mut link, 0);
+		assert_eq!(link.receive(&mut bytes), Some(1));
+
+		// 1 is late, 2 waits for it
+		queue(&mut link, 2);
+		assert_eq!(link.receive(&mut bytes), None);
+		queue(&mut link, 1);
+		assert_eq!(link.receive(&mut bytes).map(|_| bytes[0]), Some(1));
+		assert_eq!(link.receive(&mut bytes).map(|_| bytes[0]), Some(2));
+
+		// 3 never comes, 4 plays once 5 arrived behind it
+		queue(&mut link, 4);
+		assert_eq!(link.receive(&mut bytes), None);
+		queue(&mut link, 5);
+		assert_eq!(link.receive(&mut bytes).map(|_| bytes[0]), Some(4));
+	}
+	#[test]
+	fn orders_packets_across_the_wrap() {
+		let mut link = Link::default();
+		let mut bytes = [0u8; MAX_PACKET_BYTES];
+		queue(&mut link, u32::MAX - 1);
+		assert_eq!(link.receive(&mut bytes).map(|_| bytes[0]), Some(254));
+
+		// 0 comes after u32::MAX, which is late
+		queue(&mut link, 0);
+		assert_eq!(link.receive(&mut bytes), None);
+		queue(&mut link, u32::MAX);
+		assert_eq!(link.receive(&mut bytes).map(|_| bytes[0]), Some(255));
+		assert_eq!(link.receive(&mut bytes).map(|_| bytes[0]), Some(0));
+	}
+}
@@ -0,0 +1,263 @@
+use super::params::Parameter;
+use std::env;
+
+/// Parameter title, short title, and units
+#[derive(Copy, Clone, Debug)]
+pub struct Text {
+	pub title: &'static str,
+	pub short_title: &'static str,
+	pub units: &'static str,
+}
+
+/// Language of the names and units shown to the host
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Locale {
+	English,
+	German,
+	Japanese,
+}
+
+impl Locale {
+	/// Parse a POSIX or BCP 47 style tag, like "de_DE.UTF-8" or "ja-JP"
+	pub fn from_tag(tag: &str) -> Option<Self> {
+		let language = tag.split(&['_', '-', '.'][..]).next()?;
+		match language.to_ascii_lowercase().as_str() {
+			"en" => Some(Self::English),
+			"de" => Some(Self::German),
+			"ja" => Some(Self::Japanese),
+			_ => None,
+		}
+	}
+
+	/// IHostApplication only reports the host name, so the locale comes from the environment
+	pub fn detect() -> Self {
+		["LC_ALL", "LC_MESSAGES", "LANG", "LANGUAGE"]
+			.iter()
+			.filter_map(|key| env::var(key).ok())
+			.find_map(|tag| Self::from_tag(&tag))
+			.unwrap_or(Self::English)
+	}
+
+	/// Separator shown between whole and fractional digits
+	pub fn decimal_separator(self) -> char {
+		match self {
+			Self::German => ',',
+			_ => '.',
+		}
+	}
+}
+
+/// Text for a parameter in the given locale, None when there is no translation
+pub fn translate(param: Parameter, locale: Locale) -> Option<Text> {
+	let (title, short_title, units) = match locale {
+		Locale::English => return None,
+		Locale::German => german(param)?,
+		Locale::Japanese => japanese(param)?,
+	};
+
+	Some(Text {
+		title,
+		short_title,
+		units,
+	})
+}
+
+type Entry = (&'static str, &'static str, &'static str);
+
+fn german(param: Parameter) -> Option<Entry> {
+	let entry = match param {
+		Parameter::Bypass => ("Bypass", "", ""),
+		Parameter::MaxBandwith => ("Max. Bandbreite", "Band", "kHz"),
+		Parameter::Complexity => ("Komplexität", "Kmpx", ""),
+		Parameter::PredictedLoss => ("Erwarteter Verlust", "ErVl", "%"),
+		Parameter::RandomLoss => ("Zufallsverlust", "ZfVl", "%"),
+		Parameter::RoundRobinLoss => ("Reihum-Verlust", "RhVl", "%"),
+		Parameter::Freeze => ("Einfrieren", "Einfr", ""),
+		Parameter::Repeat => ("Wiederholung", "Wdh", "x"),
+		Parameter::Pattern => ("Muster", "Mstr", ""),
+		Parameter::PatternLength => ("Musterlänge", "MsLn", "Schritte"),
+		Parameter::EuclidSteps => ("Euklid Schritte", "EuSt", "Pakete"),
+		Parameter::EuclidPulses => ("Euklid Ausfälle", "EuAf", "Pakete"),
+		Parameter::EuclidRotation => ("Euklid Rotation", "EuRt", "Pakete"),
+		Parameter::Latency => ("Latenz", "Ltnz", "Samples"),
+		Parameter::Governor => ("Auto-Komplexität", "AuKx", ""),
+		Parameter::Xruns => ("Aussetzer", "Auss", ""),
+		Parameter::OfflineQuality => ("Offline-Qualität", "OfQl", ""),
+		Parameter::InternalRate => ("Interne Rate", "Rate", "kHz"),
+		Parameter::DecodeFec => ("FEC dekodieren", "FEC", ""),
+		Parameter::Verbosity => ("Debug-Ausführlichkeit", "Dbg", ""),
+		Parameter::RestoreSnapshot => ("Sicherung wiederherstellen", "WdHr", ""),
+		Parameter::InternalEdits => ("Interne Änderungen", "IÄnd", ""),
+		Parameter::Coloration => ("Gerät", "Gert", ""),
+		Parameter::EchoLevel => ("Echo-Pegel", "EcPg", "%"),
+		Parameter::EchoDelay => ("Echo-Verzögerung", "EcVz", "ms"),
+		Parameter::EchoDamping => ("Echo-Dämpfung", "EcDp", "%"),
+		Parameter::NoiseSnr => ("Rauschabstand", "RsAb", "dB"),
+		Parameter::CodecLayer => ("Codec-Schicht", "Schi", ""),
+		Parameter::NetworkEnable => ("Netzwerk aktiv", "Netz", ""),
+		Parameter::LevelWarning => ("Pegelwarnung", "Pegl", ""),
+		Parameter::LostPattern => ("Verlust durch Muster", "VlMu", ""),
+		Parameter::LostEuclidean => ("Verlust durch Euklid", "VlEu", ""),
+		Parameter::LostRandom => ("Verlust durch Zufall", "VlZu", ""),
+		Parameter::LostOversized => ("Verlust durch Übergröße", "VlÜb", ""),
+		Parameter::LowRateFallback => ("Niedrige Rate ausweichen", "NRAw", ""),
+		Parameter::EffectiveMode => ("Tatsächlicher Modus", "Mod", ""),
+		Parameter::EffectiveBandwidth => ("Tatsächliche Bandbreite", "TBbr", ""),
+		Parameter::EffectiveFrames => ("Frames pro Paket", "Frms", ""),
+		Parameter::ComplexityUp => ("Komplexität hoch", "Kmp+", ""),
+		Parameter::ComplexityDown => ("Komplexität runter", "Kmp-", ""),
+		Parameter::BandwidthUp => ("Bandbreite hoch", "Bbr+", ""),
+		Parameter::BandwidthDown => ("Bandbreite runter", "Bbr-", ""),
+		Parameter::FreezeSync => ("Einfrieren synchron", "EfSy", ""),
+		Parameter::HighPassCutoff => ("Hochpass-Grenzfrequenz", "HPF", "Hz"),
+		Parameter::HighPassSlope => ("Hochpass-Steilheit", "HPSt", ""),
+		Parameter::Emphasis => ("Vorverzerrung", "Vorv", ""),
+		Parameter::Monitor => ("Abhören", "Abhö", ""),
+		Parameter::MaxPacketBytes => ("Max. Paketgröße", "MTU", "B"),
+		Parameter::OversizedPackets => ("Übergroße Pakete", "Überg", ""),
+		Parameter::HalfDuplex => ("Halbduplex", "HDpx", ""),
+		Parameter::DuplexThreshold => ("Duplex-Schwelle", "DxSw", "dB"),
+		Parameter::DuplexHold => ("Duplex-Haltezeit", "DxHz", "ms"),
+		Parameter::DuplexDepth => ("Duplex-Tiefe", "DxTf", "dB"),
+		Parameter::Talker => ("Sprecher", "Spr", ""),
+		Parameter::DropNow => ("Jetzt verwerfen", "Verw", ""),
+		Parameter::LostManual => ("Verlust durch Verwerfen", "VlVw", ""),
+		Parameter::OfflineComplexity => ("Offline-Komplexität", "OfKx", ""),
+		Parameter::Warning => ("Warnung", "Warn", ""),
+		Parameter::CpuLoad => ("CPU-Last", "CPU", "%"),
+		Parameter::LossSeed => ("Verlust-Startwert", "Seed", ""),
+		Parameter::LinkMode => ("Verbindung", "Verb", ""),
+		Parameter::LinkPort => ("Verbindungsport", "Port", ""),
+		Parameter::LostLink => ("Verlust auf Verbindung", "VlVb", ""),
+		Parameter::SwapChannels => ("L/R tauschen", "Tsch", ""),
+		Parameter::InvertLeft => ("Links invertieren", "InvL", ""),
+		Parameter::InvertRight => ("Rechts invertieren", "InvR", ""),
+		Parameter::MonoSum => ("Monosumme", "Mono", ""),
+		Parameter::AbCompare => ("A/B-Vergleich", "A/B", ""),
+		Parameter::RateControl => ("Ratensteuerung", "Rate", ""),
+		Parameter::AdaptiveBitrate => ("Adaptive Bitrate", "AdBr", ""),
+		Parameter::AdaptiveMin => ("Adaptive Untergrenze", "AdUg", "kbps"),
+		Parameter::AdaptiveMax => ("Adaptive Obergrenze", "AdOg", "kbps"),
+		Parameter::AdaptiveAttack => ("Adaptiver Anstieg", "AdAn", "ms"),
+		Parameter::AdaptiveRelease => ("Adaptiver Abfall", "AdAb", "ms"),
+		Parameter::FrameDuration => ("Rahmendauer", "RmDr", "ms"),
+		Parameter::TimeWarp => ("Zeitdehnung", "Dehn", "%"),
+		Parameter::NullResult => ("Nulltest", "Null", ""),
+		Parameter::NullDifference => ("Nulldifferenz", "NlDf", "dB"),
+		Parameter::Dtx => ("DTX", "DTX", ""),
+		Parameter::InDtx => ("In DTX", "InDx", ""),
+		Parameter::SignalHint => ("Signalhinweis", "Sig", ""),
+		Parameter::ForceChannels => ("Kanäle erzwingen", "KErz", ""),
+		Parameter::PredictionDisabled => ("Prädiktion aus", "PrAus", ""),
+		Parameter::LossInstance => ("Verlust-Instanz", "Inst", ""),
+		Parameter::PhaseInversionDisabled => ("Phaseninversion aus", "PhAus", ""),
+		Parameter::Preroll => ("Encoder-Vorlauf", "Vorl", ""),
+		Parameter::AdaptiveNarrowing => ("Adaptive Verengung", "Veng", ""),
+		Parameter::DecoderGain => ("Decoder-Verstärkung", "DVst", "dB"),
+		Parameter::InbandFec => ("Inband-FEC", "IFEC", ""),
+		Parameter::LostRoundRobin => ("Verlust durch Reihum", "VlRh", ""),
+		Parameter::RecordedEvents => ("Aufgezeichnete Ereignisse", "AEre", ""),
+		param if param.step().is_some() => ("Schritt", "S", ""),
+		_ => return None,
+	};
+	Some(entry)
+}
+
+fn japanese(param: Parameter) -> Option<Entry> {
+	let entry = match param {
+		Parameter::Bypass => ("バイパス", "", ""),
+		Parameter::MaxBandwith => ("最大帯域幅", "帯域", "kHz"),
+		Parameter::Complexity => ("複雑度", "複雑", ""),
+		Parameter::PredictedLoss => ("予測損失", "予測", "%"),
+		Parameter::RandomLoss => ("ランダム損失", "乱損", "%"),
+		Parameter::RoundRobinLoss => ("ラウンドロビン損失", "RR損", "%"),
+		Parameter::Freeze => ("フリーズ", "凍結", ""),
+		Parameter::Repeat => ("リピート", "反復", "回"),
+		Parameter::Pattern => ("パターン", "パタ", ""),
+		Parameter::PatternLength => ("パターン長", "長さ", "ステップ"),
+		Parameter::EuclidSteps => ("ユークリッド ステップ", "ユSt", "パケット"),
+		Parameter::EuclidPulses => ("ユークリッド 損失", "ユ損", "パケット"),
+		Parameter::EuclidRotation => ("ユークリッド 回転", "ユ回", "パケット"),
+		Parameter::Latency => ("レイテンシー", "遅延", "サンプル"),
+		Parameter::Governor => ("自動複雑度", "自動", ""),
+		Parameter::Xruns => ("音切れ", "音切", ""),
+		Parameter::OfflineQuality => ("オフライン品質", "オフ", ""),
+		Parameter::InternalRate => ("内部レート", "レート", "kHz"),
+		Parameter::DecodeFec => ("FECデコード", "FEC", ""),
+		Parameter::Verbosity => ("デバッグ詳細度", "Dbg", ""),
+		Parameter::RestoreSnapshot => ("スナップショット復元", "復元", ""),
+		Parameter::InternalEdits => ("内部変更", "内変", ""),
+		Parameter::Coloration => ("デバイス", "機器", ""),
+		Parameter::EchoLevel => ("エコーレベル", "エコ", "%"),
+		Parameter::EchoDelay => ("エコー遅延", "エ遅", "ms"),
+		Parameter::EchoDamping => ("エコー減衰", "エ減", "%"),
+		Parameter::NoiseSnr => ("ノイズSN比", "SN比", "dB"),
+		Parameter::CodecLayer => ("コーデック層", "層", ""),
+		Parameter::NetworkEnable => ("ネットワーク有効", "網", ""),
+		Parameter::LevelWarning => ("レベル警告", "警告", ""),
+		Parameter::LostPattern => ("パターン損失数", "パ損", ""),
+		Parameter::LostEuclidean => ("ユークリッド損失数", "ユ損", ""),
+		Parameter::LostRandom => ("ランダム損失数", "ラ損", ""),
+		Parameter::LostOversized => ("過大パケット損失数", "過損", ""),
+		Parameter::LowRateFallback => ("低レート代替", "低代", ""),
+		Parameter::EffectiveMode => ("実際のモード", "モード", ""),
+		Parameter::EffectiveBandwidth => ("実際の帯域幅", "実帯", ""),
+		Parameter::EffectiveFrames => ("パケットあたりフレーム数", "フレ", ""),
+		Parameter::ComplexityUp => ("複雑度を上げる", "複+", ""),
+		Parameter::ComplexityDown => ("複雑度を下げる", "複-", ""),
+		Parameter::BandwidthUp => ("帯域幅を上げる", "帯+", ""),
+		Parameter::BandwidthDown => ("帯域幅を下げる", "帯-", ""),
+		Parameter::FreezeSync => ("フリーズ同期", "同期", ""),
+		Parameter::HighPassCutoff => ("ハイパス周波数", "HPF", "Hz"),
+		Parameter::HighPassSlope => ("ハイパス傾斜", "傾斜", ""),
+		Parameter::Emphasis => ("プリエンファシス", "強調", ""),
+		Parameter::Monitor => ("モニター", "モニ", ""),
+		Parameter::MaxPacketBytes => ("最大パケットサイズ", "MTU", "B"),
+		Parameter::OversizedPackets => ("超過パケット", "超過", ""),
+		Parameter::HalfDuplex => ("半二重", "半二重", ""),
+		Parameter::DuplexThreshold => ("半二重しきい値", "しきい", "dB"),
+		Parameter::DuplexHold => ("半二重ホールド", "ホールド", "ms"),
+		Parameter::DuplexDepth => ("半二重深さ", "深さ", "dB"),
+		Parameter::Talker => ("話者", "話者", ""),
+		Parameter::DropNow => ("今すぐドロップ", "ドロップ", ""),
+		Parameter::LostManual => ("手動パケット損失数", "手損", ""),
+		Parameter::OfflineComplexity => ("オフライン複雑度", "オフ複", ""),
+		Parameter::Warning => ("警告", "警告", ""),
+		Parameter::CpuLoad => ("CPU負荷", "CPU", "%"),
+		Parameter::LossSeed => ("損失シード", "シード", ""),
+		Parameter::LinkMode => ("リンク", "リンク", ""),
+		Parameter::LinkPort => ("リンクポート", "ポート", ""),
+		Parameter::LostLink => ("リンク損失数", "リ損", ""),
+		Parameter::SwapChannels => ("左右入れ替え", "入替", ""),
+		Parameter::InvertLeft => ("左チャンネル反転", "左反", ""),
+		Parameter::InvertRight => ("右チャンネル反転", "右反", ""),
+		Parameter::MonoSum => ("モノラル合成", "モノ", ""),
+		Parameter::AbCompare => ("A/B比較", "A/B", ""),
+		Parameter::RateControl => ("レート制御", "レート", ""),
+		Parameter::AdaptiveBitrate => ("適応ビットレート", "適応", ""),
+		Parameter::AdaptiveMin => ("適応下限", "下限", "kbps"),
+		Parameter::AdaptiveMax => ("適応上限", "上限", "kbps"),
+		Parameter::AdaptiveAttack => ("適応アタック", "アタック", "ms"),
+		Parameter::AdaptiveRelease => ("適応リリース", "リリース", "ms"),
+		Parameter::FrameDuration => ("フレーム長", "フレ長", "ms"),
+		Parameter::TimeWarp => ("タイムワープ", "ワープ", "%"),
+		Parameter::NullResult => ("ヌルテスト", "ヌル", ""),
+		Parameter::NullDifference => ("ヌル差分", "差分", "dB"),
+		Parameter::Dtx => ("DTX", "DTX", ""),
+		Parameter::InDtx => ("DTX中", "DTX中", ""),
+		Parameter::SignalHint => ("信号ヒント", "信号", ""),
+		Parameter::ForceChannels => ("チャンネル固定", "固定", ""),
+		Parameter::PredictionDisabled => ("予測無効", "予測", ""),
+		Parameter::LossInstance => ("損失インスタンス", "インスト", ""),
+		Parameter::PhaseInversionDisabled => ("位相反転無効", "位相", ""),
+		Parameter::Preroll => ("エンコーダープリロール", "プリロ", ""),
+		Parameter::AdaptiveNarrowing => ("適応帯域制限", "帯制", ""),
+		Parameter::DecoderGain => ("デコーダーゲイン", "Dゲイン", "dB"),
+		Parameter::InbandFec => ("インバンドFEC", "IFEC", ""),
+		Parameter::LostRoundRobin => ("ラウンドロビン損失数", "ロ損", ""),
+		Parameter::RecordedEvents => ("記録イベント数", "記録", ""),
+		param if param.step().is_some() => ("ステップ", "S", ""),
+		_ => return None,
+	};
+	Some(entry)
+}
@@ -0,0 +1,146 @@
+use super::dsp::ParamQueueMap;
+use super::params::Parameter;
+use enum_map::EnumMap;
+use log::*;
+use std::convert::TryFrom;
+#[cfg(feature = "vst3")]
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
+use std::ptr::null_mut;
+#[cfg(feature = "vst3")]
+use vst3_com::{ComInterface, ComPtr};
+#[cfg(feature = "vst3")]
+use vst3_sys::base::{kResultFalse, kResultOk, tresult};
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IConnectionPoint, IHostApplication, IMessage};
+
+/// Parameters whose host automation the DSP ignores
+pub type Locks = EnumMap<Parameter, bool>;
+
+/// Drop the queues of locked parameters, the host still shows their automation
+/// but the DSP keeps the value it had
+pub fn ignore_locked(locks: &Locks, params: &mut ParamQueueMap) {
+	for (param, queue) in params.iter_mut() {
+		if locks[param] {
+			*queue = None;
+		}
+	}
+}
+
+/// Key of the locks in saved state
+pub const STATE_KEY: &str = "locks";
+
+/// Ids of the locked parameters
+pub fn encode(locks: &Locks) -> String {
+	locks
+		.iter()
+		.filter(|(_, locked)| **locked)
+		.map(|(param, _)| u32::from(param).to_string())
+		.collect::<Vec<_>>()
+		.join(",")
+}
+
+/// Unknown parameters are skipped, so newer sessions still load
+pub fn decode(string: &str) -> Locks {
+	let mut locks = Locks::default();
+	for id in string.split(',').filter(|id| !id.is_empty()) {
+		match id.parse::<u32>().ok().map(Parameter::try_from) {
+			Some(Ok(param)) => locks[param] = true,
+			_ => warn!("locks: skipped {:?}", id),
+		}
+	}
+	locks
+}
+
+const MESSAGE_ID: &[u8] = b"OpusLock\0";
+const PARAM_ATTRIBUTE_ID: &[u8] = b"id\0";
+const LOCKED_ATTRIBUTE_ID: &[u8] = b"locked\0";
+
+/// Tell the peer a parameter was locked or unlocked
+#[cfg(feature = "vst3")]
+pub unsafe fn send(
+	context: *mut c_void,
+	peer: *mut c_void,
+	param: Parameter,
+	locked: bool,
+) -> tresult {
+	if context.is_null() || peer.is_null() {
+		return kResultFalse;
+	}
+
+	let host: ComPtr<dyn IHostApplication> = ComPtr::new(context as *mut *mut _);
+	let mut cid = <dyn IMessage as ComInterface>::IID;
+	let mut iid = <dyn IMessage as ComInterface>::IID;
+	let mut message = null_mut();
+	let result = host.create_instance(&mut cid, &mut iid, &mut message);
+	if result != kResultOk || message.is_null() {
+		warn!("locks: host can't create messages");
+		return result;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	message.set_message_id(MESSAGE_ID.as_ptr() as *const _);
+
+	let attributes = message.get_attributes();
+	if !attributes.is_null() {
+		let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+		let id = PARAM_ATTRIBUTE_ID.as_ptr() as *const _;
+		attributes.set_int(id, i64::from(u32::from(param)));
+		let id = LOCKED_ATTRIBUTE_ID.as_ptr() as *const _;
+		attributes.set_int(id, i64::from(locked));
+	}
+
+	let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+	let result = peer.notify(message.as_raw() as *mut c_void);
+	message.release();
+	result
+}
+
+/// The parameter and whether it's locked from a lock message, None for any other message
+#[cfg(feature = "vst3")]
+pub unsafe fn receive(message: *mut c_void) -> Option<(Parameter, bool)> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut param = 0;
+	let mut locked = 0;
+	let id = PARAM_ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_int(id, &mut param) != kResultOk {
+		return None;
+	}
+	let id = LOCKED_ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_int(id, &mut locked) != kResultOk {
+		return None;
+	}
+
+	let param = Parameter::try_from(u32::try_from(param).ok()?).ok()?;
+	Some((param, locked != 0))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decode_inverts_encode() {
+		let mut locks = Locks::default();
+		locks[Parameter::Complexity] = true;
+		locks[Parameter::Dtx] = true;
+		assert_eq!(decode(&encode(&locks)), locks);
+		assert_eq!(decode("99999,,x"), Locks::default());
+	}
+}
@@ -0,0 +1,323 @@
+use enum_map::Enum;
+use enum_map::EnumMap;
+
+/// Why a packet was concealed, the first cause that applies is recorded
+#[derive(Copy, Clone, Debug, PartialEq, Enum)]
+pub enum LossCause {
+	Pattern,
+	Euclidean,
+	Random,
+	/// Too large for the packet buffer
+	Oversized,
+	/// Dropped by hand with Drop Now
+	Manual,
+	/// Didn't arrive from the sender instance
+	Link,
+	RoundRobin,
+}
+
+impl LossCause {
+	pub const ALL: [LossCause; 7] = [
+		LossCause::Pattern,
+		LossCause::Euclidean,
+		LossCause::Random,
+		LossCause::Oversized,
+		LossCause::Manual,
+		LossCause::Link,
+		LossCause::RoundRobin,
+	];
+}
+
+/// Concealed packets by cause
+#[derive(Default)]
+pub struct LossCounts {
+	counts: EnumMap<LossCause, u32>,
+	reported: EnumMap<LossCause, u32>,
+}
+
+impl LossCounts {
+	/// Count a lost packet
+	pub fn record(&mut self, cause: LossCause) {
+		self.counts[cause] = self.counts[cause].saturating_add(1);
+	}
+
+	/// Packets lost to a cause since the last reset
+	pub fn count(&self, cause: LossCause) -> u32 {
+		self.counts[cause]
+	}
+
+	/// Count for a cause if it moved since it was last reported
+	pub fn take_change(&mut self, cause: LossCause) -> Option<u32> {
+		if self.counts[cause] == self.reported[cause] {
+			return None;
+		}
+		self.reported[cause] = self.counts[cause];
+		Some(self.counts[cause])
+	}
+
+	/// Start counting again, the cleared counts are still reported
+	pub fn reset(&mut self) {
+		self.counts = EnumMap::default();
+	}
+}
+
+/// Number of steps in the loss pattern
+pub const STEPS: usize = 16;
+
+/// Per-packet loss sequence, programmed like a drum pattern
+pub struct StepPattern {
+	pub enabled: bool,
+	pub length: usize,
+	pub steps: [bool; STEPS],
+	position: usize,
+}
+
+impl Default for StepPattern {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			length: STEPS,
+			steps: [false; STEPS],
+			position: 0,
+		}
+	}
+}
+
+impl StepPattern {
+	/// Move to where the pattern is after `packets` packets from the start
+	pub fn seek(&mut self, packets: u64) {
+		self.position = (packets % self.length.clamp(1, STEPS) as u64) as usize;
+	}
+
+	/// Advance by one packet, returns true when the packet should be dropped
+	pub fn next(&mut self) -> bool {
+		let step = self.position % self.length.clamp(1, STEPS);
+		self.position = step + 1;
+		self.enabled && self.steps[step]
+	}
+
+	/// Back to the start of the pattern
+	pub fn reset(&mut self) {
+		self.position = 0;
+	}
+}
+
+/// Longest Euclidean cycle
+pub const EUCLID_STEPS: usize = 32;
+
+/// Whether step `index` of a rotated Euclidean rhythm E(pulses, steps) is a pulse
+pub fn euclidean(steps: usize, pulses: usize, rotation: usize, index: usize) -> bool {
+	if steps == 0 || pulses == 0 {
+		return false;
+	}
+	let i = (index + rotation) % steps;
+	(i * pulses) % steps < pulses
+}
+
+/// Drops `pulses` packets spread as evenly as possible over every `steps` packets
+pub struct Euclidean {
+	pub steps: usize,
+	pub pulses: usize,
+	pub rotation: usize,
+	position: usize,
+}
+
+impl Default for Euclidean {
+	fn default() -> Self {
+		Self {
+			steps: 16,
+			pulses: 0,
+			rotation: 0,
+			position: 0,
+		}
+	}
+}
+
+impl Euclidean {
+	/// Move to where the rhythm is after `packets` packets from the start
+	pub fn seek(&mut self, packets: u64) {
+		self.position = (packets % self.steps.clamp(1, EUCLID_STEPS) as u64) as usize;
+	}
+
+	/// Advance by one packet, returns true when the packet should be dropped
+	pub fn next(&mut self) -> bool {
+		let steps = self.steps.clamp(1, EUCLID_STEPS);
+		let step = self.position % steps;
+		self.position = step + 1;
+		euclidean(steps, self.pulses.min(steps), self.rotation, step)
+	}
+
+	/// Back to the first step of the cycle
+	pub fn reset(&mut self) {
+		self.position = 0;
+	}
+}
+
+/// Largest user seed, four hex digits like the seed is shown
+pub const MAX_SEED: u32 = 0xFFFF;
+
+/// A seed as text, in hex so a take of random loss is easy to copy between sessions
+pub fn format_seed(seed: u32) -> String {
+	match seed {
+		0 => "Random".to_string(),
+		seed => format!("0x{:04X}", seed),
+	}
+}
+
+/// Reads what format_seed shows, or a plain decimal seed
+pub fn parse_seed(string: &str) -> Option<u32> {
+	let seed = if string.eq_ignore_ascii_case("random") {
+		0
+	} else if let Some(hex) = string
+		.strip_prefix("0x")
+		.or_else(|| string.strip_prefix("0X"))
+	{
+		u32::from_str_radix(hex, 16).ok()?
+	} else {
+		string.parse().ok()?
+	};
+	Some(seed).filter(|seed| *seed <= MAX_SEED)
+}
+
+/// Mixes the bits of a counter, from SplitMix64
+fn mix(mut z: u64) -> u64 {
+	z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+	z ^ (z >> 31)
+}
+
+/// Largest instance index in a group
+pub const MAX_INSTANCE: u32 = 64;
+
+/// Seed of one instance in a group sharing `seed`, reproducible but independent
+/// of the other instances. Instance 0 keeps the group's seed
+pub fn sub_seed(seed: u64, instance: u32) -> u64 {
+	match instance {
+		0 => seed,
+		instance => mix(seed ^ mix(u64::from(instance) << 32)),
+	}
+}
+
+/// Random numbers drawn from a seed and the packet's index since the project start,
+/// instead of a free running generator, so a loop or a jump gets the same drops
+#[derive(Default)]
+pub struct RandomStream {
+	index: u64,
+}
+
+impl RandomStream {
+	/// Move to the packet `packets` packets from the start
+	pub fn seek(&mut self, packets: u64) {
+		self.index = packets;
+	}
+
+	/// Uniform in 0..1 for the next packet
+	pub fn next(&mut self, seed: u64) -> f64 {
+		let bits = mix(seed ^ mix(self.index));
+		self.index = self.index.wrapping_add(1);
+		(bits >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	/// Back to the first packet
+	pub fn reset(&mut self) {
+		self.index = 0;
+	}
+}
+
+/// Evenly spaced loss at a rate, a packet is dropped each time the rate summed over
+/// the packets since the project start passes a whole number
+#[derive(Default)]
+pub struct RoundRobin {
+	index: u64,
+}
+
+impl RoundRobin {
+	/// Move to the packet `packets` packets from the start
+	pub fn seek(&mut self, packets: u64) {
+		self.index = packets;
+	}
+
+	/// Advance by one packet, returns true when the packet should be dropped
+	pub fn next(&mut self, rate: f64) -> bool {
+		let rate = rate.clamp(0.0, 1.0);
+		let before = (self.index as f64 * rate).floor();
+		self.index = self.index.wrapping_add(1);
+		(self.index as f64 * rate).floor() > before
+	}
+
+	/// Back to the first packet
+	pub fn reset(&mut self) {
+		self.index = 0;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::euclidean;
+	use super::format_seed;
+	use super::parse_seed;
+	use super::sub_seed;
+	use super::RandomStream;
+	use super::RoundRobin;
+	use super::MAX_SEED;
+
+	fn pattern(steps: usize, pulses: usize, rotation: usize) -> String {
+		(0..steps)
+			.map(|i| {
+				if euclidean(steps, pulses, rotation, i) {
+					'x'
+				} else {
+					'.'
+				}
+			})
+			.collect()
+	}
+
+	#[test]
+	fn euclidean_rhythms() {
+		assert_eq!(pattern(8, 3, 0), "x..x..x.");
+		assert_eq!(pattern(4, 4, 0), "xxxx");
+		assert_eq!(pattern(5, 0, 0), ".....");
+		assert_eq!(pattern(8, 3, 1), "..x..x.x");
+	}
+
+	#[test]
+	fn random_stream_follows_the_index() {
+		let mut from_start = RandomStream::default();
+		let values: Vec<f64> = (0..8).map(|_| from_start.next(7)).collect();
+		let mut jumped = RandomStream::default();
+		jumped.seek(5);
+		assert_eq!(jumped.next(7), values[5]);
+		assert!(values.iter().all(|x| (0.0..1.0).contains(x)));
+	}
+
+	#[test]
+	fn round_robin_spreads_drops_evenly() {
+		let mut round_robin = RoundRobin::default();
+		let drops: Vec<bool> = (0..8).map(|_| round_robin.next(0.25)).collect();
+		assert_eq!(drops.iter().filter(|x| **x).count(), 2);
+		assert_eq!(drops[..4].iter().filter(|x| **x).count(), 1);
+		round_robin.seek(0);
+		assert!(!(0..8).any(|_| round_robin.next(0.0)));
+		assert!((0..8).all(|_| round_robin.next(1.0)));
+	}
+
+	#[test]
+	fn seeds_read_back_from_text() {
+		for seed in [0, 1, 0x270F, 0x2710, MAX_SEED].iter() {
+			assert_eq!(parse_seed(&format_seed(*seed)), Some(*seed));
+		}
+		assert_eq!(format_seed(MAX_SEED), "0xFFFF");
+		assert_eq!(parse_seed("42"), Some(42));
+		assert_eq!(parse_seed("0x10000"), None);
+	}
+
+	#[test]
+	fn instances_get_their_own_seeds() {
+		assert_eq!(sub_seed(7, 0), 7);
+		assert_eq!(sub_seed(7, 2), sub_seed(7, 2));
+		assert_ne!(sub_seed(7, 1), sub_seed(7, 2));
+		assert_ne!(sub_seed(7, 1), sub_seed(8, 1));
+	}
+}
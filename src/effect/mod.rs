@@ -1,16 +1,76 @@
+mod adaptive;
+#[cfg(feature = "vst3")]
+mod autosave;
+mod bursts;
+#[cfg(feature = "capi")]
+mod capi;
+mod channels;
+mod clean;
+mod coloration;
+#[cfg(feature = "vst3")]
 mod controller;
+mod convolver;
+mod curve;
 mod dsp;
+mod duplex;
+mod echo;
+mod effective;
+mod events;
+mod fade;
+mod handset;
+#[cfg(feature = "vst3")]
+mod inbox;
+mod levels;
+mod link;
+mod locale;
+mod lock;
+mod loss;
+mod monitor;
+mod noise;
+mod null;
 mod params;
+mod pool;
+mod preset;
+#[cfg(feature = "vst3")]
 mod processor;
+mod reference;
+mod resampler;
+mod selftest;
+#[cfg(feature = "vst3")]
+mod shared;
+#[cfg(all(feature = "vst3", feature = "single-component"))]
+mod single;
+#[cfg(feature = "vst3")]
+mod snapshot;
+#[cfg(feature = "vst3")]
+mod spare;
+mod state;
+mod stutter;
+mod timeline;
+mod warnings;
+mod warp;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watchdog;
+mod wav;
 
 use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
 use vst3_com::IID;
 
+#[cfg(feature = "vst3")]
 pub use controller::OpusController;
+#[cfg(feature = "vst3")]
 pub use processor::OpusProcessor;
+#[cfg(all(feature = "vst3", feature = "single-component"))]
+pub use single::OpusSingle;
 
 pub struct ContextPtr(*mut c_void);
 
+/// The other side of an IConnectionPoint connection
+pub struct ConnectionPtr(*mut c_void);
+
+#[cfg(feature = "vst3")]
 pub struct VstClassInfo {
 	pub cid: IID,
 	pub name: &'static str,
@@ -0,0 +1,45 @@
+/// Points in the chain to listen to, in parameter order
+pub const TAPS: [&str; 3] = ["Output", "Encoder Input", "Decoder Output"];
+
+/// The finished output, after coloration
+pub const TAP_OUTPUT: usize = 0;
+
+/// What the encoder is given, after the echo, noise and handset filters
+pub const TAP_INPUT: usize = 1;
+
+/// What the decoder made, before de-emphasis and coloration
+pub const TAP_DECODED: usize = 2;
+
+/// Solo one stage of the chain in place of the output, to find which one makes an artifact
+#[derive(Default)]
+pub struct Monitor {
+	/// Index into TAPS
+	pub tap: usize,
+	/// Encoder input, held back by the codec's delay
+	line: Vec<[f32; 2]>,
+	write: usize,
+}
+
+impl Monitor {
+	/// Room for the longest delay the input tap needs, which allocates
+	pub fn set_len(&mut self, frames: usize) {
+		self.line = vec![[0.0; 2]; frames + 1];
+		self.write = 0;
+	}
+
+	/// Delay the encoder input by as much as the codec delays the decoded audio,
+	/// so switching taps doesn't move the audio in time
+	pub fn delay_input(&mut self, frames: &[[f32; 2]], delay: usize, out: &mut [[f32; 2]]) {
+		let len = self.line.len();
+		if len == 0 {
+			out.copy_from_slice(frames);
+			return;
+		}
+		let delay = delay.min(len - 1);
+		for (frame, out) in frames.iter().zip(out.iter_mut()) {
+			self.line[self.write] = *frame;
+			*out = self.line[(self.write + len - delay) % len];
+			self.write = (self.write + 1) % len;
+		}
+	}
+}
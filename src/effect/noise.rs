@@ -0,0 +1,57 @@
+/// Packets for the speech level to fall by about 1/e, so pauses keep the noise going
+const SPEECH_RELEASE: f32 = 100.0;
+
+/// Packets the noise level is averaged over
+const NOISE_AVERAGE: f32 = 10.0;
+
+/// Mixes the noise bus into the encoder input at a signal-to-noise ratio
+/// against the recent speech level, leaving the dry signal alone
+pub struct NoiseMix {
+	pub snr_db: f32,
+	speech: f32,
+	noise: f32,
+}
+
+impl Default for NoiseMix {
+	fn default() -> Self {
+		Self {
+			snr_db: 20.0,
+			speech: 0.0,
+			noise: 0.0,
+		}
+	}
+}
+
+fn power(frames: &[[f32; 2]]) -> f32 {
+	let sum: f32 = frames.iter().map(|[l, r]| (l * l + r * r) / 2.0).sum();
+	sum / frames.len().max(1) as f32
+}
+
+impl NoiseMix {
+	/// Forget the measured levels
+	pub fn reset(&mut self) {
+		self.speech = 0.0;
+		self.noise = 0.0;
+	}
+
+	/// Add the noise packet to the encoder's copy of the input packet
+	pub fn mix(&mut self, packet: &mut [[f32; 2]], noise: &[[f32; 2]]) {
+		// Fast attack, slow release, like an active speech level
+		let speech = power(packet);
+		if speech > self.speech {
+			self.speech = speech;
+		} else {
+			self.speech += (speech - self.speech) / SPEECH_RELEASE;
+		}
+		self.noise += (power(noise) - self.noise) / NOISE_AVERAGE;
+
+		if self.noise <= f32::EPSILON {
+			return;
+		}
+		let gain = (self.speech / self.noise).sqrt() * 10f32.powf(-self.snr_db / 20.0);
+		for (frame, n) in packet.iter_mut().zip(noise) {
+			frame[0] += n[0] * gain;
+			frame[1] += n[1] * gain;
+		}
+	}
+}
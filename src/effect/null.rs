@@ -0,0 +1,187 @@
+use super::wav;
+use crate::error::Error;
+use crate::error::Result;
+#[cfg(feature = "vst3")]
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
+use std::slice;
+#[cfg(feature = "vst3")]
+use vst3_com::ComPtr;
+#[cfg(feature = "vst3")]
+use vst3_sys::base::kResultOk;
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IMessage};
+
+/// Outcome of the comparison, in parameter order
+pub const RESULTS: [&str; 3] = ["No Reference", "Pass", "Fail"];
+
+const NO_REFERENCE: u8 = 0;
+const PASS: u8 = 1;
+const FAIL: u8 = 2;
+
+/// Floor of the difference meter, in dBFS
+pub const MIN_DIFF_DB: f64 = -150.0;
+
+/// A difference up to this still nulls, in dBFS
+const TOLERANCE_DB: f64 = -90.0;
+
+fn to_db(peak: f32) -> f64 {
+	(20.0 * f64::from(peak).log10()).max(MIN_DIFF_DB)
+}
+
+/// Compares the output against an earlier render of the project, sample by sample
+/// at the project position, so an update that changes a render shows up as a failure
+#[derive(Clone, Default)]
+pub struct NullCheck {
+	path: Option<String>,
+	reference: Vec<[f32; 2]>,
+	/// Rate of the file
+	rate: f64,
+	/// The file matches the host rate
+	active: bool,
+	/// Largest difference since the transport started
+	peak: f32,
+	/// Some output was compared since the transport started
+	compared: bool,
+	reported: Option<(u8, i32)>,
+}
+
+impl NullCheck {
+	/// Path of the loaded reference render
+	pub fn path(&self) -> Option<&str> {
+		self.path.as_deref()
+	}
+
+	/// Read the whole reference render, which stays off the audio thread
+	pub fn load(&mut self, path: &str, host_rate: f64) -> Result<()> {
+		self.path = Some(path.to_string());
+		self.reference = Vec::new();
+		self.active = false;
+		self.restart();
+		let error = |err: hound::Error| Error::NullReference(format!("{}: {}", path, err));
+		let wav = wav::read(path, None).map_err(error)?;
+		self.reference = wav.frames;
+		self.rate = wav.rate;
+		self.set_host_rate(host_rate)
+	}
+
+	/// Only compares when the render was made at the host rate
+	pub fn set_host_rate(&mut self, rate: f64) -> Result<()> {
+		self.active = !self.reference.is_empty() && (self.rate - rate).abs() < 0.5;
+		match self.path.as_deref() {
+			Some(path) if !self.active && !self.reference.is_empty() => Err(Error::NullReference(
+				format!("{} is at {} Hz, the host at {} Hz", path, self.rate, rate),
+			)),
+			_ => Ok(()),
+		}
+	}
+
+	/// Null check is on
+	pub fn is_active(&self) -> bool {
+		self.active
+	}
+
+	/// Compare an output frame with the reference at a project frame, past its end
+	/// the reference is silent
+	pub fn check(&mut self, frame: Option<i64>, output: [f32; 2]) {
+		let frame = match frame {
+			Some(frame) if self.active && frame >= 0 => frame as usize,
+			_ => return,
+		};
+		let [r0, r1] = self.reference.get(frame).copied().unwrap_or([0.0; 2]);
+		let diff = (output[0] - r0).abs().max((output[1] - r1).abs());
+		self.peak = self.peak.max(diff);
+		self.compared = true;
+	}
+
+	/// Start over when the transport starts or jumps
+	pub fn restart(&mut self) {
+		self.peak = 0.0;
+		self.compared = false;
+	}
+
+	/// Result shown, pass or fail only once something was compared
+	pub fn result(&self) -> u8 {
+		if !self.active || !self.compared {
+			NO_REFERENCE
+		} else if to_db(self.peak) <= TOLERANCE_DB {
+			PASS
+		} else {
+			FAIL
+		}
+	}
+
+	/// Largest difference so far, in dBFS
+	pub fn difference_db(&self) -> f64 {
+		to_db(self.peak)
+	}
+
+	/// Result and difference if either changed since they were last reported,
+	/// the difference to a tenth of a dB
+	pub fn take_change(&mut self) -> Option<(u8, f64)> {
+		let current = (self.result(), (self.difference_db() * 10.0).round() as i32);
+		if self.reported == Some(current) {
+			return None;
+		}
+		self.reported = Some(current);
+		Some((current.0, self.difference_db()))
+	}
+}
+
+const MESSAGE_ID: &[u8] = b"OpusNullReference\0";
+const ATTRIBUTE_ID: &[u8] = b"path\0";
+
+/// Path from a null reference message, a UTF-8 binary attribute so an editor can send any path
+#[cfg(feature = "vst3")]
+pub unsafe fn receive(message: *mut c_void) -> Option<String> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut data = std::ptr::null();
+	let mut size = 0;
+	let id = ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_binary(id, &mut data, &mut size) != kResultOk || data.is_null() {
+		return None;
+	}
+
+	let bytes = slice::from_raw_parts(data as *const u8, size as usize);
+	String::from_utf8(bytes.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fails_past_the_tolerance() {
+		let mut null = NullCheck {
+			reference: vec![[0.5; 2]; 4],
+			rate: 48000.0,
+			..NullCheck::default()
+		};
+		null.set_host_rate(48000.0).unwrap();
+		null.check(Some(0), [0.5; 2]);
+		assert_eq!(null.result(), PASS);
+
+		null.check(Some(1), [0.5, 0.4]);
+		assert_eq!(null.result(), FAIL);
+		assert!((null.difference_db() + 20.0).abs() < 0.01);
+
+		null.restart();
+		assert_eq!(null.result(), NO_REFERENCE);
+	}
+}
@@ -1,46 +1,210 @@
+use super::adaptive;
+use super::coloration::DEVICES;
+use super::curve::Curve;
+use super::dsp::OpusDSP;
+use super::dsp::CAP_MODES;
+use super::dsp::FORCE_CHANNELS;
+use super::dsp::FRAME_DURATIONS;
+use super::dsp::LAYERS;
+use super::dsp::MAX_CAP_BYTES;
+use super::dsp::MIN_PACKET_BYTES;
+use super::dsp::OPUS_RATES;
+use super::dsp::PREROLLS;
+use super::dsp::RATE_CONTROLS;
+use super::dsp::SIGNALS;
+use super::duplex;
+use super::duplex::TALKERS;
+use super::echo::MAX_DELAY_SECS;
+use super::effective::BANDWIDTH_NAMES;
+use super::effective::MAX_FRAMES;
+use super::effective::MODES;
+use super::handset;
+use super::handset::SLOPES;
+use super::levels::WARNINGS;
+use super::link;
+use super::locale;
+use super::locale::Locale;
+use super::locale::Text;
+use super::loss;
+use super::loss::LossCause;
+use super::monitor::TAPS;
+use super::null;
+#[cfg(feature = "vst3")]
+use super::preset;
+use super::reference;
+use super::stutter::SYNC;
+use super::warp;
+use super::watchdog;
+use crate::error::Result;
+use crate::sys::{kRootUnitId, ParameterFlags};
+#[cfg(feature = "vst3")]
 use crate::vst_str;
-use anyhow::Result;
 use audiopus::Bandwidth;
 use enum_map::Enum;
 use num_enum::IntoPrimitive;
 use num_enum::TryFromPrimitive;
 use std::convert::Into;
 use variant_count::VariantCount;
+#[cfg(feature = "vst3")]
 use vst3_sys::vst;
-use vst3_sys::vst::ParameterFlags;
+#[cfg(feature = "vst3")]
 use vst3_sys::vst::ParameterInfo;
+#[cfg(feature = "vst3")]
 use vst3_sys::vst::UnitInfo;
-use super::dsp::OpusDSP;
 
-pub fn bandwidth_from_value(value: f64) -> Bandwidth {
-	match (value * 4.0 + 0.5) as usize {
-		0 => Bandwidth::Narrowband,
-		1 => Bandwidth::Mediumband,
-		2 => Bandwidth::Wideband,
-		3 => Bandwidth::Superwideband,
-		4 => Bandwidth::Fullband,
-		_ => Bandwidth::Auto,
+/// ParameterFlags::kIsHidden, added in VST 3.7
+const KIS_HIDDEN: i32 = 1 << 4;
+
+const AUTOMATE: i32 = ParameterFlags::kCanAutomate as i32;
+
+/// Latency in frames which normalizes to 1.0 for the hidden latency parameter
+const LATENCY_RANGE: f64 = (1 << 20) as f64;
+
+/// Xrun count which normalizes to 1.0
+const XRUN_RANGE: f64 = (1 << 20) as f64;
+
+/// Internal edit count which normalizes to 1.0
+const EDIT_RANGE: f64 = (1 << 20) as f64;
+
+/// Recorded event count which normalizes to 1.0
+const EVENT_RANGE: f64 = (1 << 20) as f64;
+
+/// Lost packet count which normalizes to 1.0
+const LOSS_RANGE: f64 = (1 << 20) as f64;
+
+/// Decoder gain either way, in dB, the decoder takes up to 128
+const MAX_DECODER_GAIN_DB: f64 = 24.0;
+
+/// Max Bandwidth choices, narrowest first
+pub const BANDWIDTHS: [Bandwidth; 5] = [
+	Bandwidth::Narrowband,
+	Bandwidth::Mediumband,
+	Bandwidth::Wideband,
+	Bandwidth::Superwideband,
+	Bandwidth::Fullband,
+];
+
+/// Log levels for the Debug Verbosity list
+const VERBOSITY: [log::LevelFilter; 4] = [
+	log::LevelFilter::Warn,
+	log::LevelFilter::Info,
+	log::LevelFilter::Debug,
+	log::LevelFilter::Trace,
+];
+
+/// How normalized values map to plain values
+#[derive(Copy, Clone, Debug)]
+pub enum Range {
+	/// Off is 0, on is 1
+	Toggle,
+	/// Whole numbers from min to max
+	Stepped { min: f64, max: f64 },
+	/// Any number from min to max
+	Continuous { min: f64, max: f64 },
+	/// Index into a list of labels
+	List(&'static [&'static str]),
+}
+
+impl Range {
+	pub fn step_count(self) -> i32 {
+		match self {
+			Self::Toggle => 1,
+			Self::Stepped { min, max } => (max - min) as i32,
+			Self::Continuous { .. } => 0,
+			Self::List(labels) => labels.len() as i32 - 1,
+		}
+	}
+
+	// Unlike clamp, max and min land a NaN from the host on 0
+	#[allow(clippy::manual_clamp)]
+	pub fn to_plain(self, value: f64) -> f64 {
+		let value = value.max(0.0).min(1.0);
+		match self {
+			Self::Toggle => (value > 0.5) as u8 as f64,
+			Self::Stepped { min, max } => min + (value * (max - min)).round(),
+			Self::Continuous { min, max } => min + value * (max - min),
+			Self::List(labels) => (value * (labels.len() - 1) as f64).round(),
+		}
 	}
+
+	#[allow(clippy::manual_clamp)]
+	pub fn to_normalized(self, plain: f64) -> f64 {
+		let value = match self {
+			Self::Toggle => plain,
+			Self::Stepped { min, max } | Self::Continuous { min, max } => {
+				(plain - min) / (max - min)
+			}
+			Self::List(labels) => plain / (labels.len() - 1) as f64,
+		};
+		value.max(0.0).min(1.0)
+	}
+
+	/// Labels indexed by plain value
+	pub fn labels(self) -> Option<&'static [&'static str]> {
+		match self {
+			Self::Toggle => Some(&["Off", "On"]),
+			Self::List(labels) => Some(labels),
+			_ => None,
+		}
+	}
+}
+
+/// How plain values are shown to the user
+#[derive(Copy, Clone)]
+pub enum Format {
+	/// Number with this many decimals, followed by the units
+	Decimal(usize),
+	/// Like Decimal with the sign always shown, for gains like "+6.0 dB"
+	Signed(usize),
+	/// The label at the plain value, Off and On for toggles
+	Label,
+	/// Hand written for special values
+	Custom(fn(f64) -> String),
+	/// Hand written both ways, for values typed in a form Decimal doesn't read
+	Parsed {
+		format: fn(f64) -> String,
+		parse: fn(&str) -> Option<f64>,
+	},
+}
+
+/// Everything about a parameter, from which the VST plumbing is derived
+#[derive(Copy, Clone)]
+pub struct Descriptor {
+	pub text: Text,
+	pub unit: Unit,
+	pub range: Range,
+	/// Plain default value
+	pub default: f64,
+	pub flags: i32,
+	pub format: Format,
+	/// Plain value from the DSP
+	pub get: fn(Parameter, &OpusDSP) -> Result<f64>,
+	/// Plain value into the DSP
+	pub set: fn(Parameter, &mut OpusDSP, f64) -> Result<()>,
 }
 
-///
+/// Groups the parameters are shown in
 #[derive(Copy, Clone, Debug, Enum, IntoPrimitive, TryFromPrimitive, VariantCount)]
 #[repr(i32)]
 pub enum Unit {
-	Root = vst::kRootUnitId,
+	Root = kRootUnitId,
 	Encoder,
 	Decoder,
 	Network,
+	Glitch,
+	Pattern,
+	Euclidean,
 }
 
 impl Unit {
+	#[cfg(feature = "vst3")]
 	pub fn get_info(self) -> UnitInfo {
 		match self {
 			Self::Root => UnitInfo {
 				id: self.into(),
 				parent_unit_id: vst::kNoParentUnitId,
 				name: vst_str::str_16("Root"),
-				program_list_id: vst::kNoProgramListId,
+				program_list_id: preset::PROGRAM_LIST_ID,
 			},
 			Self::Encoder => UnitInfo {
 				id: self.into(),
@@ -60,193 +224,1520 @@ impl Unit {
 				name: vst_str::str_16("Network"),
 				program_list_id: vst::kNoProgramListId,
 			},
+			Self::Glitch => UnitInfo {
+				id: self.into(),
+				parent_unit_id: Unit::Root.into(),
+				name: vst_str::str_16("Glitch"),
+				program_list_id: vst::kNoProgramListId,
+			},
+			Self::Pattern => UnitInfo {
+				id: self.into(),
+				parent_unit_id: Unit::Network.into(),
+				name: vst_str::str_16("Pattern"),
+				program_list_id: vst::kNoProgramListId,
+			},
+			Self::Euclidean => UnitInfo {
+				id: self.into(),
+				parent_unit_id: Unit::Network.into(),
+				name: vst_str::str_16("Euclidean"),
+				program_list_id: vst::kNoProgramListId,
+			},
 		}
 	}
 }
 
-///
-#[derive(Copy, Clone, Debug, Enum, IntoPrimitive, TryFromPrimitive, VariantCount)]
-#[repr(u32)]
-pub enum Parameter {
-	Bypass,
-	MaxBandwith,
-	Complexity,
-	PredictedLoss,
-	RandomLoss,
-	RoundRobinLoss,
+fn text(title: &'static str, short_title: &'static str, units: &'static str) -> Text {
+	Text {
+		title,
+		short_title,
+		units,
+	}
 }
 
-impl Parameter {
-	pub fn get_from_dsp(self, dsp: &OpusDSP) -> Result<f64> {
-		let value = match self {
-			Self::Bypass => dsp.bypass as u8 as f64,
-			Self::RandomLoss => dsp.loss_random,
-			Self::RoundRobinLoss => dsp.loss_roundrobin,
-			Self::PredictedLoss => f64::from(dsp.encoder.packet_loss_perc()?) / 100.0,
-			Self::Complexity => f64::from(dsp.encoder.complexity()?) / 10.0,
-			Self::MaxBandwith => match dsp.encoder.max_bandwidth()? {
-				Bandwidth::Narrowband => 0.0,
-				Bandwidth::Mediumband => 0.25,
-				Bandwidth::Wideband => 0.5,
-				Bandwidth::Superwideband => 0.75,
-				Bandwidth::Fullband => 1.0,
-				Bandwidth::Auto => 1.0,
-			},
-		};
+/// Hidden button that steps another parameter, for key commands and hardware controllers
+fn nudge_descriptor(text: Text) -> Descriptor {
+	Descriptor {
+		text,
+		unit: Unit::Encoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE | KIS_HIDDEN,
+		format: Format::Label,
+		get: |_, _| Ok(0.0),
+		set: |_, _, _| Ok(()),
+	}
+}
 
-		Ok(value)
+/// Shared by the counters of concealed packets
+fn loss_descriptor(text: Text) -> Descriptor {
+	Descriptor {
+		text,
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: 0.0,
+			max: LOSS_RANGE,
+		},
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Decimal(0),
+		get: |param, dsp| match param.loss_cause() {
+			Some(cause) => Ok(f64::from(dsp.losses.count(cause))),
+			None => Ok(0.0),
+		},
+		set: |_, _, _| Ok(()),
 	}
+}
 
-	pub fn set_to_dsp(self, dsp: &mut OpusDSP, value: f64) -> Result<()> {
-		match self {
-			Parameter::Bypass => dsp.bypass = value > 0.5,
-			Parameter::RandomLoss => dsp.loss_random = value,
-			Parameter::RoundRobinLoss => dsp.loss_roundrobin = value,
-			Parameter::PredictedLoss => {
-				let percentage = (value * 100.0 + f64::EPSILON) as u8;
-				dsp.encoder.set_packet_loss_perc(percentage)?
+/// Shared by every step of the loss pattern
+fn step_descriptor() -> Descriptor {
+	Descriptor {
+		text: text("Step", "S", ""),
+		unit: Unit::Pattern,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |param, dsp| match param.step() {
+			Some(step) => Ok(dsp.pattern.steps[step] as u8 as f64),
+			None => Ok(0.0),
+		},
+		set: |param, dsp, value| {
+			if let Some(step) = param.step() {
+				dsp.pattern.steps[step] = value > 0.5;
 			}
-			Parameter::Complexity => {
-				let complexity = (value * 10.0 + f64::EPSILON) as u8;
-				dsp.encoder.set_complexity(complexity)?
+			Ok(())
+		},
+	}
+}
+
+crate::parameters! {
+	Bypass = "bypass" => Descriptor {
+		text: text("Bypass", "", ""),
+		unit: Unit::Root,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE | ParameterFlags::kIsBypass as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.bypass as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.bypass = value > 0.5;
+			Ok(())
+		},
+	},
+
+	MaxBandwith = "max_bandwidth" => Descriptor {
+		text: text("Max Bandwith", "Band", "kHz"),
+		unit: Unit::Encoder,
+		range: Range::List(&["4", "6", "8", "12", "20"]),
+		default: 4.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| {
+			let bw = dsp.max_bandwidth;
+			let index = BANDWIDTHS.iter().position(|x| *x == bw);
+			Ok(index.unwrap_or(BANDWIDTHS.len() - 1) as f64)
+		},
+		set: |_, dsp, value| {
+			dsp.max_bandwidth = BANDWIDTHS[(value as usize).min(BANDWIDTHS.len() - 1)];
+			dsp.apply_bandwidth()
+		},
+	},
+
+	Complexity = "complexity" => Descriptor {
+		text: text("Complexity", "Cmpx", ""),
+		unit: Unit::Encoder,
+		range: Range::Stepped {
+			min: 0.0,
+			max: 10.0,
+		},
+		default: 9.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.complexity)),
+		set: |_, dsp, value| {
+			dsp.complexity = value as u8;
+			dsp.apply_complexity()
+		},
+	},
+
+	PredictedLoss = "predicted_loss" => Descriptor {
+		text: text("Predicted Loss", "PdLs", "%"),
+		unit: Unit::Encoder,
+		range: Range::Stepped {
+			min: 0.0,
+			max: 100.0,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.encoder.packet_loss_perc()?)),
+		set: |_, dsp, value| Ok(dsp.encoder.set_packet_loss_perc(value as u8)?),
+	},
+
+	RandomLoss = "random_loss" => Descriptor {
+		text: text("Random Loss", "RndLs", "%"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: 0.0,
+			max: 100.0,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(2),
+		get: |_, dsp| Ok(dsp.loss_random * 100.0),
+		set: |_, dsp, value| {
+			dsp.loss_random = value / 100.0;
+			Ok(())
+		},
+	},
+
+	RoundRobinLoss = "round_robin_loss" => Descriptor {
+		text: text("Round Robin Loss", "RRLs", "%"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: 0.0,
+			max: 100.0,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(2),
+		get: |_, dsp| Ok(dsp.loss_roundrobin * 100.0),
+		set: |_, dsp, value| {
+			dsp.loss_roundrobin = value / 100.0;
+			Ok(())
+		},
+	},
+
+	Freeze = "freeze" => Descriptor {
+		text: text("Freeze", "Frz", ""),
+		unit: Unit::Glitch,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.freeze as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.freeze = value > 0.5;
+			Ok(())
+		},
+	},
+
+	Repeat = "repeat" => Descriptor {
+		text: text("Repeat", "Rpt", "x"),
+		unit: Unit::Glitch,
+		range: Range::Stepped {
+			min: 0.0,
+			max: 16.0,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Custom(|value| match value as u32 {
+			0 => "Hold".to_string(),
+			n => format!("{}", n),
+		}),
+		get: |_, dsp| Ok(f64::from(dsp.repeat)),
+		set: |_, dsp, value| {
+			dsp.repeat = value as u32;
+			Ok(())
+		},
+	},
+
+	Pattern = "pattern" => Descriptor {
+		text: text("Pattern", "Ptn", ""),
+		unit: Unit::Pattern,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.pattern.enabled as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.pattern.enabled = value > 0.5;
+			Ok(())
+		},
+	},
+
+	PatternLength = "pattern_length" => Descriptor {
+		text: text("Pattern Length", "PtLn", "steps"),
+		unit: Unit::Pattern,
+		range: Range::Stepped {
+			min: 1.0,
+			max: loss::STEPS as f64,
+		},
+		default: loss::STEPS as f64,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.pattern.length as f64),
+		set: |_, dsp, value| {
+			dsp.pattern.length = value as usize;
+			Ok(())
+		},
+	},
+
+	Step1 = "step_1" => step_descriptor(),
+	Step2 = "step_2" => step_descriptor(),
+	Step3 = "step_3" => step_descriptor(),
+	Step4 = "step_4" => step_descriptor(),
+	Step5 = "step_5" => step_descriptor(),
+	Step6 = "step_6" => step_descriptor(),
+	Step7 = "step_7" => step_descriptor(),
+	Step8 = "step_8" => step_descriptor(),
+	Step9 = "step_9" => step_descriptor(),
+	Step10 = "step_10" => step_descriptor(),
+	Step11 = "step_11" => step_descriptor(),
+	Step12 = "step_12" => step_descriptor(),
+	Step13 = "step_13" => step_descriptor(),
+	Step14 = "step_14" => step_descriptor(),
+	Step15 = "step_15" => step_descriptor(),
+	Step16 = "step_16" => step_descriptor(),
+
+	EuclidSteps = "euclid_steps" => Descriptor {
+		text: text("Euclid Steps", "EuSt", "packets"),
+		unit: Unit::Euclidean,
+		range: Range::Stepped {
+			min: 1.0,
+			max: loss::EUCLID_STEPS as f64,
+		},
+		default: 16.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.euclid.steps as f64),
+		set: |_, dsp, value| {
+			dsp.euclid.steps = value as usize;
+			Ok(())
+		},
+	},
+
+	EuclidPulses = "euclid_pulses" => Descriptor {
+		text: text("Euclid Drops", "EuDr", "packets"),
+		unit: Unit::Euclidean,
+		range: Range::Stepped {
+			min: 0.0,
+			max: loss::EUCLID_STEPS as f64,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.euclid.pulses as f64),
+		set: |_, dsp, value| {
+			dsp.euclid.pulses = value as usize;
+			Ok(())
+		},
+	},
+
+	EuclidRotation = "euclid_rotation" => Descriptor {
+		text: text("Euclid Rotation", "EuRt", "packets"),
+		unit: Unit::Euclidean,
+		range: Range::Stepped {
+			min: 0.0,
+			max: (loss::EUCLID_STEPS - 1) as f64,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.euclid.rotation as f64),
+		set: |_, dsp, value| {
+			dsp.euclid.rotation = value as usize;
+			Ok(())
+		},
+	},
+
+	Latency = "latency" => Descriptor {
+		text: text("Latency", "Ltcy", "samples"),
+		unit: Unit::Root,
+		range: Range::Continuous {
+			min: 0.0,
+			max: LATENCY_RANGE,
+		},
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32 | KIS_HIDDEN,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.latency() as f64),
+		set: |_, _, _| Ok(()),
+	},
+
+	Governor = "governor" => Descriptor {
+		text: text("Auto Complexity", "AuCx", ""),
+		unit: Unit::Encoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.governor as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.governor = value > 0.5;
+			Ok(())
+		},
+	},
+
+	Xruns = "xruns" => Descriptor {
+		text: text("Xruns", "Xrun", ""),
+		unit: Unit::Root,
+		range: Range::Continuous {
+			min: 0.0,
+			max: XRUN_RANGE,
+		},
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.watchdog.xruns)),
+		set: |_, _, _| Ok(()),
+	},
+
+	OfflineQuality = "offline_quality" => Descriptor {
+		text: text("Offline Quality", "OfQl", ""),
+		unit: Unit::Root,
+		range: Range::Toggle,
+		default: 1.0,
+		flags: 0,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.offline_quality as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.offline_quality = value > 0.5;
+			Ok(())
+		},
+	},
+
+	InternalRate = "internal_rate" => Descriptor {
+		text: text("Internal Rate", "Rate", "kHz"),
+		unit: Unit::Encoder,
+		range: Range::List(&["Auto", "8", "12", "16", "24", "48"]),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| match dsp.internal_rate {
+			Some(rate) => {
+				let index = OPUS_RATES.iter().position(|x| *x == rate).unwrap_or(0);
+				Ok((index + 1) as f64)
 			}
-			Parameter::MaxBandwith => {
-				let bw = match (value * 4.0 + f64::EPSILON) as usize {
-					0 => Bandwidth::Narrowband,
-					1 => Bandwidth::Mediumband,
-					2 => Bandwidth::Wideband,
-					3 => Bandwidth::Superwideband,
-					4 => Bandwidth::Fullband,
-					_ => Bandwidth::Auto,
-				};
-				dsp.encoder.set_max_bandwidth(bw)?
+			None => Ok(0.0),
+		},
+		set: |_, dsp, value| {
+			dsp.internal_rate = match value as usize {
+				0 => None,
+				index => OPUS_RATES.get(index - 1).copied(),
+			};
+			Ok(())
+		},
+	},
+
+	DecodeFec = "decode_fec" => Descriptor {
+		text: text("Decode FEC", "FEC", ""),
+		unit: Unit::Decoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.decode_fec as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.set_decode_fec(value > 0.5);
+			Ok(())
+		},
+	},
+
+	Verbosity = "verbosity" => Descriptor {
+		text: text("Debug Verbosity", "Dbg", ""),
+		unit: Unit::Root,
+		range: Range::List(&["Warn", "Info", "Debug", "Trace"]),
+		default: 1.0,
+		flags: KIS_HIDDEN,
+		format: Format::Label,
+		get: |_, _| {
+			let level = log::max_level();
+			Ok(VERBOSITY.iter().position(|x| *x == level).unwrap_or(0) as f64)
+		},
+		set: |_, _, value| {
+			log::set_max_level(VERBOSITY[(value as usize).min(VERBOSITY.len() - 1)]);
+			Ok(())
+		},
+	},
+
+	RestoreSnapshot = "restore_snapshot" => Descriptor {
+		text: text("Restore Snapshot", "Rstr", ""),
+		unit: Unit::Root,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: if cfg!(feature = "autosave") { 0 } else { KIS_HIDDEN },
+		format: Format::Label,
+		get: |_, _| Ok(0.0),
+		set: |_, _, _| Ok(()),
+	},
+
+	InternalEdits = "internal_edits" => Descriptor {
+		text: text("Internal Edits", "IEdt", ""),
+		unit: Unit::Root,
+		range: Range::Continuous {
+			min: 0.0,
+			max: EDIT_RANGE,
+		},
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32 | KIS_HIDDEN,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.internal_edits) % EDIT_RANGE),
+		set: |_, _, _| Ok(()),
+	},
+
+	Coloration = "coloration" => Descriptor {
+		text: text("Device", "Dev", ""),
+		unit: Unit::Decoder,
+		range: Range::List(&DEVICES),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.coloration.device() as f64),
+		set: |_, dsp, value| {
+			dsp.coloration.set_device(value as usize);
+			Ok(())
+		},
+	},
+
+	EchoLevel = "echo_level" => Descriptor {
+		text: text("Echo Level", "EcLv", "%"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: 0.0,
+			max: 100.0,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.echo.level) * 100.0),
+		set: |_, dsp, value| {
+			dsp.echo.level = (value / 100.0) as f32;
+			Ok(())
+		},
+	},
+
+	EchoDelay = "echo_delay" => Descriptor {
+		text: text("Echo Delay", "EcDl", "ms"),
+		unit: Unit::Network,
+		range: Range::Stepped {
+			min: 20.0,
+			max: MAX_DELAY_SECS * 1000.0,
+		},
+		default: 120.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.echo.delay_ms),
+		set: |_, dsp, value| {
+			dsp.echo.delay_ms = value;
+			Ok(())
+		},
+	},
+
+	EchoDamping = "echo_damping" => Descriptor {
+		text: text("Echo Damping", "EcDp", "%"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: 0.0,
+			max: 100.0,
+		},
+		default: 50.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.echo.damping) * 100.0),
+		set: |_, dsp, value| {
+			dsp.echo.damping = (value / 100.0) as f32;
+			Ok(())
+		},
+	},
+
+	NoiseSnr = "noise_snr" => Descriptor {
+		text: text("Noise SNR", "NSNR", "dB"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: -10.0,
+			max: 40.0,
+		},
+		default: 20.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.noise.snr_db)),
+		set: |_, dsp, value| {
+			dsp.noise.snr_db = value as f32;
+			Ok(())
+		},
+	},
+
+	CodecLayer = "codec_layer" => Descriptor {
+		text: text("Codec Layer", "Layr", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&LAYERS),
+		default: 0.0,
+		flags: 0,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.layer() as f64),
+		set: |_, dsp, value| dsp.set_layer(value as usize),
+	},
+
+	NetworkEnable = "network_enable" => Descriptor {
+		text: text("Network Enable", "Net", ""),
+		unit: Unit::Network,
+		range: Range::Toggle,
+		default: 1.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.network as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.network = value > 0.5;
+			Ok(())
+		},
+	},
+
+	LevelWarning = "level_warning" => Descriptor {
+		text: text("Level Warning", "Levl", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&WARNINGS),
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(f64::from(dsp.levels.warning())),
+		set: |_, _, _| Ok(()),
+	},
+
+	LostPattern = "lost_pattern" => loss_descriptor(text("Lost to Pattern", "LsPt", "")),
+	LostEuclidean = "lost_euclidean" => loss_descriptor(text("Lost to Euclidean", "LsEu", "")),
+	LostRandom = "lost_random" => loss_descriptor(text("Lost to Random", "LsRn", "")),
+	LostOversized = "lost_oversized" => loss_descriptor(text("Lost to Oversize", "LsOv", "")),
+
+	LowRateFallback = "low_rate_fallback" => Descriptor {
+		text: text("Low Rate Fallback", "LoFb", ""),
+		unit: Unit::Root,
+		range: Range::Toggle,
+		default: 1.0,
+		flags: 0,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.low_rate_fallback as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.low_rate_fallback = value > 0.5;
+			Ok(())
+		},
+	},
+
+	EffectiveMode = "effective_mode" => Descriptor {
+		text: text("Effective Mode", "Mode", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&MODES),
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(f64::from(dsp.effective.get().mode)),
+		set: |_, _, _| Ok(()),
+	},
+
+	EffectiveBandwidth = "effective_bandwidth" => Descriptor {
+		text: text("Effective Bandwidth", "EfBw", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&BANDWIDTH_NAMES),
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(f64::from(dsp.effective.get().bandwidth)),
+		set: |_, _, _| Ok(()),
+	},
+
+	EffectiveFrames = "effective_frames" => Descriptor {
+		text: text("Frames per Packet", "Frms", ""),
+		unit: Unit::Encoder,
+		range: Range::Stepped {
+			min: 0.0,
+			max: MAX_FRAMES as f64,
+		},
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.effective.get().frames)),
+		set: |_, _, _| Ok(()),
+	},
+
+	ComplexityUp = "complexity_up" => nudge_descriptor(text("Complexity Up", "Cmp+", "")),
+	ComplexityDown = "complexity_down" => nudge_descriptor(text("Complexity Down", "Cmp-", "")),
+	BandwidthUp = "bandwidth_up" => nudge_descriptor(text("Bandwidth Up", "Bnd+", "")),
+	BandwidthDown = "bandwidth_down" => nudge_descriptor(text("Bandwidth Down", "Bnd-", "")),
+
+	FreezeSync = "freeze_sync" => Descriptor {
+		text: text("Freeze Sync", "FzSy", ""),
+		unit: Unit::Glitch,
+		range: Range::List(&SYNC),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.freeze_sync.sync as f64),
+		set: |_, dsp, value| {
+			dsp.freeze_sync.sync = value as usize;
+			Ok(())
+		},
+	},
+
+	HighPassCutoff = "high_pass_cutoff" => Descriptor {
+		text: text("High-Pass Cutoff", "HPF", "Hz"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: handset::MIN_CUTOFF,
+			max: handset::MAX_CUTOFF,
+		},
+		default: 100.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.handset.cutoff),
+		set: |_, dsp, value| {
+			dsp.handset.cutoff = value;
+			Ok(())
+		},
+	},
+
+	HighPassSlope = "high_pass_slope" => Descriptor {
+		text: text("High-Pass Slope", "HPSl", ""),
+		unit: Unit::Network,
+		range: Range::List(&SLOPES),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.handset.slope as f64),
+		set: |_, dsp, value| {
+			dsp.handset.slope = value as usize;
+			Ok(())
+		},
+	},
+
+	Emphasis = "emphasis" => Descriptor {
+		text: text("Pre-Emphasis", "Emph", ""),
+		unit: Unit::Network,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.handset.emphasis as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.handset.emphasis = value > 0.5;
+			Ok(())
+		},
+	},
+
+	Monitor = "monitor" => Descriptor {
+		text: text("Monitor", "Mon", ""),
+		unit: Unit::Root,
+		range: Range::List(&TAPS),
+		default: 0.0,
+		flags: 0,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.monitor.tap as f64),
+		set: |_, dsp, value| {
+			dsp.monitor.tap = value as usize;
+			Ok(())
+		},
+	},
+
+	MaxPacketBytes = "max_packet_bytes" => Descriptor {
+		text: text("Max Packet Bytes", "MTU", "B"),
+		unit: Unit::Network,
+		range: Range::Stepped {
+			min: MIN_PACKET_BYTES as f64,
+			max: MAX_CAP_BYTES as f64,
+		},
+		default: MAX_CAP_BYTES as f64,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.max_packet_bytes as f64),
+		set: |_, dsp, value| {
+			dsp.max_packet_bytes = value as usize;
+			Ok(())
+		},
+	},
+
+	OversizedPackets = "oversized_packets" => Descriptor {
+		text: text("Oversized Packets", "Ovsz", ""),
+		unit: Unit::Network,
+		range: Range::List(&CAP_MODES),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.cap_mode as f64),
+		set: |_, dsp, value| {
+			dsp.cap_mode = value as usize;
+			Ok(())
+		},
+	},
+
+	HalfDuplex = "half_duplex" => Descriptor {
+		text: text("Half Duplex", "HDpx", ""),
+		unit: Unit::Network,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.duplex.enabled as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.duplex.enabled = value > 0.5;
+			Ok(())
+		},
+	},
+
+	DuplexThreshold = "duplex_threshold" => Descriptor {
+		text: text("Duplex Threshold", "DxTh", "dB"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: duplex::MIN_THRESHOLD_DB,
+			max: duplex::MAX_THRESHOLD_DB,
+		},
+		default: -40.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(1),
+		get: |_, dsp| Ok(dsp.duplex.threshold_db),
+		set: |_, dsp, value| {
+			dsp.duplex.threshold_db = value;
+			Ok(())
+		},
+	},
+
+	DuplexHold = "duplex_hold" => Descriptor {
+		text: text("Duplex Hold", "DxHd", "ms"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: 0.0,
+			max: duplex::MAX_HOLD_MS,
+		},
+		default: 200.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.duplex.hold_ms),
+		set: |_, dsp, value| {
+			dsp.duplex.hold_ms = value;
+			Ok(())
+		},
+	},
+
+	DuplexDepth = "duplex_depth" => Descriptor {
+		text: text("Duplex Depth", "DxDp", "dB"),
+		unit: Unit::Network,
+		range: Range::Continuous {
+			min: duplex::MIN_DEPTH_DB,
+			max: 0.0,
+		},
+		default: duplex::MIN_DEPTH_DB,
+		flags: AUTOMATE,
+		format: Format::Decimal(1),
+		get: |_, dsp| Ok(dsp.duplex.depth_db),
+		set: |_, dsp, value| {
+			dsp.duplex.depth_db = value;
+			Ok(())
+		},
+	},
+
+	Talker = "talker" => Descriptor {
+		text: text("Talking", "Talk", ""),
+		unit: Unit::Network,
+		range: Range::List(&TALKERS),
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(f64::from(dsp.duplex.talker())),
+		set: |_, _, _| Ok(()),
+	},
+
+	DropNow = "drop_now" => Descriptor {
+		text: text("Drop Now", "Drop", ""),
+		unit: Unit::Glitch,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.drop_now as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.set_drop_now(value > 0.5);
+			Ok(())
+		},
+	},
+
+	LostManual = "lost_manual" => loss_descriptor(text("Lost to Drop Now", "LsDr", "")),
+
+	OfflineComplexity = "offline_complexity" => Descriptor {
+		text: text("Offline Complexity", "OfCx", ""),
+		unit: Unit::Encoder,
+		range: Range::Stepped {
+			min: 0.0,
+			max: 10.0,
+		},
+		default: 10.0,
+		flags: 0,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.offline_complexity)),
+		set: |_, dsp, value| {
+			dsp.offline_complexity = value as u8;
+			dsp.apply_complexity()
+		},
+	},
+
+	Warning = "warning" => Descriptor {
+		text: text("Warning", "Warn", ""),
+		unit: Unit::Root,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.warnings.is_active() as u8 as f64),
+		set: |_, _, _| Ok(()),
+	},
+
+	CpuLoad = "cpu_load" => Descriptor {
+		text: text("CPU Load", "CPU", "%"),
+		unit: Unit::Root,
+		range: Range::Continuous {
+			min: 0.0,
+			max: watchdog::MAX_LOAD,
+		},
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.watchdog.load()),
+		set: |_, _, _| Ok(()),
+	},
+
+	LossSeed = "loss_seed" => Descriptor {
+		text: text("Loss Seed", "Seed", ""),
+		unit: Unit::Network,
+		range: Range::Stepped {
+			min: 0.0,
+			max: loss::MAX_SEED as f64,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Parsed {
+			format: |value| loss::format_seed(value as u32),
+			parse: |string| loss::parse_seed(string).map(f64::from),
+		},
+		get: |_, dsp| Ok(f64::from(dsp.loss_seed)),
+		set: |_, dsp, value| {
+			dsp.loss_seed = value as u32;
+			Ok(())
+		},
+	},
+
+	LinkMode = "link_mode" => Descriptor {
+		text: text("Link", "Link", ""),
+		unit: Unit::Network,
+		range: Range::List(&link::MODES),
+		default: 0.0,
+		flags: 0,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.link.mode() as f64),
+		set: |_, dsp, value| {
+			dsp.link.set_mode(value as usize);
+			Ok(())
+		},
+	},
+
+	LinkPort = "link_port" => Descriptor {
+		text: text("Link Port", "Port", ""),
+		unit: Unit::Network,
+		range: Range::Stepped {
+			min: link::MIN_PORT as f64,
+			max: link::MAX_PORT as f64,
+		},
+		default: link::DEFAULT_PORT as f64,
+		flags: 0,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.link.port())),
+		set: |_, dsp, value| {
+			dsp.link.set_port(value as u16);
+			Ok(())
+		},
+	},
+
+	LostLink = "lost_link" => loss_descriptor(text("Lost on Link", "LsLk", "")),
+
+	SwapChannels = "swap_channels" => Descriptor {
+		text: text("Swap L/R", "Swap", ""),
+		unit: Unit::Decoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.channels.swap as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.channels.swap = value > 0.5;
+			Ok(())
+		},
+	},
+
+	InvertLeft = "invert_left" => Descriptor {
+		text: text("Invert Left", "InvL", ""),
+		unit: Unit::Decoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.channels.invert_left as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.channels.invert_left = value > 0.5;
+			Ok(())
+		},
+	},
+
+	InvertRight = "invert_right" => Descriptor {
+		text: text("Invert Right", "InvR", ""),
+		unit: Unit::Decoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.channels.invert_right as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.channels.invert_right = value > 0.5;
+			Ok(())
+		},
+	},
+
+	MonoSum = "mono_sum" => Descriptor {
+		text: text("Mono Sum", "Mono", ""),
+		unit: Unit::Decoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.channels.mono as u8 as f64),
+		set: |_, dsp, value| {
+			dsp.channels.mono = value > 0.5;
+			Ok(())
+		},
+	},
+
+	AbCompare = "ab_compare" => Descriptor {
+		text: text("A/B", "A/B", ""),
+		unit: Unit::Root,
+		range: Range::List(&reference::SOURCES),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.reference.source as f64),
+		set: |_, dsp, value| {
+			dsp.reference.source = (value as usize).min(reference::SOURCES.len() - 1);
+			Ok(())
+		},
+	},
+
+	RateControl = "rate_control" => Descriptor {
+		text: text("Rate Control", "Rate", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&RATE_CONTROLS),
+		default: 1.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.rate_control()? as f64),
+		set: |_, dsp, value| dsp.set_rate_control(value as usize),
+	},
+
+	AdaptiveBitrate = "adaptive_bitrate" => Descriptor {
+		text: text("Adaptive Bitrate", "AdBr", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&adaptive::SOURCES),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.adaptive.source as f64),
+		set: |_, dsp, value| {
+			dsp.adaptive.source = (value as usize).min(adaptive::SOURCES.len() - 1);
+			if dsp.adaptive.is_enabled() {
+				Ok(())
+			} else {
+				dsp.apply_bitrate()?;
+				dsp.apply_bandwidth()
 			}
-		};
+		},
+	},
 
-		Ok(())
-	}
+	AdaptiveMin = "adaptive_min" => Descriptor {
+		text: text("Adaptive Floor", "AdFl", "kbps"),
+		unit: Unit::Encoder,
+		range: Range::Continuous {
+			min: adaptive::MIN_KBPS,
+			max: adaptive::MAX_KBPS,
+		},
+		default: 12.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.adaptive.min_kbps),
+		set: |_, dsp, value| {
+			dsp.adaptive.min_kbps = value;
+			Ok(())
+		},
+	},
 
-	pub fn get_parameter_info(self) -> ParameterInfo {
-		match self {
-			Self::Bypass => ParameterInfo {
-				id: self.into(),
-				title: vst_str::str_16("Bypass"),
-				short_title: [0; 128],
-				units: [0; 128],
-				step_count: 1,
-				default_normalized_value: 0.0,
-				unit_id: Unit::Root.into(),
-				flags: ParameterFlags::kCanAutomate as i32 | ParameterFlags::kIsBypass as i32,
-			},
+	AdaptiveMax = "adaptive_max" => Descriptor {
+		text: text("Adaptive Ceiling", "AdCl", "kbps"),
+		unit: Unit::Encoder,
+		range: Range::Continuous {
+			min: adaptive::MIN_KBPS,
+			max: adaptive::MAX_KBPS,
+		},
+		default: 64.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.adaptive.max_kbps),
+		set: |_, dsp, value| {
+			dsp.adaptive.max_kbps = value;
+			Ok(())
+		},
+	},
 
-			Self::MaxBandwith => ParameterInfo {
-				id: self.into(),
-				title: vst_str::str_16("Max Bandwith"),
-				short_title: vst_str::str_16("Band"),
-				units: vst_str::str_16("kHz"),
-				step_count: 5 - 1,
-				default_normalized_value: 1.0,
-				unit_id: Unit::Encoder.into(),
-				flags: ParameterFlags::kCanAutomate as i32,
-			},
+	AdaptiveAttack = "adaptive_attack" => Descriptor {
+		text: text("Adaptive Attack", "AdAt", "ms"),
+		unit: Unit::Encoder,
+		range: Range::Continuous {
+			min: 0.0,
+			max: adaptive::MAX_TIME_MS,
+		},
+		default: 50.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.adaptive.attack_ms),
+		set: |_, dsp, value| {
+			dsp.adaptive.attack_ms = value;
+			Ok(())
+		},
+	},
 
-			Self::Complexity => ParameterInfo {
-				id: self.into(),
-				title: vst_str::str_16("Complexity"),
-				short_title: vst_str::str_16("Cmpx"),
-				units: vst_str::str_16(""),
-				step_count: 10,
-				default_normalized_value: 0.9,
-				unit_id: Unit::Encoder.into(),
-				flags: ParameterFlags::kCanAutomate as i32,
-			},
+	AdaptiveRelease = "adaptive_release" => Descriptor {
+		text: text("Adaptive Release", "AdRl", "ms"),
+		unit: Unit::Encoder,
+		range: Range::Continuous {
+			min: 0.0,
+			max: adaptive::MAX_TIME_MS,
+		},
+		default: 500.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.adaptive.release_ms),
+		set: |_, dsp, value| {
+			dsp.adaptive.release_ms = value;
+			Ok(())
+		},
+	},
 
-			Self::PredictedLoss => ParameterInfo {
-				id: self.into(),
-				title: vst_str::str_16("Predicted Loss"),
-				short_title: vst_str::str_16("PdLs"),
-				units: vst_str::str_16("%"),
-				step_count: 100,
-				default_normalized_value: 0.0,
-				unit_id: Unit::Encoder.into(),
-				flags: ParameterFlags::kCanAutomate as i32,
-			},
+	FrameDuration = "frame_duration" => Descriptor {
+		text: text("Frame Duration", "FrDr", "ms"),
+		unit: Unit::Encoder,
+		range: Range::List(&FRAME_DURATIONS),
+		default: 3.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.frame_duration as f64),
+		set: |_, dsp, value| {
+			dsp.frame_duration = (value as usize).min(FRAME_DURATIONS.len() - 1);
+			Ok(())
+		},
+	},
 
-			Self::RandomLoss => ParameterInfo {
-				id: self.into(),
-				title: vst_str::str_16("Random Loss"),
-				short_title: vst_str::str_16("RndLs"),
-				units: vst_str::str_16("%"),
-				step_count: 0,
-				default_normalized_value: 0.0,
-				unit_id: Unit::Network.into(),
-				flags: ParameterFlags::kCanAutomate as i32,
-			},
+	TimeWarp = "time_warp" => Descriptor {
+		text: text("Time Warp", "Warp", "%"),
+		unit: Unit::Decoder,
+		range: Range::Continuous {
+			min: 0.0,
+			max: warp::MAX_WARP_PERCENT,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(dsp.warp.max_percent),
+		set: |_, dsp, value| {
+			dsp.warp.max_percent = value;
+			Ok(())
+		},
+	},
 
-			Self::RoundRobinLoss => ParameterInfo {
-				id: self.into(),
-				title: vst_str::str_16("Round Robin Loss"),
-				short_title: vst_str::str_16("RRLs"),
-				units: vst_str::str_16("%"),
-				step_count: 0,
-				default_normalized_value: 0.0,
-				unit_id: Unit::Network.into(),
-				flags: ParameterFlags::kCanAutomate as i32,
-			},
+	NullResult = "null_result" => Descriptor {
+		text: text("Null Test", "Null", ""),
+		unit: Unit::Root,
+		range: Range::List(&null::RESULTS),
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(f64::from(dsp.null.result())),
+		set: |_, _, _| Ok(()),
+	},
+
+	NullDifference = "null_difference" => Descriptor {
+		text: text("Null Difference", "NlDf", "dB"),
+		unit: Unit::Root,
+		range: Range::Continuous {
+			min: null::MIN_DIFF_DB,
+			max: 0.0,
+		},
+		default: null::MIN_DIFF_DB,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Decimal(1),
+		get: |_, dsp| Ok(dsp.null.difference_db()),
+		set: |_, _, _| Ok(()),
+	},
+
+	Dtx = "dtx" => Descriptor {
+		text: text("DTX", "DTX", ""),
+		unit: Unit::Encoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.dtx()? as u8 as f64),
+		set: |_, dsp, value| dsp.set_dtx(value > 0.5),
+	},
+
+	InDtx = "in_dtx" => Descriptor {
+		text: text("In DTX", "InDx", ""),
+		unit: Unit::Encoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.effective.in_dtx() as u8 as f64),
+		set: |_, _, _| Ok(()),
+	},
+
+	SignalHint = "signal_hint" => Descriptor {
+		text: text("Signal Hint", "Sig", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&SIGNALS),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.signal_hint() as f64),
+		set: |_, dsp, value| dsp.set_signal_hint(value as usize),
+	},
+
+	ForceChannels = "force_channels" => Descriptor {
+		text: text("Force Channels", "FChn", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&FORCE_CHANNELS),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.force_channels()? as f64),
+		set: |_, dsp, value| dsp.set_force_channels(value as usize),
+	},
+
+	PredictionDisabled = "prediction_disabled" => Descriptor {
+		text: text("Disable Prediction", "NoPr", ""),
+		unit: Unit::Encoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.prediction_disabled()? as u8 as f64),
+		set: |_, dsp, value| dsp.set_prediction_disabled(value > 0.5),
+	},
+
+	LossInstance = "loss_instance" => Descriptor {
+		text: text("Loss Instance", "Inst", ""),
+		unit: Unit::Network,
+		range: Range::Stepped {
+			min: 0.0,
+			max: loss::MAX_INSTANCE as f64,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Custom(|value| match value as u32 {
+			0 => "Shared".to_string(),
+			n => format!("{}", n),
+		}),
+		get: |_, dsp| Ok(f64::from(dsp.loss_instance)),
+		set: |_, dsp, value| {
+			dsp.loss_instance = value as u32;
+			Ok(())
+		},
+	},
+
+	PhaseInversionDisabled = "phase_inversion_disabled" => Descriptor {
+		text: text("Disable Phase Inversion", "NoPh", ""),
+		unit: Unit::Encoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.phase_inversion_disabled()? as u8 as f64),
+		set: |_, dsp, value| dsp.set_phase_inversion_disabled(value > 0.5),
+	},
+
+	Preroll = "preroll" => Descriptor {
+		text: text("Encoder Pre-roll", "Prer", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&PREROLLS),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.preroll as f64),
+		set: |_, dsp, value| {
+			dsp.preroll = (value as usize).min(PREROLLS.len() - 1);
+			Ok(())
+		},
+	},
+
+	AdaptiveNarrowing = "adaptive_narrowing" => Descriptor {
+		text: text("Adaptive Narrowing", "Narr", ""),
+		unit: Unit::Encoder,
+		range: Range::List(&adaptive::NARROWINGS),
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.adaptive.narrowing as f64),
+		set: |_, dsp, value| {
+			dsp.adaptive.narrowing = (value as usize).min(adaptive::NARROWINGS.len() - 1);
+			dsp.apply_bandwidth()
+		},
+	},
+
+	DecoderGain = "decoder_gain" => Descriptor {
+		text: text("Decoder Gain", "DGn", "dB"),
+		unit: Unit::Decoder,
+		range: Range::Continuous {
+			min: -MAX_DECODER_GAIN_DB,
+			max: MAX_DECODER_GAIN_DB,
+		},
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Signed(1),
+		get: |_, dsp| Ok(f64::from(dsp.decoder.gain()?) / 256.0),
+		set: |_, dsp, value| Ok(dsp.decoder.set_gain((value * 256.0).round() as i32)?),
+	},
+
+	InbandFec = "inband_fec" => Descriptor {
+		text: text("Inband FEC", "IFEC", ""),
+		unit: Unit::Encoder,
+		range: Range::Toggle,
+		default: 0.0,
+		flags: AUTOMATE,
+		format: Format::Label,
+		get: |_, dsp| Ok(dsp.encoder.inband_fec()? as u8 as f64),
+		set: |_, dsp, value| Ok(dsp.encoder.set_inband_fec(value > 0.5)?),
+	},
+
+	LostRoundRobin = "lost_round_robin" => loss_descriptor(text("Lost to Round Robin", "LsRR", "")),
+
+	RecordedEvents = "recorded_events" => Descriptor {
+		text: text("Recorded Events", "REvt", ""),
+		unit: Unit::Root,
+		range: Range::Continuous {
+			min: 0.0,
+			max: EVENT_RANGE,
+		},
+		default: 0.0,
+		flags: ParameterFlags::kIsReadOnly as i32 | KIS_HIDDEN,
+		format: Format::Decimal(0),
+		get: |_, dsp| Ok(f64::from(dsp.events.recorded()) % EVENT_RANGE),
+		set: |_, _, _| Ok(()),
+	},
+}
+
+impl Parameter {
+	/// Index into the loss pattern for step parameters
+	pub fn step(self) -> Option<usize> {
+		let first: u32 = Self::Step1.into();
+		let id: u32 = self.into();
+		match id.checked_sub(first) {
+			Some(index) if (index as usize) < loss::STEPS => Some(index as usize),
+			_ => None,
 		}
 	}
 
-	pub fn get_param_string_by_value(&self, value: f64) -> Option<String> {
+	/// Cause counted by a lost packet counter
+	pub fn loss_cause(self) -> Option<LossCause> {
 		match self {
-			Self::Bypass => None,
-			Self::Complexity => Some(format!("{:.0}", value * 10.0)),
-			Self::PredictedLoss => Some(format!("{:.0}", value * 100.0)),
-			Self::RandomLoss => Some(format!("{:.2}", value * 100.0)),
-			Self::RoundRobinLoss => Some(format!("{:.2}", value * 100.0)),
-			Self::MaxBandwith => Some(
-				match bandwidth_from_value(value) {
-					Bandwidth::Narrowband => "4",
-					Bandwidth::Mediumband => "6",
-					Bandwidth::Wideband => "8",
-					Bandwidth::Superwideband => "12",
-					Bandwidth::Fullband => "20",
-					Bandwidth::Auto => "Auto",
-				}
-				.to_string(),
-			),
+			Self::LostPattern => Some(LossCause::Pattern),
+			Self::LostEuclidean => Some(LossCause::Euclidean),
+			Self::LostRandom => Some(LossCause::Random),
+			Self::LostOversized => Some(LossCause::Oversized),
+			Self::LostManual => Some(LossCause::Manual),
+			Self::LostLink => Some(LossCause::Link),
+			Self::LostRoundRobin => Some(LossCause::RoundRobin),
+			_ => None,
 		}
 	}
 
-	pub fn get_param_value_by_string(&self, _string: &str) -> Option<f64> {
-		match self {
-			Self::Bypass => None,
-			Self::PredictedLoss => None,
-			Self::Complexity => None,
-			Self::MaxBandwith => None,
-			Self::RandomLoss => None,
-			Self::RoundRobinLoss => None,
+	/// Read-only counter for a cause
+	pub fn loss_counter(cause: LossCause) -> Self {
+		match cause {
+			LossCause::Pattern => Self::LostPattern,
+			LossCause::Euclidean => Self::LostEuclidean,
+			LossCause::Random => Self::LostRandom,
+			LossCause::Oversized => Self::LostOversized,
+			LossCause::Manual => Self::LostManual,
+			LossCause::Link => Self::LostLink,
+			LossCause::RoundRobin => Self::LostRoundRobin,
 		}
 	}
 
-	pub fn normalized_param_to_plain(&self, value: f64) -> f64 {
+	/// The parameter a nudge button steps, and by how many steps
+	pub fn nudge(self) -> Option<(Self, f64)> {
 		match self {
-			Self::Bypass => value,
-			Self::PredictedLoss => value,
-			Self::Complexity => value,
-			Self::MaxBandwith => value,
-			Self::RandomLoss => value,
-			Self::RoundRobinLoss => value,
+			Self::ComplexityUp => Some((Self::Complexity, 1.0)),
+			Self::ComplexityDown => Some((Self::Complexity, -1.0)),
+			Self::BandwidthUp => Some((Self::MaxBandwith, 1.0)),
+			Self::BandwidthDown => Some((Self::MaxBandwith, -1.0)),
+			_ => None,
 		}
 	}
 
-	pub fn plain_param_to_normalized(&self, plain_value: f64) -> f64 {
-		match self {
-			Self::Bypass => plain_value,
-			Self::PredictedLoss => plain_value,
-			Self::Complexity => plain_value,
-			Self::MaxBandwith => plain_value,
-			Self::RandomLoss => plain_value,
-			Self::RoundRobinLoss => plain_value,
+	/// Output parameters are written by the processor, and never saved
+	pub fn is_read_only(self) -> bool {
+		self.descriptor().flags & ParameterFlags::kIsReadOnly as i32 != 0
+	}
+
+	/// Verbosity and the monitor tap are only meant to last a session, and restoring is
+	/// an action, so projects don't keep them
+	pub fn is_saved(self) -> bool {
+		!self.is_read_only()
+			&& !matches!(
+				self,
+				Self::Verbosity | Self::RestoreSnapshot | Self::Monitor | Self::DropNow
+			) && self.nudge().is_none()
+	}
+
+	/// Shown by the codec class, which has no network or far end
+	pub fn is_codec(self) -> bool {
+		matches!(self.descriptor().unit, Unit::Root | Unit::Encoder)
+	}
+
+	/// Changes that rebuild the coders, the processor crossfades to a spare with them
+	/// instead of restarting the coders under the playing audio
+	pub fn is_destructive(self) -> bool {
+		matches!(
+			self,
+			Self::InternalRate | Self::CodecLayer | Self::LowRateFallback | Self::FrameDuration
+		)
+	}
+
+	/// Only continuous parameters can be bent, steps and lists stay evenly spaced
+	pub fn has_curve(self) -> bool {
+		matches!(self.descriptor().range, Range::Continuous { .. }) && !self.is_read_only()
+	}
+
+	fn to_plain(self, value: f64, curve: Curve) -> f64 {
+		let range = self.descriptor().range;
+		if self.has_curve() {
+			range.to_plain(curve.shape(value))
+		} else {
+			range.to_plain(value)
+		}
+	}
+
+	fn to_normalized(self, plain: f64, curve: Curve) -> f64 {
+		let range = self.descriptor().range;
+		if self.has_curve() {
+			curve.unshape(range.to_normalized(plain))
+		} else {
+			range.to_normalized(plain)
+		}
+	}
+
+	/// English title, short title, and units
+	pub fn text(self) -> Text {
+		self.descriptor().text
+	}
+
+	pub fn default_normalized(self) -> f64 {
+		let descriptor = self.descriptor();
+		descriptor.range.to_normalized(descriptor.default)
+	}
+
+	pub fn get_from_dsp(self, dsp: &OpusDSP) -> Result<f64> {
+		let plain = (self.descriptor().get)(self, dsp)?;
+		Ok(self.to_normalized(plain, dsp.curves[self]))
+	}
+
+	pub fn set_to_dsp(self, dsp: &mut OpusDSP, value: f64) -> Result<()> {
+		let plain = self.to_plain(value, dsp.curves[self]);
+		(self.descriptor().set)(self, dsp, plain)
+	}
+
+	#[cfg(feature = "vst3")]
+	pub fn get_parameter_info(self, locale: Locale, curve: Curve) -> ParameterInfo {
+		let descriptor = self.descriptor();
+		let text = locale::translate(self, locale).unwrap_or(descriptor.text);
+		let (title, short_title) = match self.step() {
+			Some(step) => (
+				vst_str::str_16(&format!("{} {}", text.title, step + 1)),
+				vst_str::str_16(&format!("{}{}", text.short_title, step + 1)),
+			),
+			None => (
+				vst_str::str_16(text.title),
+				vst_str::str_16(text.short_title),
+			),
+		};
+
+		ParameterInfo {
+			id: self.into(),
+			title,
+			short_title,
+			units: vst_str::str_16(text.units),
+			step_count: descriptor.range.step_count(),
+			default_normalized_value: self.to_normalized(descriptor.default, curve),
+			unit_id: descriptor.unit.into(),
+			flags: descriptor.flags,
 		}
 	}
+
+	/// Numbers get the units, like "12 kHz" or "35 %", labels like "Auto" stand alone
+	pub fn get_param_string_by_value(
+		&self,
+		value: f64,
+		locale: Locale,
+		curve: Curve,
+	) -> Option<String> {
+		let descriptor = self.descriptor();
+		let plain = self.to_plain(value, curve);
+		let (string, numeric) = match descriptor.format {
+			Format::Decimal(precision) => (format!("{:.*}", precision, plain), true),
+			Format::Signed(precision) => (format!("{:+.*}", precision, plain), true),
+			Format::Label => {
+				let label = descriptor.range.labels()?.get(plain as usize)?;
+				(label.to_string(), label.parse::<f64>().is_ok())
+			}
+			Format::Custom(format) | Format::Parsed { format, .. } => return Some(format(plain)),
+		};
+
+		let string = string.replace('.', &locale.decimal_separator().to_string());
+		let units = locale::translate(*self, locale)
+			.unwrap_or(descriptor.text)
+			.units;
+		if numeric && !units.is_empty() {
+			Some(format!("{} {}", string, units))
+		} else {
+			Some(string)
+		}
+	}
+
+	/// Accepts what get_param_string_by_value shows, with or without units
+	pub fn get_param_value_by_string(
+		&self,
+		string: &str,
+		locale: Locale,
+		curve: Curve,
+	) -> Option<f64> {
+		let descriptor = self.descriptor();
+		let localized = locale::translate(*self, locale).unwrap_or(descriptor.text);
+		let mut string = string.trim();
+		for units in [localized.units, descriptor.text.units].iter() {
+			if !units.is_empty() {
+				string = string.strip_suffix(units).unwrap_or(string).trim_end();
+			}
+		}
+		let string = string.replace(locale.decimal_separator(), ".");
+		let string = string.as_str();
+		let plain = match descriptor.format {
+			Format::Label => descriptor
+				.range
+				.labels()?
+				.iter()
+				.position(|label| label.eq_ignore_ascii_case(string))? as f64,
+			Format::Decimal(_) | Format::Signed(_) => string.parse::<f64>().ok()?,
+			Format::Parsed { parse, .. } => parse(string)?,
+			_ => return None,
+		};
+		Some(self.to_normalized(plain, curve))
+	}
+
+	pub fn normalized_param_to_plain(&self, value: f64, curve: Curve) -> f64 {
+		self.to_plain(value, curve)
+	}
+
+	pub fn plain_param_to_normalized(&self, plain_value: f64, curve: Curve) -> f64 {
+		self.to_normalized(plain_value, curve)
+	}
 }
@@ -0,0 +1,63 @@
+use super::dsp::MAX_PACKET_BYTES;
+
+/// Encoded packets held past the block they were made in, in slabs allocated up front
+/// so holding them never allocates on the audio thread
+pub struct PacketPool {
+	slabs: Vec<[u8; MAX_PACKET_BYTES]>,
+	lens: Vec<usize>,
+	free: Vec<usize>,
+}
+
+/// A slab taken from the pool, given back with `PacketPool::release`
+#[derive(Debug)]
+pub struct Packet(usize);
+
+impl PacketPool {
+	/// Room for `capacity` packets, all allocated up front
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			slabs: vec![[0; MAX_PACKET_BYTES]; capacity],
+			lens: vec![0; capacity],
+			free: (0..capacity).rev().collect(),
+		}
+	}
+
+	/// Copy the packet into a free slab, None when every slab is taken
+	pub fn store(&mut self, bytes: &[u8]) -> Option<Packet> {
+		let index = self.free.pop()?;
+		let len = bytes.len().min(MAX_PACKET_BYTES);
+		self.slabs[index][..len].copy_from_slice(&bytes[..len]);
+		self.lens[index] = len;
+		Some(Packet(index))
+	}
+
+	/// Bytes of a stored packet
+	pub fn get(&self, packet: &Packet) -> &[u8] {
+		&self.slabs[packet.0][..self.lens[packet.0]]
+	}
+
+	/// Give a packet's slab back for the next one
+	pub fn release(&mut self, packet: Packet) {
+		self.free.push(packet.0);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::PacketPool;
+
+	#[test]
+	fn reuses_released_slabs() {
+		let mut pool = PacketPool::new(2);
+		let a = pool.store(&[1, 2, 3]).unwrap();
+		let b = pool.store(&[4]).unwrap();
+		assert!(pool.store(&[5]).is_none());
+		assert_eq!(pool.get(&a), &[1, 2, 3]);
+		assert_eq!(pool.get(&b), &[4]);
+
+		pool.release(a);
+		let c = pool.store(&[6, 7]).unwrap();
+		assert_eq!(pool.get(&c), &[6, 7]);
+		assert!(pool.store(&[8]).is_none());
+	}
+}
@@ -0,0 +1,132 @@
+#[cfg(feature = "vst3")]
+use log::*;
+#[cfg(feature = "vst3")]
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
+use std::ptr::null_mut;
+#[cfg(feature = "vst3")]
+use std::slice;
+#[cfg(feature = "vst3")]
+use vst3_com::{ComInterface, ComPtr};
+#[cfg(feature = "vst3")]
+use vst3_sys::base::{kResultFalse, kResultOk, tresult};
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IConnectionPoint, IHostApplication, IMessage};
+
+/// Key of the preset name in saved state
+pub const STATE_KEY: &str = "preset_name";
+
+/// The root unit's program list, holding only the current preset
+pub const PROGRAM_LIST_ID: i32 = 0;
+
+/// Shown while the preset has no name
+pub const UNTITLED: &str = "Untitled";
+
+/// Longest name kept, in bytes, so it still fits a String128 after conversion
+pub const MAX_LEN: usize = 127;
+
+/// Trimmed to a single line that fits, on a character boundary
+pub fn clean(name: &str) -> String {
+	let name = name.lines().next().unwrap_or_default().trim();
+	let mut end = name.len().min(MAX_LEN);
+	while !name.is_char_boundary(end) {
+		end -= 1;
+	}
+	name[..end].to_owned()
+}
+
+/// Name shown for a preset, untitled ones get a placeholder
+pub fn display(name: &str) -> &str {
+	if name.is_empty() {
+		UNTITLED
+	} else {
+		name
+	}
+}
+
+const MESSAGE_ID: &[u8] = b"OpusPresetName\0";
+const ATTRIBUTE_ID: &[u8] = b"name\0";
+
+/// Tell the peer the preset was renamed
+#[cfg(feature = "vst3")]
+pub unsafe fn send(context: *mut c_void, peer: *mut c_void, name: &str) -> tresult {
+	if context.is_null() || peer.is_null() {
+		return kResultFalse;
+	}
+
+	let host: ComPtr<dyn IHostApplication> = ComPtr::new(context as *mut *mut _);
+	let mut cid = <dyn IMessage as ComInterface>::IID;
+	let mut iid = <dyn IMessage as ComInterface>::IID;
+	let mut message = null_mut();
+	let result = host.create_instance(&mut cid, &mut iid, &mut message);
+	if result != kResultOk || message.is_null() {
+		warn!("preset: host can't create messages");
+		return result;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	message.set_message_id(MESSAGE_ID.as_ptr() as *const _);
+
+	let attributes = message.get_attributes();
+	if !attributes.is_null() {
+		let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+		attributes.set_binary(
+			ATTRIBUTE_ID.as_ptr() as *const _,
+			name.as_ptr() as *const c_void,
+			name.len() as u32,
+		);
+	}
+
+	let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+	let result = peer.notify(message.as_raw() as *mut c_void);
+	message.release();
+	result
+}
+
+/// The name from a rename message, a UTF-8 binary attribute, None for any other message
+#[cfg(feature = "vst3")]
+pub unsafe fn receive(message: *mut c_void) -> Option<String> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut data = std::ptr::null();
+	let mut size = 0;
+	let id = ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_binary(id, &mut data, &mut size) != kResultOk {
+		return None;
+	}
+	if data.is_null() {
+		return Some(String::new());
+	}
+
+	let bytes = slice::from_raw_parts(data as *const u8, size as usize);
+	Some(clean(&String::from_utf8_lossy(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clean_keeps_one_short_line() {
+		assert_eq!(clean("  Phone call \nsecond line"), "Phone call");
+		let long = "é".repeat(100);
+		let cleaned = clean(&long);
+		assert!(cleaned.len() <= MAX_LEN);
+		assert!(cleaned.chars().all(|c| c == 'é'));
+	}
+}
@@ -1,17 +1,51 @@
+use super::bursts;
+use super::bursts::BurstRuns;
+use super::coloration::ImpulseFile;
+use super::curve;
+use super::curve::Curve;
+use super::dsp::last_point;
 use super::dsp::upgrade_param_changes;
+use super::dsp::write_output_param;
+use super::dsp::AuxInputs;
 use super::dsp::OpusDSP;
+use super::dsp::ParamQueueMap;
+use super::events;
+use super::events::EventReader;
+use super::inbox::Delivery;
+use super::inbox::Inbox;
+use super::link::Opener;
+use super::lock;
+use super::loss::LossCause;
+use super::null;
+use super::null::NullCheck;
 use super::params::Parameter;
+use super::preset;
+use super::selftest;
+use super::shared;
+use super::shared::SharedParams;
+use super::snapshot;
+use super::spare::Builder;
+use super::spare::Spare;
+use super::spare::Template;
+use super::state;
+use super::warnings;
+use super::warnings::Warnings;
+use super::ConnectionPtr;
 use super::ContextPtr;
 use super::VstClassInfo;
+use crate::error::Error;
+use crate::error::Result;
 use crate::vst_result;
 use crate::vst_str;
 use enum_map::EnumMap;
 use hex_literal::hex;
 use log::*;
 use std::cell::RefCell;
-use std::mem::size_of;
+use std::ffi::CStr;
 use std::ptr::null_mut;
 use std::slice;
+use std::sync::Arc;
+use std::time::Instant;
 use vst3_com::{c_void, sys::GUID, ComPtr, IID};
 use vst3_sys::base::kInvalidArgument;
 use vst3_sys::base::ClassCardinality;
@@ -19,7 +53,13 @@ use vst3_sys::base::{
 	kNotImplemented, kResultFalse, kResultOk, kResultTrue, tresult, IBStream, IPluginBase, TBool,
 };
 use vst3_sys::vst::kStereo;
+use vst3_sys::vst::AudioBusBuffers;
 use vst3_sys::vst::BusDirections;
+use vst3_sys::vst::BusTypes;
+use vst3_sys::vst::IAttributeList;
+use vst3_sys::vst::IConnectionPoint;
+use vst3_sys::vst::IMessage;
+use vst3_sys::vst::IoModes;
 use vst3_sys::vst::MediaTypes;
 use vst3_sys::vst::SpeakerArrangement;
 use vst3_sys::vst::{
@@ -33,6 +73,11 @@ const KAUDIO: MediaType = MediaTypes::kAudio as MediaType;
 const KEVENT: MediaType = MediaTypes::kEvent as MediaType;
 const KINPUT: MediaType = BusDirections::kInput as BusDirection;
 const KOUTPUT: MediaType = BusDirections::kOutput as BusDirection;
+const KMAIN: BusType = BusTypes::kMain as BusType;
+const KAUX: BusType = BusTypes::kAux as BusType;
+const KSIMPLE: IoMode = IoModes::kSimple as IoMode;
+const KADVANCED: IoMode = IoModes::kAdvanced as IoMode;
+const KOFFLINE: IoMode = IoModes::kOfflineProcessing as IoMode;
 
 pub struct AudioBus {
 	name: [i16; 128],
@@ -43,18 +88,46 @@ pub struct AudioBus {
 }
 
 struct CurrentProcessorMode(i32);
+struct CurrentIoMode(IoMode);
+struct Processing(bool);
+struct SpareSlot(Option<Spare>);
+struct WaitingImpulse(Option<Arc<ImpulseFile>>);
+struct PresetName(String);
 struct ProcessSetupWrapper(ProcessSetup);
 struct AudioInputs(Vec<AudioBus>);
 struct AudioOutputs(Vec<AudioBus>);
 
-#[VST3(implements(IComponent, IAudioProcessor))]
+#[VST3(implements(IComponent, IAudioProcessor, IConnectionPoint))]
 pub struct OpusProcessor {
 	current_process_mode: RefCell<CurrentProcessorMode>,
+	io_mode: RefCell<CurrentIoMode>,
 	process_setup: RefCell<ProcessSetupWrapper>,
 	audio_inputs: RefCell<AudioInputs>,
 	audio_outputs: RefCell<AudioOutputs>,
 	context: RefCell<ContextPtr>,
 	opus_dsp: RefCell<OpusDSP>,
+	/// The playing DSP's burst histogram, read in notify()
+	bursts: Arc<BurstRuns>,
+	/// The playing DSP's events, drained in notify()
+	events: EventReader,
+	peer: RefCell<ConnectionPtr>,
+	shared: Arc<SharedParams>,
+	/// What notify() received, for process() to pick up
+	inbox: Inbox,
+	/// The warnings the controller was sent
+	shown: RefCell<Warnings>,
+	processing: RefCell<Processing>,
+	spare: RefCell<SpareSlot>,
+	/// An impulse response from notify(), until a spare is built with it
+	impulse: RefCell<WaitingImpulse>,
+	/// Builds the spares destructive changes need, off the audio thread
+	builder: RefCell<Builder>,
+	/// Opens the link's sockets for the DSP and its spares, None for the codec class
+	opener: Option<Opener>,
+	/// Saved with the state so sessions and exported chunks say what they are
+	preset_name: RefCell<PresetName>,
+	/// The codec class, without the network or the noise bus
+	codec_only: bool,
 }
 
 impl OpusProcessor {
@@ -67,12 +140,35 @@ impl OpusProcessor {
 		name: "Opus Parvulum",
 		category: "Audio Module Class",
 		subcategories: "Fx",
-		class_flags: 1, // 1 distributable, 2 simple io supported
+		class_flags: 1 | 2, // 1 distributable, 2 simple io supported
+		cardinality: ClassCardinality::kManyInstances as i32,
+	};
+
+	pub const CODEC_CID: IID = GUID {
+		data: hex!("6e0f4b1d9c2a47e3b85d0a3f27c91e68"),
+	};
+
+	pub const CODEC_INFO: VstClassInfo = VstClassInfo {
+		cid: Self::CODEC_CID,
+		name: "Opus Parvulum Codec",
+		category: "Audio Module Class",
+		subcategories: "Fx",
+		class_flags: 1 | 2, // 1 distributable, 2 simple io supported
 		cardinality: ClassCardinality::kManyInstances as i32,
 	};
 
 	pub fn new() -> Box<Self> {
+		Self::build(false)
+	}
+
+	/// Encoder straight into the decoder, with only the encoder's parameters
+	pub fn new_codec() -> Box<Self> {
+		Self::build(true)
+	}
+
+	fn build(codec_only: bool) -> Box<Self> {
 		let current_process_mode = RefCell::new(CurrentProcessorMode(0));
+		let io_mode = RefCell::new(CurrentIoMode(KADVANCED));
 		let process_setup = RefCell::new(ProcessSetupWrapper(ProcessSetup {
 			process_mode: 0,
 			symbolic_sample_size: 0,
@@ -82,14 +178,40 @@ impl OpusProcessor {
 		let audio_inputs = RefCell::new(AudioInputs(vec![]));
 		let audio_outputs = RefCell::new(AudioOutputs(vec![]));
 		let context = RefCell::new(ContextPtr(null_mut()));
-		let opus_dsp = RefCell::new(OpusDSP::default());
+		let opener = if codec_only { None } else { Opener::start() };
+		let opus_dsp = RefCell::new(Self::new_dsp(codec_only, opener.as_ref()));
+		let bursts = opus_dsp.borrow().bursts.shared();
+		let events = opus_dsp.borrow_mut().events.take_reader().unwrap();
+		let peer = RefCell::new(ConnectionPtr(null_mut()));
+		let shared = Arc::new(SharedParams::default());
+		let inbox = Inbox::default();
+		let shown = RefCell::new(Warnings::default());
+		let processing = RefCell::new(Processing(false));
+		let spare = RefCell::new(SpareSlot(None));
+		let impulse = RefCell::new(WaitingImpulse(None));
+		let builder = RefCell::new(Builder::start(opener.clone()));
+		let preset_name = RefCell::new(PresetName(String::new()));
 		Self::allocate(
 			current_process_mode,
+			io_mode,
 			process_setup,
 			audio_inputs,
 			audio_outputs,
 			context,
 			opus_dsp,
+			bursts,
+			events,
+			peer,
+			shared,
+			inbox,
+			shown,
+			processing,
+			spare,
+			impulse,
+			builder,
+			opener,
+			preset_name,
+			codec_only,
 		)
 	}
 
@@ -97,27 +219,451 @@ impl OpusProcessor {
 		Box::into_raw(Self::new()) as *mut c_void
 	}
 
-	pub unsafe fn add_audio_input(&self, name: &str, arr: SpeakerArrangement) {
+	pub fn create_codec_instance() -> *mut c_void {
+		Box::into_raw(Self::new_codec()) as *mut c_void
+	}
+
+	fn new_dsp(codec_only: bool, opener: Option<&Opener>) -> OpusDSP {
+		let mut dsp = OpusDSP::default();
+		if codec_only {
+			dsp.set_codec_only();
+		}
+		if let Some(opener) = opener {
+			dsp.link.attach(opener);
+		}
+		dsp
+	}
+
+	pub unsafe fn add_audio_input(&self, name: &str, bus_type: BusType, arr: SpeakerArrangement) {
 		let new_bus = AudioBus {
 			name: vst_str::str_16(name),
-			bus_type: 0,
-			flags: 1,
+			bus_type,
+			// Only main buses are active by default
+			flags: (bus_type == KMAIN) as i32,
 			active: false as u8,
 			speaker_arr: arr,
 		};
 		self.audio_inputs.borrow_mut().0.push(new_bus);
 	}
 
-	pub unsafe fn add_audio_output(&self, name: &str, arr: SpeakerArrangement) {
+	pub unsafe fn add_audio_output(&self, name: &str, bus_type: BusType, arr: SpeakerArrangement) {
 		let new_bus = AudioBus {
 			name: vst_str::str_16(name),
-			bus_type: 0,
-			flags: 1,
+			bus_type,
+			flags: (bus_type == KMAIN) as i32,
 			active: false as u8,
 			speaker_arr: arr,
 		};
 		self.audio_outputs.borrow_mut().0.push(new_bus);
 	}
+
+	/// Normalized values of every parameter, from the DSP
+	pub fn parameter_values(&self) -> Result<EnumMap<Parameter, f64>> {
+		let dsp = self.opus_dsp.try_borrow()?;
+		let mut params = EnumMap::<Parameter, f64>::default();
+		for (param, value) in params.iter_mut() {
+			*value = param.get_from_dsp(&dsp)?;
+		}
+		Ok(params)
+	}
+
+	/// Bounces get the best quality regardless of the realtime settings
+	fn is_offline(&self, process_mode: i32) -> bool {
+		process_mode == 2 || self.io_mode.borrow().0 == KOFFLINE
+	}
+
+	/// Pass new warnings on to the controller, they wait until there is one
+	unsafe fn send_warnings(&self, warnings: &mut Warnings) {
+		if self.peer.borrow().0.is_null() {
+			return;
+		}
+		if warnings.take_unsent().is_some() {
+			self.shown.borrow_mut().copy_from(warnings);
+		}
+		self.send_shown();
+	}
+
+	/// The warning of a file notify() loaded, shown before process() hands it to the DSP
+	unsafe fn show_warning(&self, source: &'static str, line: Option<String>) {
+		self.shown.borrow_mut().replace(source, line);
+		if !self.peer.borrow().0.is_null() {
+			self.send_shown();
+		}
+	}
+
+	unsafe fn send_shown(&self) {
+		if let Some(text) = self.shown.borrow_mut().take_unsent() {
+			let result = warnings::send(self.context.borrow().0, self.peer.borrow().0, &text);
+			info!("send_warnings() => {}", result);
+		}
+	}
+
+	/// Nothing calls process() outside of processing, so what notify() received goes
+	/// into the DSP here
+	fn apply_inbox_when_stopped(&self) -> Result<()> {
+		if !self.processing.borrow().0 {
+			let mut dsp = self.opus_dsp.try_borrow_mut()?;
+			let mut impulse = self.impulse.try_borrow_mut()?;
+			self.inbox.apply(&mut dsp, None, &mut impulse.0);
+			if let Some(file) = impulse.0.take() {
+				dsp.coloration.set_impulse(Some(file));
+			}
+		}
+		Ok(())
+	}
+
+	/// The settings a render starts with, headed by what the parameters don't cover
+	unsafe fn send_snapshot(&self, dsp: &OpusDSP, mode: &str, setup: &ProcessSetup) -> Result<()> {
+		let values = state::plain_values(dsp)?;
+		let text = format!(
+			"{} {}\npreset: {}\nmode: {}\nsample_rate: {}\n{}",
+			env!("CARGO_PKG_NAME"),
+			env!("CARGO_PKG_VERSION"),
+			preset::display(&self.preset_name.try_borrow()?.0),
+			mode,
+			setup.sample_rate,
+			state::snapshot(&values)
+		);
+		let peer = self.peer.borrow().0;
+		let result = snapshot::send(self.context.borrow().0, peer, &text);
+		info!("send_snapshot() => {}\n{}", result, text);
+		Ok(())
+	}
+
+	/// Offline and prefetch both render ahead of playback
+	fn is_rendering(&self, process_mode: i32) -> bool {
+		process_mode != 0 || self.io_mode.borrow().0 == KOFFLINE
+	}
+
+	/// A DSP with the parameters and curves of `from`, set up for `setup`
+	fn copy_dsp(&self, from: &OpusDSP, setup: &ProcessSetup) -> Result<Box<OpusDSP>> {
+		let offline = self.is_offline(setup.process_mode);
+		let rendering = self.is_rendering(setup.process_mode);
+		let template = Template::of(from, *setup, offline, rendering, self.codec_only)?;
+		let mut next = template.build(self.opener.as_ref())?;
+		// Not a parameter, and a spare taking over mid-render must keep comparing
+		next.null = from.null.clone();
+		Ok(next)
+	}
+
+	/// Take destructive changes out of the block and the shared store and have a spare
+	/// with them built off the audio thread, starting from a spare that is already
+	/// building or warming so its settings carry over. A spare that finished building
+	/// starts warming here
+	unsafe fn crossfade_destructive(
+		&self,
+		dsp: &mut OpusDSP,
+		params: &mut ParamQueueMap,
+	) -> Result<()> {
+		let mut spare = self.spare.try_borrow_mut()?;
+		let mut builder = self.builder.try_borrow_mut()?;
+		let mut impulse = self.impulse.try_borrow_mut()?;
+		if let Some(next) = builder.take_built() {
+			if let Some(old) = spare.0.replace(next) {
+				builder.retire(old);
+			}
+			info!("process() spare DSP built, warming it");
+		}
+		// The host's changes come later in the block, so they win over the controller's
+		let shared = self.shared.take_destructive();
+
+		// Without a builder the changes stay in the block, and the coders only catch up
+		// with them at the next setup, rebuilding them here would allocate
+		if !builder.is_running() {
+			for (param, value) in shared.iter() {
+				if let Some(value) = value {
+					if let Err(err) = param.set_to_dsp(dsp, *value) {
+						warn!("process() {:?} to {}: {}", param, value, err);
+					}
+				}
+			}
+			return Ok(());
+		}
+
+		let current = spare.0.as_ref().map_or(&*dsp, |spare| &*spare.dsp);
+		let mut changes = EnumMap::<Parameter, Option<f64>>::default();
+		let mut changed = false;
+		for (param, queue) in params.iter() {
+			if !param.is_destructive() {
+				continue;
+			}
+			let last = queue.as_ref().and_then(|queue| last_point(&**queue));
+			if let Some(value) = last.or(shared[param]) {
+				let playing = match builder.pending() {
+					Some(pending) => pending.values[param],
+					None => param.get_from_dsp(current)?,
+				};
+				let plain = param.normalized_param_to_plain(value, Curve::Linear);
+				if plain != param.normalized_param_to_plain(playing, Curve::Linear) {
+					changes[param] = Some(value);
+					changed = true;
+				}
+			}
+		}
+
+		// A new impulse response needs its convolver prepared, which allocates
+		changed |= impulse.0.is_some();
+
+		// Coders that failed, or changes a full builder left in place, need a spare as well
+		let rebuild = !changed
+			&& builder.pending().is_none()
+			&& !builder.has_failed()
+			&& current.needs_rebuild();
+
+		if changed || rebuild {
+			let mut template = match builder.pending() {
+				Some(pending) => pending.clone(),
+				None => {
+					let setup = self.process_setup.borrow().0;
+					let offline = self.is_offline(setup.process_mode);
+					let rendering = self.is_rendering(setup.process_mode);
+					Template::of(current, setup, offline, rendering, self.codec_only)?
+				}
+			};
+			for (param, value) in changes.iter() {
+				if let Some(value) = value {
+					template.values[param] = *value;
+				}
+			}
+			if let Some(file) = &impulse.0 {
+				template.impulse = Some(file.clone());
+			}
+			// A full builder leaves the changes in the block, applied in place, and the
+			// controller's in the store for the next block
+			if !builder.request(template) {
+				for (param, value) in shared.iter() {
+					if let Some(value) = value {
+						self.shared.set(param, *value);
+					}
+				}
+				return Ok(());
+			}
+			if rebuild {
+				info!("process() coders need rebuilding, building a spare DSP");
+			}
+			// The pending template holds it now, so this doesn't free it
+			if impulse.0.take().is_some() {
+				info!("process() impulse response changed, building a spare DSP");
+			}
+			for (param, value) in changes.iter() {
+				if let Some(value) = value {
+					info!(
+						"process() {:?} changed to {}, building a spare DSP",
+						param, value
+					);
+				}
+			}
+		}
+
+		for (param, queue) in params.iter_mut() {
+			if param.is_destructive() {
+				*queue = None;
+			}
+		}
+		Ok(())
+	}
+
+	/// Store the controller writes to directly, when they share a process
+	pub fn shared_params(&self) -> Arc<SharedParams> {
+		self.shared.clone()
+	}
+
+	/// Number of buses visible to the host, simple mode only shows the main pair
+	fn visible_buses(&self, count: usize) -> usize {
+		match self.io_mode.borrow().0 {
+			KSIMPLE => count.min(1),
+			_ => count,
+		}
+	}
+
+	/// Buses past the main pair don't exist in simple mode
+	fn is_hidden_bus(&self, index: i32) -> bool {
+		index < 0 || index as usize >= self.visible_buses(usize::MAX)
+	}
+
+	/// Fade out over whatever blocks the host still sends, the reset waits for resume
+	/// so the fade has audio to work on
+	fn stop(&self) -> Result<()> {
+		let mut dsp = self.opus_dsp.try_borrow_mut()?;
+		// Nothing is playing, so a waiting spare can take over without a crossfade
+		if let Some(mut spare) = self.spare.try_borrow_mut()?.0.take() {
+			dsp.take_over(&mut spare.dsp);
+		}
+		dsp.fade.fade_out();
+		Ok(())
+	}
+
+	/// Start over after a stop, from silence, so no stale packet plays
+	fn resume(&self) -> Result<()> {
+		let mut dsp = self.opus_dsp.try_borrow_mut()?;
+		if dsp.fade.is_stopping() {
+			dsp.reset();
+			dsp.fade.fade_in();
+		}
+		Ok(())
+	}
+
+	/// Aux buses only count once the host turns them on
+	fn is_aux_active(&self, index: usize) -> bool {
+		let inputs = self.audio_inputs.borrow();
+		inputs.0.get(index).is_some_and(|bus| bus.active != 0)
+	}
+
+	/// The host turned the auxiliary output bus on
+	fn is_aux_output_active(&self, index: usize) -> bool {
+		let outputs = self.audio_outputs.borrow();
+		outputs.0.get(index).is_some_and(|bus| bus.active != 0)
+	}
+}
+
+const IMPULSE_MESSAGE_ID: &[u8] = b"OpusImpulseResponse\0";
+const IMPULSE_ATTRIBUTE_ID: &[u8] = b"path\0";
+
+/// Path from an impulse response message, a UTF-8 binary attribute so an editor can send any path
+unsafe fn impulse_path(message: *mut c_void) -> Option<String> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != IMPULSE_MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut data = std::ptr::null();
+	let mut size = 0;
+	let id = IMPULSE_ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_binary(id, &mut data, &mut size) != kResultOk || data.is_null() {
+		return None;
+	}
+
+	let bytes = slice::from_raw_parts(data as *const u8, size as usize);
+	String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// Index of the aux input mixed into the encoder
+const NOISE_BUS: usize = 1;
+
+/// Index of the aux input the half-duplex gate listens to
+const FAR_END_BUS: usize = 2;
+
+/// Index of the aux input the output can be switched to
+const REFERENCE_BUS: usize = 3;
+
+/// Index of the aux input whose level the adaptive bitrate can follow
+const BANDWIDTH_BUS: usize = 4;
+
+/// Output bus with the unprocessed input, aligned to the main output
+const CLEAN_BUS: usize = 1;
+
+/// Channels of the main input and output buses
+struct MainBuses<'a> {
+	input: [&'a [f32]; 2],
+	/// Aux inputs the host connected something to
+	aux: AuxInputs<'a>,
+	output: [&'a mut [f32]; 2],
+	/// The clean output, when the host gave it buffers
+	clean: Option<[&'a mut [f32]; 2]>,
+	/// Both input channels are silent
+	silent: bool,
+	output_bus: &'a mut AudioBusBuffers,
+}
+
+/// Channels of an aux input, when the host connected something that isn't silent
+unsafe fn aux_bus(
+	inputs: &[AudioBusBuffers],
+	index: usize,
+	num_samples: usize,
+) -> Option<[&[f32]; 2]> {
+	inputs
+		.get(index)
+		.filter(|bus| bus.silence_flags & 0b11 != 0b11 && !bus.buffers.is_null())
+		.map(|bus| {
+			slice::from_raw_parts(bus.buffers as *const *const f32, bus.num_channels as usize)
+		})
+		.filter(|buffers| buffers.len() >= 2 && buffers.iter().all(|buffer| !buffer.is_null()))
+		.map(|buffers| {
+			[
+				slice::from_raw_parts(buffers[0], num_samples),
+				slice::from_raw_parts(buffers[1], num_samples),
+			]
+		})
+}
+
+unsafe fn main_buses(data: &ProcessData) -> Result<MainBuses> {
+	let num_samples = data.num_samples as usize;
+
+	let inputs = slice::from_raw_parts(data.inputs, data.num_inputs as usize);
+	let in_bus = match inputs.first() {
+		Some(bus) => bus,
+		None => return Err(Error::NotInitialized("requires at least 1 input bus")),
+	};
+	let buffers = slice::from_raw_parts(
+		in_bus.buffers as *const *const f32,
+		in_bus.num_channels as usize,
+	);
+	if buffers.len() < 2 {
+		return Err(Error::NotInitialized("requires at least 2 input channels"));
+	}
+	let input = [
+		slice::from_raw_parts(buffers[0], num_samples),
+		slice::from_raw_parts(buffers[1], num_samples),
+	];
+
+	let aux = AuxInputs {
+		noise: aux_bus(inputs, NOISE_BUS, num_samples),
+		far_end: aux_bus(inputs, FAR_END_BUS, num_samples),
+		reference: aux_bus(inputs, REFERENCE_BUS, num_samples),
+		bandwidth: aux_bus(inputs, BANDWIDTH_BUS, num_samples),
+	};
+
+	let outputs = slice::from_raw_parts_mut(data.outputs, data.num_outputs as usize);
+	let (output_bus, aux_outputs) = match outputs.split_first_mut() {
+		Some(buses) => buses,
+		None => return Err(Error::NotInitialized("requires at least 1 output bus")),
+	};
+	let buffers = slice::from_raw_parts(
+		output_bus.buffers as *const *mut f32,
+		output_bus.num_channels as usize,
+	);
+	if buffers.len() < 2 {
+		return Err(Error::NotInitialized("requires at least 2 output channels"));
+	}
+	let output = [
+		slice::from_raw_parts_mut(buffers[0], num_samples),
+		slice::from_raw_parts_mut(buffers[1], num_samples),
+	];
+
+	let clean = aux_outputs
+		.get_mut(CLEAN_BUS - 1)
+		.filter(|bus| !bus.buffers.is_null())
+		.and_then(|bus| {
+			let buffers =
+				slice::from_raw_parts(bus.buffers as *const *mut f32, bus.num_channels as usize);
+			if buffers.len() < 2 || buffers.iter().any(|buffer| buffer.is_null()) {
+				return None;
+			}
+			bus.silence_flags = 0;
+			Some([
+				slice::from_raw_parts_mut(buffers[0], num_samples),
+				slice::from_raw_parts_mut(buffers[1], num_samples),
+			])
+		});
+
+	Ok(MainBuses {
+		input,
+		aux,
+		output,
+		clean,
+		silent: in_bus.silence_flags & 0b11 == 0b11,
+		output_bus,
+	})
 }
 
 fn get_channel_count(arr: SpeakerArrangement) -> i32 {
@@ -135,27 +681,43 @@ fn get_channel_count(arr: SpeakerArrangement) -> i32 {
 impl IComponent for OpusProcessor {
 	unsafe fn get_controller_class_id(&self, tuid: *mut IID) -> tresult {
 		info!("get_controller_class_id()");
-		*tuid = super::controller::OpusController::INFO.cid;
+		*tuid = if self.codec_only {
+			super::controller::OpusController::CODEC_CID
+		} else {
+			super::controller::OpusController::CID
+		};
 		kResultOk
 	}
 
 	unsafe fn set_io_mode(&self, mode: IoMode) -> tresult {
-		info!("set_io_mode(mode: {})", mode);
-		kNotImplemented
+		match mode {
+			KSIMPLE | KADVANCED | KOFFLINE => {}
+			_ => {
+				warn!("set_io_mode(mode: {}) => kInvalidArgument", mode);
+				return kInvalidArgument;
+			}
+		}
+
+		let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
+		dsp.set_offline(mode == KOFFLINE);
+		self.io_mode.borrow_mut().0 = mode;
+
+		info!("set_io_mode(mode: {}) => kResultOk", mode);
+		kResultOk
 	}
 
 	unsafe fn get_bus_count(&self, media_type: MediaType, dir: BusDirection) -> i32 {
 		let result = match media_type {
 			KAUDIO => match dir {
-				KINPUT => self.audio_inputs.borrow().0.len() as i32,
-				KOUTPUT => self.audio_outputs.borrow().0.len() as i32,
+				KINPUT => self.visible_buses(self.audio_inputs.borrow().0.len()) as i32,
+				KOUTPUT => self.visible_buses(self.audio_outputs.borrow().0.len()) as i32,
 				_ => 0,
 			},
 			KEVENT => 0,
 			_ => 0,
 		};
 
-		info!(
+		debug!(
 			"get_bus_count(media_type: {}, dir: {}) => {}",
 			media_type, dir, result
 		);
@@ -171,6 +733,14 @@ impl IComponent for OpusProcessor {
 	) -> tresult {
 		let info = &mut *info;
 
+		if self.is_hidden_bus(index) {
+			debug!(
+				"get_bus_info(index: {}) => kInvalidArgument, simple mode",
+				index
+			);
+			return kInvalidArgument;
+		}
+
 		let result = match media_type {
 			KAUDIO => match direction {
 				KINPUT => match self.audio_inputs.borrow().0.get(index as usize) {
@@ -209,7 +779,7 @@ impl IComponent for OpusProcessor {
 			_ => kInvalidArgument,
 		};
 
-		info!(
+		debug!(
 			"get_bus_info(media_type: {}, dir: {}, index: {}) => {}",
 			media_type,
 			direction,
@@ -241,6 +811,10 @@ impl IComponent for OpusProcessor {
 			media_type, dir, index, state
 		);
 
+		if self.is_hidden_bus(index) {
+			return kInvalidArgument;
+		}
+
 		let mut inputs = self.audio_inputs.borrow_mut();
 		let mut outputs = self.audio_outputs.borrow_mut();
 
@@ -270,6 +844,14 @@ impl IComponent for OpusProcessor {
 	unsafe fn set_active(&self, state: TBool) -> tresult {
 		info!("set_active(state: {})", state);
 
+		// Some hosts never call set_processing, or deactivate without it
+		let result = if state == 0 {
+			self.stop()
+		} else {
+			self.resume()
+		};
+		vst_result!(result);
+
 		kResultOk
 	}
 
@@ -279,26 +861,60 @@ impl IComponent for OpusProcessor {
 			return kResultFalse;
 		}
 
-		let mut params = EnumMap::<Parameter, f64>::default();
-
 		let state = state as *mut *mut _;
 		let state: ComPtr<dyn IBStream> = ComPtr::new(state);
-		let mut num_bytes_read = 0;
-
-		for (_, val) in params.iter_mut() {
-			let ptr = val as *mut f64 as *mut c_void;
-			state.read(ptr, size_of::<f64>() as i32, &mut num_bytes_read);
-		}
+		let chunk = vst_result!(state::read(&state));
+		let params = &chunk.values;
 
 		// Values read from saved state, into the DSP
 
 		let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
+		let before = vst_result!(state::plain_values(&dsp));
+
+		// Values were normalized along the curves saved with them
+		dsp.curves = curve::decode(chunk.string(curve::STATE_KEY).unwrap_or(""));
 
 		for (param, value) in params.iter() {
-			vst_result!(param.set_to_dsp(&mut dsp, *value));
+			if let Some(value) = value {
+				vst_result!(param.set_to_dsp(&mut dsp, *value));
+			}
+		}
+
+		// A missing file only loses the coloration, not the rest of the state
+		match chunk
+			.string(state::IMPULSE_PATH)
+			.map(|path| dsp.coloration.load_impulse(path))
+		{
+			Some(Err(err)) => {
+				let line = format!("impulse response not loaded: {}", err);
+				dsp.warnings.raise(warnings::IMPULSE, line);
+			}
+			_ => dsp.warnings.resolve(warnings::IMPULSE),
+		}
+		if let Some(path) = chunk.string(state::NULL_REFERENCE_PATH) {
+			// Warned about by the DSP, a missing render doesn't stop the session loading
+			let _ = dsp.load_null_reference(path);
+		}
+		self.send_warnings(&mut dsp.warnings);
+
+		// What changed, and what isn't default, for questions about why a session sounds different
+		let after = vst_result!(state::plain_values(&dsp));
+		let changed = state::diff(&before, &after);
+		if !changed.is_empty() {
+			info!("set_state() changed {}", changed.join(", "));
+		}
+		let custom = state::diff(&state::plain_defaults(), &after);
+		if !custom.is_empty() {
+			info!("set_state() differs from defaults in {}", custom.join(", "));
 		}
 
-		info!("set_state() => kResultOk, read {:?} f64", params.len());
+		let name = preset::clean(chunk.string(preset::STATE_KEY).unwrap_or(""));
+		vst_result!(self.preset_name.try_borrow_mut()).0 = name;
+
+		info!(
+			"set_state() => kResultOk, read {:?} values",
+			params.values().flatten().count()
+		);
 		kResultOk
 	}
 
@@ -308,25 +924,33 @@ impl IComponent for OpusProcessor {
 			return kResultFalse;
 		}
 
-		let dsp = vst_result!(self.opus_dsp.try_borrow_mut());
-		let mut params = EnumMap::<Parameter, f64>::default();
-
-		for (param, value) in params.iter_mut() {
-			*value = vst_result!(param.get_from_dsp(&dsp));
+		let params = vst_result!(self.parameter_values());
+		let dsp = vst_result!(self.opus_dsp.try_borrow());
+		let curves = curve::encode(&dsp.curves);
+		let mut strings: Vec<_> = dsp
+			.coloration
+			.impulse_path()
+			.map(|path| (state::IMPULSE_PATH, path))
+			.into_iter()
+			.collect();
+		if let Some(path) = dsp.null.path() {
+			strings.push((state::NULL_REFERENCE_PATH, path));
+		}
+		if !curves.is_empty() {
+			strings.push((curve::STATE_KEY, &curves));
+		}
+		let name = vst_result!(self.preset_name.try_borrow());
+		if !name.0.is_empty() {
+			strings.push((preset::STATE_KEY, &name.0));
 		}
 
 		// Values from the DSP, write into saved state
 
 		let state = state as *mut *mut _;
 		let state: ComPtr<dyn IBStream> = ComPtr::new(state);
-		let mut num_bytes_written = 0;
+		vst_result!(state::write(&state, &params, &strings));
 
-		for (_param, val) in params.iter() {
-			let ptr = val as *const f64 as *const c_void;
-			state.write(ptr, size_of::<f64>() as i32, &mut num_bytes_written);
-		}
-
-		info!("set_state() => kResultOk, wrote {:?} f64", params.len());
+		info!("get_state() => kResultOk, wrote {:?} values", params.len());
 		kResultOk
 	}
 }
@@ -340,8 +964,26 @@ impl IPluginBase for OpusProcessor {
 		}
 		self.context.borrow_mut().0 = context;
 
-		self.add_audio_input("Stereo In", kStereo);
-		self.add_audio_output("Stereo Out", kStereo);
+		self.add_audio_input("Stereo In", KMAIN, kStereo);
+		if !self.codec_only {
+			self.add_audio_input("Noise In", KAUX, kStereo);
+			self.add_audio_input("Far End In", KAUX, kStereo);
+			self.add_audio_input("Reference In", KAUX, kStereo);
+			self.add_audio_input("Bandwidth In", KAUX, kStereo);
+		}
+		self.add_audio_output("Stereo Out", KMAIN, kStereo);
+		if !self.codec_only {
+			self.add_audio_output("Clean Out", KAUX, kStereo);
+		}
+
+		// Validators instantiate every class, which makes this a free test run
+		if cfg!(debug_assertions) {
+			let failures = selftest::run();
+			for failure in failures.iter() {
+				warn!("initialize() self-test failed: {}", failure);
+			}
+			info!("initialize() self-test found {} failures", failures.len());
+		}
 
 		kResultOk
 	}
@@ -380,6 +1022,14 @@ impl IAudioProcessor for OpusProcessor {
 		// arr is a single SpeakerArrangement
 		let arr = &mut *arr;
 
+		if self.is_hidden_bus(index) {
+			info!(
+				"get_bus_arrangement(index: {}) => kResultFalse, simple mode",
+				index
+			);
+			return kResultFalse;
+		}
+
 		let result = match dir {
 			0 => {
 				if index as usize >= self.audio_inputs.borrow().0.len() {
@@ -438,6 +1088,15 @@ impl IAudioProcessor for OpusProcessor {
 			}
 		};
 
+		// Offline IO mode was promised to the host, realtime processing contradicts it
+		if self.io_mode.borrow().0 == KOFFLINE && setup.process_mode == 0 {
+			warn!(
+				"setup_processing() => {}: realtime in offline io mode",
+				kInvalidArgument
+			);
+			return kInvalidArgument;
+		}
+
 		const OK: i32 = kResultTrue;
 		match self.can_process_sample_size(setup.symbolic_sample_size) {
 			OK => {}
@@ -450,11 +1109,43 @@ impl IAudioProcessor for OpusProcessor {
 			}
 		}
 
-		let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
-
-		vst_result!(dsp.setup(setup));
+		// Refuse the rate here with a reason, instead of failing somewhere in the resamplers
+		let dsp = vst_result!(self.opus_dsp.try_borrow());
+		if let Err(err) = dsp.check_host_rate(setup.sample_rate) {
+			warn!("setup_processing() => {}: {}", err.result(), err);
+			return err.result();
+		}
+		drop(dsp);
+
+		// Spares in flight were built for the old setup
+		vst_result!(self.builder.try_borrow_mut()).cancel();
+
+		if self.processing.borrow().0 {
+			// Build a spare here, off the audio thread, and let process() crossfade to it
+			let dsp = vst_result!(self.opus_dsp.try_borrow());
+			let mut next = vst_result!(self.copy_dsp(&dsp, setup));
+			drop(dsp);
+			self.send_warnings(&mut next.warnings);
+			if next.is_rendering() {
+				vst_result!(self.send_snapshot(&next, mode, setup));
+			}
+			let spare = Spare::new(next, setup.max_samples_per_block as usize);
+			self.spare.borrow_mut().0 = Some(spare);
+			info!("setup_processing() during playback, warming a spare DSP");
+		} else {
+			let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
+			dsp.set_offline(self.is_offline(setup.process_mode));
+			dsp.set_rendering(self.is_rendering(setup.process_mode));
+			vst_result!(dsp.setup(setup));
+			self.send_warnings(&mut dsp.warnings);
+			if dsp.is_rendering() {
+				vst_result!(self.send_snapshot(&dsp, mode, setup));
+			}
+			self.spare.borrow_mut().0 = None;
+		}
 
 		self.process_setup.borrow_mut().0 = *setup;
+		self.current_process_mode.borrow_mut().0 = setup.process_mode;
 
 		info!(
 			"setup_processing() {} f32 at {:.2} Hz with max {} per block ({:.2} ms)",
@@ -467,25 +1158,40 @@ impl IAudioProcessor for OpusProcessor {
 		kResultOk
 	}
 
-	///
+	/// Fade out on a stop and start over on a resume, the host may keep calling process()
 	unsafe fn set_processing(&self, state: TBool) -> tresult {
 		info!("set_processing({})", state);
 
-		if state == 0 {
-			let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
-			dsp.reset();
-		}
+		self.processing.borrow_mut().0 = state != 0;
+
+		let result = if state == 0 {
+			self.stop()
+		} else {
+			self.resume()
+		};
+		vst_result!(result);
 
 		kResultTrue
 	}
 
-	///
+	/// Run a block and report the read-only parameters back to the host
 	unsafe fn process(&self, data: *mut ProcessData) -> tresult {
 		// Convert pointer to reference for borrow checking
 		let data = &mut *data;
 
 		let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
 
+		// Edits the controller shared directly, ahead of the host's parameter changes
+		self.shared.apply(&mut dsp);
+
+		// Sent to the processor outside of process(), so they're applied here
+		let mut spare = vst_result!(self.spare.try_borrow_mut());
+		let next = spare.0.as_mut().map(|next| &mut *next.dsp);
+		let mut impulse = vst_result!(self.impulse.try_borrow_mut());
+		self.inbox.apply(&mut dsp, next, &mut impulse.0);
+		drop(impulse);
+		drop(spare);
+
 		// TODO: Are these MIDI events???
 		if let Some(input_events) = data.input_events.upgrade() {
 			let num_events = input_events.get_event_count();
@@ -495,22 +1201,313 @@ impl IAudioProcessor for OpusProcessor {
 		}
 
 		// Convert parameter queues to map type
-		let input_params = upgrade_param_changes(&data.input_param_changes);
-
-		// Apply parameters and return when there are no buses
-		if data.num_inputs == 0 && data.num_outputs == 0 {
-			vst_result!(dsp.apply_parameter_changes(&input_params, usize::MAX));
+		let mut input_params = upgrade_param_changes(&data.input_param_changes);
+		lock::ignore_locked(&dsp.locks, &mut input_params);
+
+		// A flush, with no buses or no samples, only carries parameter changes
+		if data.num_samples == 0 || (data.num_inputs == 0 && data.num_outputs == 0) {
+			vst_result!(dsp.flush_parameter_changes(&input_params));
+			if let Some(next) = vst_result!(self.spare.try_borrow_mut()).0.as_mut() {
+				vst_result!(next.dsp.flush_parameter_changes(&input_params));
+			}
+			// A flush while stopping means no more audio, blocks after it stay silent
+			if dsp.fade.is_stopping() {
+				dsp.fade.mute();
+			}
 			return kResultOk;
 		}
 
-		vst_result!(dsp.process(data));
+		// Rebuilding the coders in place clicks, so these settings arrive through a spare
+		vst_result!(self.crossfade_destructive(&mut dsp, &mut input_params));
+
+		let buses = vst_result!(main_buses(data));
+		let [out0, out1] = buses.output;
+		let aux = AuxInputs {
+			noise: buses.aux.noise.filter(|_| self.is_aux_active(NOISE_BUS)),
+			far_end: buses
+				.aux
+				.far_end
+				.filter(|_| self.is_aux_active(FAR_END_BUS)),
+			reference: buses
+				.aux
+				.reference
+				.filter(|_| self.is_aux_active(REFERENCE_BUS)),
+			bandwidth: buses
+				.aux
+				.bandwidth
+				.filter(|_| self.is_aux_active(BANDWIDTH_BUS)),
+		};
+
+		let start = Instant::now();
+		let num_samples = data.num_samples as usize;
+		dsp.advance_timeline(data.context.as_ref(), num_samples);
+		let output = [&mut *out0, &mut *out1];
+		let mut silent =
+			vst_result!(dsp.process(&input_params, buses.input, aux, output, buses.silent));
+
+		// A spare DSP built off the audio thread takes over gradually
+		let mut spare = vst_result!(self.spare.try_borrow_mut());
+		if let Some(next) = spare.0.as_mut() {
+			silent = false;
+			let output = [&mut *out0, &mut *out1];
+			next.dsp
+				.advance_timeline(data.context.as_ref(), num_samples);
+			if vst_result!(next.process(&input_params, buses.input, aux, output, buses.silent)) {
+				dsp.take_over(&mut next.dsp);
+				// Now holds the DSP that was playing
+				if let Some(old) = spare.0.take() {
+					vst_result!(self.builder.try_borrow()).retire(old);
+				}
+				info!("process() spare DSP took over");
+			}
+		}
+		drop(spare);
+
+		dsp.fade.apply([out0, out1]);
+		silent |= dsp.fade.is_muted();
+
+		if let Some(clean) = buses.clean.filter(|_| self.is_aux_output_active(CLEAN_BUS)) {
+			let latency = dsp.latency();
+			dsp.clean.process(buses.input, clean, latency);
+		}
+
+		buses.output_bus.silence_flags = if silent { 0b11 } else { 0 };
+
+		let complexity = vst_result!(Parameter::Complexity.get_from_dsp(&dsp));
+		vst_result!(dsp.check_load(start.elapsed(), num_samples));
+
+		// Tell the controller, which asks the host to query latency again
+		if let Some(frames) = dsp.take_latency_change() {
+			let value = Parameter::Latency.plain_param_to_normalized(frames as f64, Curve::Linear);
+			write_output_param(&data.output_param_changes, Parameter::Latency, value);
+		}
+
+		if let Some(xruns) = dsp.watchdog.take_xrun_change() {
+			let value = Parameter::Xruns.plain_param_to_normalized(f64::from(xruns), Curve::Linear);
+			write_output_param(&data.output_param_changes, Parameter::Xruns, value);
+		}
+
+		if let Some(load) = dsp.watchdog.take_load_change() {
+			let value = Parameter::CpuLoad.plain_param_to_normalized(load, Curve::Linear);
+			write_output_param(&data.output_param_changes, Parameter::CpuLoad, value);
+		}
+
+		for cause in LossCause::ALL.iter() {
+			if let Some(count) = dsp.losses.take_change(*cause) {
+				let param = Parameter::loss_counter(*cause);
+				let value = param.plain_param_to_normalized(f64::from(count), Curve::Linear);
+				write_output_param(&data.output_param_changes, param, value);
+			}
+		}
+
+		if let Some(warning) = dsp.levels.take_warning_change() {
+			let param = Parameter::LevelWarning;
+			let value = param.plain_param_to_normalized(f64::from(warning), Curve::Linear);
+			write_output_param(&data.output_param_changes, param, value);
+		}
+
+		if let Some(active) = dsp.warnings.take_flag_change() {
+			let param = Parameter::Warning;
+			let value = param.plain_param_to_normalized(f64::from(active as u8), Curve::Linear);
+			write_output_param(&data.output_param_changes, param, value);
+		}
+
+		if let Some((result, difference)) = dsp.null.take_change() {
+			let changes = [
+				(Parameter::NullResult, f64::from(result)),
+				(Parameter::NullDifference, difference),
+			];
+			for (param, plain) in changes.iter() {
+				let value = param.plain_param_to_normalized(*plain, Curve::Linear);
+				write_output_param(&data.output_param_changes, *param, value);
+			}
+		}
+
+		if let Some(in_dtx) = dsp.effective.take_dtx_change() {
+			let param = Parameter::InDtx;
+			let value = param.plain_param_to_normalized(f64::from(in_dtx as u8), Curve::Linear);
+			write_output_param(&data.output_param_changes, param, value);
+		}
+
+		if let Some(talker) = dsp.duplex.take_talker_change() {
+			let param = Parameter::Talker;
+			let value = param.plain_param_to_normalized(f64::from(talker), Curve::Linear);
+			write_output_param(&data.output_param_changes, param, value);
+		}
+
+		if let Some(config) = dsp.effective.take_change() {
+			info!("process() encoder is now {}", config);
+			let changes = [
+				(Parameter::EffectiveMode, config.mode),
+				(Parameter::EffectiveBandwidth, config.bandwidth),
+				(Parameter::EffectiveFrames, config.frames),
+			];
+			for (param, plain) in changes.iter() {
+				let value = param.plain_param_to_normalized(f64::from(*plain), Curve::Linear);
+				write_output_param(&data.output_param_changes, *param, value);
+			}
+		}
+
+		// The governor lowered complexity
+		let governed = vst_result!(Parameter::Complexity.get_from_dsp(&dsp));
+		if governed != complexity {
+			write_output_param(&data.output_param_changes, Parameter::Complexity, governed);
+			let edits = vst_result!(Parameter::InternalEdits.get_from_dsp(&dsp));
+			write_output_param(&data.output_param_changes, Parameter::InternalEdits, edits);
+		}
+
+		// The controller pulls the events through notify(), off the audio thread
+		if let Some(recorded) = dsp.events.take_change() {
+			let param = Parameter::RecordedEvents;
+			let value = param.plain_param_to_normalized(f64::from(recorded), Curve::Linear);
+			write_output_param(&data.output_param_changes, param, value);
+		}
 
 		kResultOk
 	}
 
-	///
+	/// No tail, the output stops with the input
 	unsafe fn get_tail_samples(&self) -> u32 {
 		info!("get_tail_samples()");
 		0
 	}
 }
+
+impl IConnectionPoint for OpusProcessor {
+	unsafe fn connect(&self, other: *mut c_void) -> tresult {
+		info!("connect()");
+
+		if other.is_null() {
+			return kInvalidArgument;
+		}
+		self.peer.borrow_mut().0 = other;
+
+		if cfg!(feature = "shared-params") {
+			let context = self.context.borrow().0;
+			let result = shared::send_handshake(context, other, &self.shared);
+			info!("connect() shared params handshake => {}", result);
+		}
+
+		// Anything raised before the controller was there
+		let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
+		self.send_warnings(&mut dsp.warnings);
+
+		kResultOk
+	}
+
+	unsafe fn disconnect(&self, other: *mut c_void) -> tresult {
+		info!("disconnect()");
+
+		if self.peer.borrow().0 != other {
+			return kResultFalse;
+		}
+		self.peer.borrow_mut().0 = null_mut();
+
+		kResultOk
+	}
+
+	unsafe fn notify(&self, message: *mut c_void) -> tresult {
+		if bursts::answer(message, &self.bursts) {
+			return kResultOk;
+		}
+
+		if events::answer_pull(message, &self.events) {
+			return kResultOk;
+		}
+
+		if let Some(name) = preset::receive(message) {
+			info!("notify() preset renamed {:?}", name);
+			vst_result!(self.preset_name.try_borrow_mut()).0 = name;
+			return kResultOk;
+		}
+
+		// The value moved to the new curve arrives separately, through the host
+		if let Some((param, curve)) = curve::receive(message) {
+			info!("notify() {:?} curve {:?}", param, curve);
+			self.inbox.set_curve(param, curve);
+			vst_result!(self.apply_inbox_when_stopped());
+			return kResultOk;
+		}
+
+		// Only the controller saves the locks, it sends them again after loading
+		if let Some((param, locked)) = lock::receive(message) {
+			info!("notify() {:?} locked {}", param, locked);
+			self.inbox.set_lock(param, locked);
+			vst_result!(self.apply_inbox_when_stopped());
+			return kResultOk;
+		}
+
+		if let Some(path) = null::receive(message) {
+			info!("notify() null reference {}", path);
+			if !self.processing.borrow().0 {
+				let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
+				let result = match dsp.load_null_reference(&path) {
+					Ok(()) => kResultOk,
+					Err(err) => err.result(),
+				};
+				self.send_warnings(&mut dsp.warnings);
+				return result;
+			}
+
+			// Read here, process() only swaps it in
+			let mut null = Box::new(NullCheck::default());
+			let rate = self.process_setup.borrow().0.sample_rate;
+			let (result, line) = match null.load(&path, rate) {
+				Ok(()) => (kResultOk, None),
+				Err(err) => (err.result(), Some(err.to_string())),
+			};
+			if !self.inbox.deliver(Delivery::Null(null, line.clone())) {
+				warn!(
+					"notify() null reference {} dropped, too many files in flight",
+					path
+				);
+				return kResultFalse;
+			}
+			self.show_warning(warnings::NULL, line);
+			return result;
+		}
+
+		match impulse_path(message) {
+			Some(path) if !self.processing.borrow().0 => {
+				info!("notify() impulse response {}", path);
+				let mut dsp = vst_result!(self.opus_dsp.try_borrow_mut());
+				let result = match dsp.coloration.load_impulse(&path) {
+					Ok(()) => {
+						dsp.warnings.resolve(warnings::IMPULSE);
+						kResultOk
+					}
+					Err(err) => {
+						let line = format!("impulse response not loaded: {}", err);
+						dsp.warnings.raise(warnings::IMPULSE, line);
+						err.result()
+					}
+				};
+				self.send_warnings(&mut dsp.warnings);
+				result
+			}
+			// Read here, a spare built with it takes over
+			Some(path) => {
+				info!("notify() impulse response {} during playback", path);
+				let (file, result) = ImpulseFile::open(&path);
+				let (result, line) = match result {
+					Ok(()) => (kResultOk, None),
+					Err(err) => {
+						let line = format!("impulse response not loaded: {}", err);
+						(err.result(), Some(line))
+					}
+				};
+				let delivery = Delivery::Impulse(Some(Arc::new(file)), line.clone());
+				if !self.inbox.deliver(delivery) {
+					warn!(
+						"notify() impulse response {} dropped, too many files in flight",
+						path
+					);
+					return kResultFalse;
+				}
+				self.show_warning(warnings::IMPULSE, line);
+				result
+			}
+			None => kResultFalse,
+		}
+	}
+}
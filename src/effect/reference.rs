@@ -0,0 +1,58 @@
+/// What plays on the output, in parameter order
+pub const SOURCES: [&str; 2] = ["Coded", "Reference"];
+
+const REFERENCE: usize = 1;
+
+/// Longest delay the line holds, well past the plugin's latency
+const MAX_DELAY_SECS: f64 = 0.1;
+
+/// Another track on an aux bus, delayed by the plugin's latency so switching to it
+/// compares against the coded signal at the same point in time
+#[derive(Default)]
+pub struct Reference {
+	/// Index into SOURCES
+	pub source: usize,
+	line: Vec<[f32; 2]>,
+	write: usize,
+}
+
+impl Reference {
+	/// Room for the longest delay at the host rate, which allocates
+	pub fn set_rate(&mut self, rate: f64) {
+		self.line = vec![[0.0; 2]; (rate * MAX_DELAY_SECS) as usize + 1];
+		self.write = 0;
+	}
+
+	/// The reference track plays on the output
+	pub fn is_selected(&self) -> bool {
+		self.source == REFERENCE
+	}
+
+	/// Push one frame and return the one from `delay` frames ago
+	pub fn delay(&mut self, frame: [f32; 2], delay: usize) -> [f32; 2] {
+		let len = self.line.len();
+		if len == 0 {
+			return frame;
+		}
+		let delay = delay.min(len - 1);
+		self.line[self.write] = frame;
+		let delayed = self.line[(self.write + len - delay) % len];
+		self.write = (self.write + 1) % len;
+		delayed
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn delays_by_the_latency() {
+		let mut reference = Reference::default();
+		reference.set_rate(100.0);
+		let out: Vec<f32> = (0..6)
+			.map(|i| reference.delay([i as f32; 2], 2)[0])
+			.collect();
+		assert_eq!(out, [0.0, 0.0, 0.0, 1.0, 2.0, 3.0]);
+	}
+}
@@ -0,0 +1,94 @@
+use dasp::frame::Stereo;
+use dasp::interpolate::linear::Linear;
+use dasp::interpolate::sinc::Sinc;
+use dasp::ring_buffer;
+use dasp::signal::interpolate::Converter;
+use dasp::Frame;
+use dasp::Signal;
+use std::collections::VecDeque;
+
+/// Taps on either side of the sinc interpolator
+pub const SINC_DEPTH: usize = 16;
+
+type SincFrames = [Stereo<f32>; SINC_DEPTH * 2];
+
+pub struct BufferSignal<F: Frame>(VecDeque<F>);
+
+impl<F: Frame> BufferSignal<F> {
+	pub fn push(&mut self, elem: F) {
+		self.0.push_back(elem);
+	}
+
+	pub fn push_slice(&mut self, slice: &[F]) {
+		self.0.extend(slice);
+	}
+}
+
+impl<F: Frame> Signal for BufferSignal<F> {
+	type Frame = F;
+
+	fn next(&mut self) -> F {
+		self.0.pop_front().unwrap_or(F::EQUILIBRIUM)
+	}
+
+	fn is_exhausted(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+/// Sample rate converter fed by pushing frames. Built when the rate changes, never
+/// moved on the audio thread, so the sinc variant's size doesn't matter
+#[allow(clippy::large_enum_variant)]
+pub enum Resampler {
+	Linear(Converter<BufferSignal<Stereo<f32>>, Linear<Stereo<f32>>>),
+	Sinc(Converter<BufferSignal<Stereo<f32>>, Sinc<SincFrames>>),
+}
+
+impl Resampler {
+	/// Converter between two rates, windowed sinc when `high_quality`, linear otherwise
+	pub fn new(source_hz: f64, target_hz: f64, high_quality: bool) -> Self {
+		let buffer = BufferSignal(VecDeque::new());
+		if high_quality {
+			let frames = ring_buffer::Fixed::from([Stereo::EQUILIBRIUM; SINC_DEPTH * 2]);
+			let interpolator = Sinc::new(frames);
+			Self::Sinc(buffer.from_hz_to_hz(interpolator, source_hz, target_hz))
+		} else {
+			let interpolator = Linear::new(Stereo::EQUILIBRIUM, Stereo::EQUILIBRIUM);
+			Self::Linear(buffer.from_hz_to_hz(interpolator, source_hz, target_hz))
+		}
+	}
+
+	/// Frames of delay added by the interpolator, at the source rate
+	pub fn latency(&self) -> usize {
+		match self {
+			Self::Linear(_) => 0,
+			Self::Sinc(_) => SINC_DEPTH,
+		}
+	}
+
+	/// Buffer the next frames are pushed into
+	pub fn source_mut(&mut self) -> &mut BufferSignal<Stereo<f32>> {
+		match self {
+			Self::Linear(converter) => converter.source_mut(),
+			Self::Sinc(converter) => converter.source_mut(),
+		}
+	}
+}
+
+impl Signal for Resampler {
+	type Frame = Stereo<f32>;
+
+	fn next(&mut self) -> Stereo<f32> {
+		match self {
+			Self::Linear(converter) => converter.next(),
+			Self::Sinc(converter) => converter.next(),
+		}
+	}
+
+	fn is_exhausted(&self) -> bool {
+		match self {
+			Self::Linear(converter) => converter.is_exhausted(),
+			Self::Sinc(converter) => converter.is_exhausted(),
+		}
+	}
+}
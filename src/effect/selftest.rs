@@ -0,0 +1,107 @@
+use super::curve::Curve;
+use super::dsp::OpusDSP;
+use super::params::Parameter;
+use super::state;
+use crate::sys::ProcessSetup;
+use enum_map::EnumMap;
+use num_enum::TryFromPrimitive;
+
+/// Host rates the latency is checked at
+const RATES: [f64; 4] = [44100.0, 48000.0, 88200.0, 96000.0];
+
+/// Consistency checks for debug builds, so a validator run catches regressions
+/// without a test host. A line per failure
+pub fn run() -> Vec<String> {
+	let mut failures = Vec::new();
+	check_mapping(&mut failures);
+	check_state(&mut failures);
+	check_latency(&mut failures);
+	failures
+}
+
+fn params() -> impl Iterator<Item = Parameter> {
+	(0..Parameter::VARIANT_COUNT as u32).filter_map(|id| Parameter::try_from_primitive(id).ok())
+}
+
+/// Normalized to plain and back lands where it started, for the ends and the default
+fn check_mapping(failures: &mut Vec<String>) {
+	for param in params() {
+		for &normalized in [0.0, param.default_normalized(), 1.0].iter() {
+			let plain = param.normalized_param_to_plain(normalized, Curve::Linear);
+			let back = param.plain_param_to_normalized(plain, Curve::Linear);
+			if (back - normalized).abs() > 1e-6 {
+				failures.push(format!(
+					"mapping: {} {} → {} → {}",
+					param.key(),
+					normalized,
+					plain,
+					back
+				));
+			}
+		}
+	}
+}
+
+/// Saved values survive encoding and decoding
+fn check_state(failures: &mut Vec<String>) {
+	let dsp = OpusDSP::default();
+	let mut values = EnumMap::<Parameter, f64>::default();
+	for (param, value) in values.iter_mut() {
+		match param.get_from_dsp(&dsp) {
+			Ok(normalized) => *value = normalized,
+			Err(err) => failures.push(format!("state: can't read {}: {}", param.key(), err)),
+		}
+	}
+	let chunk = match state::decode(&state::encode(&values, &[])) {
+		Ok(chunk) => chunk,
+		Err(err) => return failures.push(format!("state: can't decode: {}", err)),
+	};
+	for (param, saved) in values.iter().filter(|(param, _)| param.is_saved()) {
+		match chunk.values[param] {
+			Some(read) if (read - saved).abs() < 1e-9 => {}
+			read => failures.push(format!(
+				"state: {} saved {} read {:?}",
+				param.key(),
+				saved,
+				read
+			)),
+		}
+	}
+}
+
+/// Latency is positive, under a second, and already reported after setup
+fn check_latency(failures: &mut Vec<String>) {
+	for &sample_rate in RATES.iter() {
+		let mut dsp = OpusDSP::default();
+		let setup = ProcessSetup {
+			process_mode: 0,
+			symbolic_sample_size: 0,
+			max_samples_per_block: 512,
+			sample_rate,
+		};
+		if let Err(err) = dsp.setup(&setup) {
+			failures.push(format!("latency: setup at {} Hz: {}", sample_rate, err));
+			continue;
+		}
+		let latency = dsp.latency();
+		if latency == 0 || latency as f64 >= sample_rate {
+			failures.push(format!("latency: {} frames at {} Hz", latency, sample_rate));
+		}
+		if let Some(changed) = dsp.take_latency_change() {
+			failures.push(format!(
+				"latency: {} frames at {} Hz not reported after setup",
+				changed, sample_rate
+			));
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn passes() {
+		assert_eq!(run(), Vec::<String>::new());
+	}
+}
@@ -0,0 +1,168 @@
+use super::dsp::OpusDSP;
+use super::params::Parameter;
+use enum_map::EnumMap;
+use log::*;
+use std::mem::size_of;
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use vst3_com::{ComInterface, ComPtr};
+use vst3_sys::base::{kResultFalse, kResultOk, tresult};
+use vst3_sys::vst::{IAttributeList, IConnectionPoint, IHostApplication, IMessage};
+
+/// Parameter values written by the controller and read by the processor,
+/// without waiting for the host to deliver them through process()
+#[derive(Default)]
+pub struct SharedParams {
+	values: EnumMap<Parameter, AtomicU64>,
+	changed: EnumMap<Parameter, AtomicBool>,
+}
+
+impl SharedParams {
+	/// Publish a controller edit, read-only parameters are left alone
+	pub fn set(&self, param: Parameter, value: f64) {
+		if param.is_read_only() {
+			return;
+		}
+		self.values[param].store(value.to_bits(), Ordering::Release);
+		self.changed[param].store(true, Ordering::Release);
+	}
+
+	/// Apply values changed since the last call, except destructive ones, which wait
+	/// for `take_destructive`. A value the DSP refuses is logged and skipped
+	pub fn apply(&self, dsp: &mut OpusDSP) {
+		for (param, changed) in self.changed.iter() {
+			if param.is_destructive() {
+				continue;
+			}
+			if changed.swap(false, Ordering::AcqRel) {
+				let value = f64::from_bits(self.values[param].load(Ordering::Acquire));
+				if let Err(err) = param.set_to_dsp(dsp, value) {
+					warn!("shared params: {:?} to {}: {}", param, value, err);
+				}
+			}
+		}
+	}
+
+	/// Destructive values changed since the last call, for a spare to be built with
+	pub fn take_destructive(&self) -> EnumMap<Parameter, Option<f64>> {
+		let mut values = EnumMap::<Parameter, Option<f64>>::default();
+		for (param, value) in values.iter_mut() {
+			if param.is_destructive() && self.changed[param].swap(false, Ordering::AcqRel) {
+				*value = Some(f64::from_bits(self.values[param].load(Ordering::Acquire)));
+			}
+		}
+		values
+	}
+}
+
+const MESSAGE_ID: &[u8] = b"OpusSharedParams\0";
+const ATTRIBUTE_ID: &[u8] = b"store\0";
+
+/// Both sides of the handshake must live in the same process for the pointer to mean anything
+#[derive(Copy, Clone)]
+#[repr(C)]
+struct Handshake {
+	process_id: u32,
+	store: *const SharedParams,
+}
+
+/// Offer the store to the connected controller
+pub unsafe fn send_handshake(
+	context: *mut c_void,
+	peer: *mut c_void,
+	store: &Arc<SharedParams>,
+) -> tresult {
+	if context.is_null() || peer.is_null() {
+		return kResultFalse;
+	}
+
+	let host: ComPtr<dyn IHostApplication> = ComPtr::new(context as *mut *mut _);
+	let mut cid = <dyn IMessage as ComInterface>::IID;
+	let mut iid = <dyn IMessage as ComInterface>::IID;
+	let mut message = null_mut();
+	let result = host.create_instance(&mut cid, &mut iid, &mut message);
+	if result != kResultOk || message.is_null() {
+		warn!("shared params: host can't create messages");
+		return result;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	message.set_message_id(MESSAGE_ID.as_ptr() as *const _);
+
+	let handshake = Handshake {
+		process_id: std::process::id(),
+		store: Arc::as_ptr(store),
+	};
+	let attributes = message.get_attributes();
+	if !attributes.is_null() {
+		let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+		let data = &handshake as *const Handshake as *const c_void;
+		attributes.set_binary(
+			ATTRIBUTE_ID.as_ptr() as *const _,
+			data,
+			size_of::<Handshake>() as u32,
+		);
+	}
+
+	let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+	let result = peer.notify(message.as_raw() as *mut c_void);
+	message.release();
+	result
+}
+
+/// The store from a handshake message, None for any other message or another process
+pub unsafe fn receive_handshake(message: *mut c_void) -> Option<Arc<SharedParams>> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || std::ffi::CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut data = std::ptr::null();
+	let mut size = 0;
+	let result = attributes.get_binary(ATTRIBUTE_ID.as_ptr() as *const _, &mut data, &mut size);
+	if result != kResultOk || data.is_null() || size as usize != size_of::<Handshake>() {
+		return None;
+	}
+
+	let handshake = std::ptr::read_unaligned(data as *const Handshake);
+	if handshake.process_id != std::process::id() || handshake.store.is_null() {
+		info!("shared params: processor is in another process");
+		return None;
+	}
+
+	// The processor keeps its reference alive for the duration of notify()
+	Arc::increment_strong_count(handshake.store);
+	Some(Arc::from_raw(handshake.store))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn destructive_values_wait_for_a_spare() {
+		let shared = SharedParams::default();
+		let mut dsp = OpusDSP::default();
+		let rate = Parameter::InternalRate.get_from_dsp(&dsp).unwrap();
+		shared.set(Parameter::InternalRate, 0.2);
+
+		shared.apply(&mut dsp);
+		assert_eq!(Parameter::InternalRate.get_from_dsp(&dsp).unwrap(), rate);
+
+		let taken = shared.take_destructive();
+		assert_eq!(taken[Parameter::InternalRate], Some(0.2));
+		assert_eq!(shared.take_destructive()[Parameter::InternalRate], None);
+	}
+}
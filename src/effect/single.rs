@@ -0,0 +1,242 @@
+use super::OpusController;
+use super::OpusProcessor;
+use super::VstClassInfo;
+use crate::vst_result;
+use hex_literal::hex;
+use log::*;
+use std::os::raw::c_void;
+use vst3_com::sys::GUID;
+use vst3_com::IID;
+use vst3_sys::base::{
+	kResultFalse, kResultOk, tresult, ClassCardinality, FIDString, IPluginBase, TBool,
+};
+use vst3_sys::vst::{
+	BusDirection, BusInfo, IAudioProcessor, IComponent, IEditController, IoMode, MediaType,
+	ParameterInfo, ProcessData, ProcessSetup, RoutingInfo, SpeakerArrangement, TChar,
+};
+use vst3_sys::VST3;
+
+/// Processor and controller in one object, for hosts that prefer a single component.
+/// The controller's parameters are kept in step with the DSP on state changes.
+#[VST3(implements(IComponent, IAudioProcessor, IEditController))]
+pub struct OpusSingle {
+	processor: Box<OpusProcessor>,
+	controller: Box<OpusController>,
+}
+
+impl OpusSingle {
+	pub const CID: IID = GUID {
+		data: hex!("5d1c27a4e0b34c1f9a7e3b0f6c2d8e41"),
+	};
+
+	pub const INFO: VstClassInfo = VstClassInfo {
+		cid: Self::CID,
+		name: "Opus Parvulum (Single)",
+		category: "Audio Module Class",
+		subcategories: "Fx",
+		class_flags: 2, // simple io supported
+		cardinality: ClassCardinality::kManyInstances as i32,
+	};
+
+	pub fn new() -> Box<Self> {
+		let processor = OpusProcessor::new();
+		let controller = OpusController::new();
+		controller.set_shared_params(Some(processor.shared_params()));
+		Self::allocate(processor, controller)
+	}
+
+	pub fn create_instance() -> *mut c_void {
+		Box::into_raw(Self::new()) as *mut c_void
+	}
+
+	/// Copy the DSP's parameter values into the controller
+	unsafe fn sync_controller(&self) -> tresult {
+		let params = vst_result!(self.processor.parameter_values());
+		for (param, value) in params.iter() {
+			let id: u32 = param.into();
+			self.controller.set_param_normalized(id, *value);
+		}
+		kResultOk
+	}
+}
+
+impl IPluginBase for OpusSingle {
+	unsafe fn initialize(&self, context: *mut c_void) -> tresult {
+		info!("initialize() single component");
+		match self.processor.initialize(context) {
+			kResultOk => self.controller.initialize(context),
+			result => result,
+		}
+	}
+
+	unsafe fn terminate(&self) -> tresult {
+		self.controller.terminate();
+		self.processor.terminate()
+	}
+}
+
+impl IComponent for OpusSingle {
+	unsafe fn get_controller_class_id(&self, _tuid: *mut IID) -> tresult {
+		// The controller is this object
+		kResultFalse
+	}
+
+	unsafe fn set_io_mode(&self, mode: IoMode) -> tresult {
+		self.processor.set_io_mode(mode)
+	}
+
+	unsafe fn get_bus_count(&self, media_type: MediaType, dir: BusDirection) -> i32 {
+		self.processor.get_bus_count(media_type, dir)
+	}
+
+	unsafe fn get_bus_info(
+		&self,
+		media_type: MediaType,
+		direction: BusDirection,
+		index: i32,
+		info: *mut BusInfo,
+	) -> tresult {
+		self.processor
+			.get_bus_info(media_type, direction, index, info)
+	}
+
+	unsafe fn get_routing_info(
+		&self,
+		in_info: *mut RoutingInfo,
+		out_info: *mut RoutingInfo,
+	) -> tresult {
+		self.processor.get_routing_info(in_info, out_info)
+	}
+
+	unsafe fn activate_bus(
+		&self,
+		media_type: MediaType,
+		dir: BusDirection,
+		index: i32,
+		state: TBool,
+	) -> tresult {
+		self.processor.activate_bus(media_type, dir, index, state)
+	}
+
+	unsafe fn set_active(&self, state: TBool) -> tresult {
+		self.processor.set_active(state)
+	}
+
+	unsafe fn set_state(&self, state: *mut c_void) -> tresult {
+		match IComponent::set_state(&*self.processor, state) {
+			kResultOk => self.sync_controller(),
+			result => result,
+		}
+	}
+
+	unsafe fn get_state(&self, state: *mut c_void) -> tresult {
+		IComponent::get_state(&*self.processor, state)
+	}
+}
+
+impl IAudioProcessor for OpusSingle {
+	unsafe fn set_bus_arrangements(
+		&self,
+		inputs: *mut SpeakerArrangement,
+		num_ins: i32,
+		outputs: *mut SpeakerArrangement,
+		num_outs: i32,
+	) -> tresult {
+		self.processor
+			.set_bus_arrangements(inputs, num_ins, outputs, num_outs)
+	}
+
+	unsafe fn get_bus_arrangement(
+		&self,
+		dir: BusDirection,
+		index: i32,
+		arr: *mut SpeakerArrangement,
+	) -> tresult {
+		self.processor.get_bus_arrangement(dir, index, arr)
+	}
+
+	unsafe fn can_process_sample_size(&self, symbolic_sample_size: i32) -> tresult {
+		self.processor.can_process_sample_size(symbolic_sample_size)
+	}
+
+	unsafe fn get_latency_samples(&self) -> u32 {
+		self.processor.get_latency_samples()
+	}
+
+	unsafe fn setup_processing(&self, setup: *const ProcessSetup) -> tresult {
+		self.processor.setup_processing(setup)
+	}
+
+	unsafe fn set_processing(&self, state: TBool) -> tresult {
+		self.processor.set_processing(state)
+	}
+
+	unsafe fn process(&self, data: *mut ProcessData) -> tresult {
+		self.processor.process(data)
+	}
+
+	unsafe fn get_tail_samples(&self) -> u32 {
+		self.processor.get_tail_samples()
+	}
+}
+
+impl IEditController for OpusSingle {
+	unsafe fn set_component_state(&self, _state: *mut c_void) -> tresult {
+		// Already in step, set_state updates the controller
+		kResultOk
+	}
+
+	unsafe fn set_state(&self, state: *mut c_void) -> tresult {
+		IEditController::set_state(&*self.controller, state)
+	}
+
+	unsafe fn get_state(&self, state: *mut c_void) -> tresult {
+		IEditController::get_state(&*self.controller, state)
+	}
+
+	unsafe fn get_parameter_count(&self) -> i32 {
+		self.controller.get_parameter_count()
+	}
+
+	unsafe fn get_parameter_info(&self, id: i32, info: *mut ParameterInfo) -> tresult {
+		self.controller.get_parameter_info(id, info)
+	}
+
+	unsafe fn get_param_string_by_value(&self, id: u32, value: f64, string: *mut TChar) -> tresult {
+		self.controller.get_param_string_by_value(id, value, string)
+	}
+
+	unsafe fn get_param_value_by_string(
+		&self,
+		id: u32,
+		ptr: *const TChar,
+		value: *mut f64,
+	) -> tresult {
+		self.controller.get_param_value_by_string(id, ptr, value)
+	}
+
+	unsafe fn normalized_param_to_plain(&self, id: u32, value_normalized: f64) -> f64 {
+		self.controller
+			.normalized_param_to_plain(id, value_normalized)
+	}
+
+	unsafe fn plain_param_to_normalized(&self, id: u32, plain_value: f64) -> f64 {
+		self.controller.plain_param_to_normalized(id, plain_value)
+	}
+
+	unsafe fn get_param_normalized(&self, id: u32) -> f64 {
+		self.controller.get_param_normalized(id)
+	}
+
+	unsafe fn set_param_normalized(&self, id: u32, value: f64) -> tresult {
+		self.controller.set_param_normalized(id, value)
+	}
+
+	unsafe fn set_component_handler(&self, handler: *mut c_void) -> tresult {
+		self.controller.set_component_handler(handler)
+	}
+
+	unsafe fn create_view(&self, name: FIDString) -> *mut c_void {
+		self.controller.create_view(name)
+	}
+}
@@ -0,0 +1,75 @@
+use log::*;
+use std::ffi::CStr;
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+use std::slice;
+use vst3_com::{ComInterface, ComPtr};
+use vst3_sys::base::{kResultFalse, kResultOk, tresult};
+use vst3_sys::vst::{IAttributeList, IConnectionPoint, IHostApplication, IMessage};
+
+const MESSAGE_ID: &[u8] = b"OpusSettings\0";
+const ATTRIBUTE_ID: &[u8] = b"text\0";
+
+/// Publish the settings a render starts with, so tools watching plugin messages
+/// can record how a bounce was degraded
+pub unsafe fn send(context: *mut c_void, peer: *mut c_void, text: &str) -> tresult {
+	if context.is_null() || peer.is_null() {
+		return kResultFalse;
+	}
+
+	let host: ComPtr<dyn IHostApplication> = ComPtr::new(context as *mut *mut _);
+	let mut cid = <dyn IMessage as ComInterface>::IID;
+	let mut iid = <dyn IMessage as ComInterface>::IID;
+	let mut message = null_mut();
+	let result = host.create_instance(&mut cid, &mut iid, &mut message);
+	if result != kResultOk || message.is_null() {
+		warn!("snapshot: host can't create messages");
+		return result;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	message.set_message_id(MESSAGE_ID.as_ptr() as *const _);
+
+	let attributes = message.get_attributes();
+	if !attributes.is_null() {
+		let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+		attributes.set_binary(
+			ATTRIBUTE_ID.as_ptr() as *const _,
+			text.as_ptr() as *const c_void,
+			text.len() as u32,
+		);
+	}
+
+	let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+	let result = peer.notify(message.as_raw() as *mut c_void);
+	message.release();
+	result
+}
+
+/// The text from a settings message, a UTF-8 binary attribute, None for any other message
+pub unsafe fn receive(message: *mut c_void) -> Option<String> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut data = std::ptr::null();
+	let mut size = 0;
+	let id = ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_binary(id, &mut data, &mut size) != kResultOk || data.is_null() {
+		return None;
+	}
+
+	let bytes = slice::from_raw_parts(data as *const u8, size as usize);
+	Some(String::from_utf8_lossy(bytes).into_owned())
+}
@@ -0,0 +1,306 @@
+use super::coloration::ImpulseFile;
+use super::curve::Curves;
+use super::dsp::AuxInputs;
+use super::dsp::OpusDSP;
+use super::dsp::ParamQueueMap;
+use super::link::Opener;
+use super::lock::Locks;
+use super::params::Parameter;
+use crate::error::Result;
+use enum_map::EnumMap;
+use log::*;
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use vst3_sys::vst::ProcessSetup;
+
+/// Frames to crossfade from the playing DSP to the spare
+const CROSSFADE_LEN: usize = 1024;
+
+/// Builds in flight between process() and the builder
+const JOBS: usize = 4;
+
+/// Spares waiting to be dropped. Only the spares built for requests, the one built
+/// by setup_processing() and the playing DSP are ever retired, and the builder drops
+/// the retired ones before it builds another, so this many never fill the queue
+const RETIRED: usize = 2 * JOBS + 4;
+
+/// Longest a retired spare waits for the builder to drop it
+const RETIRE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A DSP built for new settings off the audio thread, run alongside the playing one
+/// until its output is valid, then crossfaded in
+pub struct Spare {
+	pub dsp: Box<OpusDSP>,
+	scratch: [Vec<f32>; 2],
+	/// Frames left before the spare's output is valid
+	warmup: usize,
+	/// Frames into the crossfade
+	position: usize,
+}
+
+impl Spare {
+	/// Spare that has to run through its latency before it can take over
+	pub fn new(dsp: Box<OpusDSP>, max_block: usize) -> Self {
+		let warmup = dsp.latency();
+		Self {
+			dsp,
+			scratch: [vec![0.0; max_block], vec![0.0; max_block]],
+			warmup,
+			position: 0,
+		}
+	}
+
+	/// Process the block and mix into the playing DSP's output, true once the spare took over
+	pub fn process(
+		&mut self,
+		params: &ParamQueueMap,
+		input: [&[f32]; 2],
+		aux: AuxInputs,
+		output: [&mut [f32]; 2],
+		is_silent: bool,
+	) -> Result<bool> {
+		let num_samples = input[0].len();
+		let [out0, out1] = output;
+
+		// A block over the host's maximum is split instead of growing the scratch here,
+		// its parameter changes arrive with the last part
+		let no_changes = ParamQueueMap::default();
+		let chunk_len = self.scratch[0].len().max(1);
+		let mut start = 0;
+		while start < num_samples {
+			let end = (start + chunk_len).min(num_samples);
+			let params = if end == num_samples {
+				params
+			} else {
+				&no_changes
+			};
+			let input = [&input[0][start..end], &input[1][start..end]];
+			let [s0, s1] = &mut self.scratch;
+			let (s0, s1) = (&mut s0[..end - start], &mut s1[..end - start]);
+			let aux = aux.slice(start..end);
+			self.dsp
+				.process(params, input, aux, [&mut *s0, &mut *s1], is_silent)?;
+
+			for (i, j) in (start..end).enumerate() {
+				if self.warmup > 0 {
+					self.warmup -= 1;
+					continue;
+				}
+				let t = (self.position as f32 / CROSSFADE_LEN as f32).min(1.0);
+				out0[j] += (s0[i] - out0[j]) * t;
+				out1[j] += (s1[i] - out1[j]) * t;
+				self.position += 1;
+			}
+			start = end;
+		}
+
+		Ok(self.position >= CROSSFADE_LEN)
+	}
+}
+
+/// Everything a spare is built from, copied out of the playing DSP so the build
+/// needs nothing from it. Cloning doesn't allocate
+#[derive(Clone)]
+pub struct Template {
+	/// Normalized, changes are written over the playing values
+	pub values: EnumMap<Parameter, f64>,
+	curves: Curves,
+	locks: Locks,
+	/// Shared with the playing DSP, the spare prepares its own convolver
+	pub impulse: Option<Arc<ImpulseFile>>,
+	setup: ProcessSetup,
+	offline: bool,
+	rendering: bool,
+	codec_only: bool,
+}
+
+impl Template {
+	/// The settings of `from`, for `setup`
+	pub fn of(
+		from: &OpusDSP,
+		setup: ProcessSetup,
+		offline: bool,
+		rendering: bool,
+		codec_only: bool,
+	) -> Result<Self> {
+		let mut values = EnumMap::<Parameter, f64>::default();
+		for (param, value) in values.iter_mut() {
+			*value = param.get_from_dsp(from)?;
+		}
+		Ok(Self {
+			values,
+			curves: from.curves,
+			locks: from.locks,
+			impulse: from.coloration.impulse(),
+			setup,
+			offline,
+			rendering,
+			codec_only,
+		})
+	}
+
+	/// A DSP with these settings, set up, which allocates
+	pub fn build(&self, opener: Option<&Opener>) -> Result<Box<OpusDSP>> {
+		let mut next = Box::new(OpusDSP::default());
+		if self.codec_only {
+			next.set_codec_only();
+		}
+		if let Some(opener) = opener {
+			next.link.attach(opener);
+		}
+		next.set_offline(self.offline);
+		next.set_rendering(self.rendering);
+		next.curves = self.curves;
+		next.locks = self.locks;
+		next.coloration.set_impulse(self.impulse.clone());
+		for (param, value) in self.values.iter() {
+			param.set_to_dsp(&mut next, *value)?;
+		}
+		next.setup(&self.setup)?;
+		Ok(next)
+	}
+}
+
+/// A thread that builds spares for process() and drops the ones it's done with,
+/// process() only hands over templates and swaps finished spares in
+pub struct Builder {
+	/// Templates to build, None where there are no threads
+	jobs: Option<SyncSender<(u64, Template)>>,
+	built: Option<Receiver<(u64, Option<Spare>)>>,
+	/// Dropping a DSP frees its buffers, so it's left to the builder
+	retired: Option<SyncSender<Spare>>,
+	/// Number of the latest request, spares built for older ones are retired
+	generation: u64,
+	/// The latest request until its spare arrives, later changes start from it
+	pending: Option<Template>,
+	/// The latest request couldn't be built, so it isn't asked for again unchanged
+	failed: bool,
+}
+
+impl Builder {
+	/// Start the thread, which allocates. Spares open their sockets with `opener`
+	pub fn start(opener: Option<Opener>) -> Self {
+		let (jobs, job_receiver) = sync_channel(JOBS);
+		let (built_sender, built) = sync_channel(JOBS);
+		let (retired, retired_receiver) = sync_channel(RETIRED);
+		let thread = thread::Builder::new()
+			.name("opus-parvulum-spare".into())
+			.spawn(move || run_builder(job_receiver, built_sender, retired_receiver, opener));
+		match thread {
+			Ok(_) => Self {
+				jobs: Some(jobs),
+				built: Some(built),
+				retired: Some(retired),
+				generation: 0,
+				pending: None,
+				failed: false,
+			},
+			Err(err) => {
+				warn!("spare: can't start the builder: {}", err);
+				Self {
+					jobs: None,
+					built: None,
+					retired: None,
+					generation: 0,
+					pending: None,
+					failed: false,
+				}
+			}
+		}
+	}
+
+	/// The builder thread is up
+	pub fn is_running(&self) -> bool {
+		self.jobs.is_some()
+	}
+
+	/// Template of the spare being built
+	pub fn pending(&self) -> Option<&Template> {
+		self.pending.as_ref()
+	}
+
+	/// The last spare couldn't be built
+	pub fn has_failed(&self) -> bool {
+		self.failed
+	}
+
+	/// Ask for a spare, false when the builder can't take it and the changes should
+	/// be applied in place instead
+	pub fn request(&mut self, template: Template) -> bool {
+		let generation = self.generation.wrapping_add(1);
+		let sent = match &self.jobs {
+			Some(jobs) => jobs.try_send((generation, template.clone())).is_ok(),
+			None => false,
+		};
+		if sent {
+			self.generation = generation;
+			self.pending = Some(template);
+			self.failed = false;
+		}
+		sent
+	}
+
+	/// Forget the requests in flight, their spares are retired when they arrive
+	pub fn cancel(&mut self) {
+		self.generation = self.generation.wrapping_add(1);
+		self.pending = None;
+	}
+
+	/// The spare for the latest request, once it's built
+	pub fn take_built(&mut self) -> Option<Spare> {
+		let built = self.built.as_ref()?;
+		let mut latest = None;
+		while let Ok((generation, spare)) = built.try_recv() {
+			if generation == self.generation {
+				self.pending = None;
+				self.failed = spare.is_none();
+				latest = spare;
+			} else if let Some(spare) = spare {
+				self.retire(spare);
+			}
+		}
+		latest
+	}
+
+	/// Drop a spare on the builder, here only when there is no builder
+	pub fn retire(&self, spare: Spare) {
+		if let Some(retired) = &self.retired {
+			if retired.try_send(spare).is_err() {
+				warn!("spare: retired queue is full, dropped a DSP in process()");
+			}
+		}
+	}
+}
+
+/// Ends when the processor drops its builder
+fn run_builder(
+	jobs: Receiver<(u64, Template)>,
+	built: SyncSender<(u64, Option<Spare>)>,
+	retired: Receiver<Spare>,
+	opener: Option<Opener>,
+) {
+	loop {
+		let job = jobs.recv_timeout(RETIRE_INTERVAL);
+		while let Ok(spare) = retired.try_recv() {
+			drop(spare);
+		}
+		let (generation, template) = match job {
+			Ok(job) => job,
+			Err(RecvTimeoutError::Timeout) => continue,
+			Err(RecvTimeoutError::Disconnected) => return,
+		};
+		let max_block = template.setup.max_samples_per_block as usize;
+		let spare = match template.build(opener.as_ref()) {
+			Ok(dsp) => Some(Spare::new(dsp, max_block)),
+			Err(err) => {
+				warn!("spare: can't build a DSP: {}", err);
+				None
+			}
+		};
+		if built.send((generation, spare)).is_err() {
+			return;
+		}
+	}
+}
@@ -0,0 +1,366 @@
+use super::curve::Curve;
+use super::dsp::OpusDSP;
+use super::locale::Locale;
+use super::params::Parameter;
+use crate::error::Error;
+use crate::error::Result;
+use enum_map::EnumMap;
+use log::*;
+use std::mem::size_of;
+#[cfg(feature = "vst3")]
+use vst3_com::{c_void, ComPtr};
+#[cfg(feature = "vst3")]
+use vst3_sys::base::{kResultOk, IBStream, IStreamSeekMode};
+
+/// Start of a keyed state chunk, older chunks are a bare list of f64
+const MAGIC: [u8; 4] = *b"OPvS";
+const VERSION: u32 = 1;
+
+/// Normalized values from a state chunk, None when the chunk doesn't have one
+pub type Values = EnumMap<Parameter, Option<f64>>;
+
+/// Key of the impulse response path
+pub const IMPULSE_PATH: &str = "impulse_path";
+
+/// Key of the null reference path
+pub const NULL_REFERENCE_PATH: &str = "null_reference_path";
+
+/// Everything in a state chunk
+#[derive(Default)]
+pub struct Chunk {
+	pub values: Values,
+	/// Text settings by key, like file paths, saved after the values
+	pub strings: Vec<(String, String)>,
+}
+
+impl Chunk {
+	/// String saved under a key
+	pub fn string(&self, key: &str) -> Option<&str> {
+		self.strings
+			.iter()
+			.find(|(k, _)| k == key)
+			.map(|(_, value)| value.as_str())
+	}
+}
+
+/// Streams may take fewer bytes than asked, so write until done or the stream stops
+#[cfg(feature = "vst3")]
+pub unsafe fn write_bytes(stream: &ComPtr<dyn IBStream>, bytes: &[u8]) -> Result<()> {
+	let mut written = 0;
+	while written < bytes.len() {
+		let mut num_bytes_written = 0;
+		let rest = &bytes[written..];
+		let ptr = rest.as_ptr() as *const c_void;
+		let result = stream.write(ptr, rest.len() as i32, &mut num_bytes_written);
+		if result != kResultOk || num_bytes_written <= 0 {
+			return Err(Error::Stream("write stopped early"));
+		}
+		written += num_bytes_written as usize;
+	}
+	Ok(())
+}
+
+/// Streams may return fewer bytes than asked, so read until full or the end of the stream
+#[cfg(feature = "vst3")]
+pub unsafe fn read_bytes(stream: &ComPtr<dyn IBStream>, bytes: &mut [u8]) -> usize {
+	let mut filled = 0;
+	while filled < bytes.len() {
+		let mut num_bytes_read = 0;
+		let rest = &mut bytes[filled..];
+		let ptr = rest.as_mut_ptr() as *mut c_void;
+		let result = stream.read(ptr, rest.len() as i32, &mut num_bytes_read);
+		if result != kResultOk || num_bytes_read <= 0 {
+			break;
+		}
+		filled += num_bytes_read as usize;
+	}
+	filled
+}
+
+/// Fills the buffer from a stream or a byte slice, returning how many bytes were read
+type Source<'a> = dyn FnMut(&mut [u8]) -> usize + 'a;
+
+fn read_exact(source: &mut Source, bytes: &mut [u8]) -> Result<()> {
+	let len = source(bytes);
+	if len != bytes.len() {
+		return Err(Error::Truncated);
+	}
+	Ok(())
+}
+
+/// Longest string a chunk may hold, paths and encoded curves are far shorter
+pub const MAX_STRING_BYTES: u32 = 64 * 1024;
+
+/// A string length read from a chunk, refused past MAX_STRING_BYTES so a corrupt
+/// chunk can't ask for gigabytes
+pub fn string_len(len: u32) -> Result<usize> {
+	if len > MAX_STRING_BYTES {
+		let msg = format!("string of {} bytes, at most {}", len, MAX_STRING_BYTES);
+		return Err(Error::StateFormat(msg));
+	}
+	Ok(len as usize)
+}
+
+fn read_u32(source: &mut Source) -> Result<u32> {
+	let mut bytes = [0; size_of::<u32>()];
+	read_exact(source, &mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+/// Drop values that aren't numbers and clamp the rest to the normalized range
+fn validate(param: Parameter, value: f64) -> Option<f64> {
+	if !value.is_finite() {
+		warn!("state: {:?} is {}, keeping the current value", param, value);
+		return None;
+	}
+	// Any curve maps 0..1 onto 0..1, linear is enough to snap and clamp
+	let plain = param.normalized_param_to_plain(value, Curve::Linear);
+	let clamped = param.plain_param_to_normalized(plain, Curve::Linear);
+	if (clamped - value).abs() > 1e-9 {
+		warn!("state: {:?} is {}, corrected to {}", param, value, clamped);
+	}
+	Some(clamped)
+}
+
+/// Every saved parameter by key, then the strings.
+/// Readers from before strings stop after the values, so the version stays the same.
+pub fn encode(values: &EnumMap<Parameter, f64>, strings: &[(&str, &str)]) -> Vec<u8> {
+	let saved = values.iter().filter(|(param, _)| param.is_saved());
+
+	let mut bytes = Vec::new();
+	bytes.extend_from_slice(&MAGIC);
+	bytes.extend_from_slice(&VERSION.to_le_bytes());
+	bytes.extend_from_slice(&(saved.clone().count() as u32).to_le_bytes());
+
+	for (param, value) in saved {
+		let key = param.key().as_bytes();
+		bytes.push(key.len() as u8);
+		bytes.extend_from_slice(key);
+		bytes.extend_from_slice(&value.to_le_bytes());
+	}
+
+	bytes.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+	for (key, value) in strings {
+		bytes.push(key.len() as u8);
+		bytes.extend_from_slice(key.as_bytes());
+		bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+		bytes.extend_from_slice(value.as_bytes());
+	}
+
+	bytes
+}
+
+/// Write values and strings as the current chunk version
+#[cfg(feature = "vst3")]
+pub unsafe fn write(
+	stream: &ComPtr<dyn IBStream>,
+	values: &EnumMap<Parameter, f64>,
+	strings: &[(&str, &str)],
+) -> Result<()> {
+	write_bytes(stream, &encode(values, strings))
+}
+
+/// A keyed chunk from encode()
+pub fn decode(mut bytes: &[u8]) -> Result<Chunk> {
+	let mut source = |buffer: &mut [u8]| {
+		let len = buffer.len().min(bytes.len());
+		buffer[..len].copy_from_slice(&bytes[..len]);
+		bytes = &bytes[len..];
+		len
+	};
+
+	let mut magic = [0; 4];
+	if source(&mut magic) != magic.len() || magic != MAGIC {
+		return Err(Error::StateFormat("not a keyed chunk".to_string()));
+	}
+	read_keyed(&mut source)
+}
+
+/// Read a keyed chunk, or a chunk from before keys were saved
+#[cfg(feature = "vst3")]
+pub unsafe fn read(stream: &ComPtr<dyn IBStream>) -> Result<Chunk> {
+	let mut start = 0;
+	stream.tell(&mut start);
+
+	let mut magic = [0; 4];
+	if read_bytes(stream, &mut magic) != magic.len() || magic != MAGIC {
+		let mut position = 0;
+		let result = stream.seek(start, IStreamSeekMode::kIBSeekSet as i32, &mut position);
+		if result != kResultOk {
+			return Err(Error::Stream("can't seek"));
+		}
+		let values = read_legacy(stream)?;
+		return Ok(Chunk {
+			values,
+			..Chunk::default()
+		});
+	}
+
+	// Half a chunk could be anything, so none of it is used
+	match read_keyed(&mut |bytes: &mut [u8]| read_bytes(stream, bytes)) {
+		Err(Error::Truncated) => {
+			warn!("state: chunk is truncated, using defaults");
+			Ok(Chunk {
+				values: defaults(),
+				..Chunk::default()
+			})
+		}
+		result => result,
+	}
+}
+
+/// Default for every saved parameter
+fn defaults() -> Values {
+	let mut values = Values::default();
+	for (param, value) in values.iter_mut().filter(|(param, _)| param.is_saved()) {
+		*value = Some(param.default_normalized());
+	}
+	values
+}
+
+/// The rest of a keyed chunk, after the magic
+fn read_keyed(source: &mut Source) -> Result<Chunk> {
+	let version = read_u32(source)?;
+	if version > VERSION {
+		let msg = format!("version {} is newer than {}", version, VERSION);
+		return Err(Error::StateFormat(msg));
+	}
+
+	let mut values = Values::default();
+	for _ in 0..read_u32(source)? {
+		let mut len = [0u8];
+		read_exact(source, &mut len)?;
+		let mut key = vec![0; len[0] as usize];
+		read_exact(source, &mut key)?;
+		let mut value = [0; size_of::<f64>()];
+		read_exact(source, &mut value)?;
+
+		// Keys from newer versions are skipped
+		match std::str::from_utf8(&key).ok().and_then(Parameter::from_key) {
+			Some(param) if param.is_saved() => {
+				values[param] = validate(param, f64::from_le_bytes(value))
+			}
+			_ => {}
+		}
+	}
+
+	// Chunks from before strings end here
+	let mut count = [0; size_of::<u32>()];
+	let strings = match source(&mut count) {
+		0 => Vec::new(),
+		len if len == count.len() => read_strings(source, u32::from_le_bytes(count))?,
+		_ => return Err(Error::Truncated),
+	};
+
+	Ok(Chunk { values, strings })
+}
+
+fn read_strings(source: &mut Source, count: u32) -> Result<Vec<(String, String)>> {
+	let mut strings = Vec::new();
+	for _ in 0..count {
+		let mut len = [0u8];
+		read_exact(source, &mut len)?;
+		let mut key = vec![0; len[0] as usize];
+		read_exact(source, &mut key)?;
+		let mut value = vec![0; string_len(read_u32(source)?)?];
+		read_exact(source, &mut value)?;
+
+		match (String::from_utf8(key), String::from_utf8(value)) {
+			(Ok(key), Ok(value)) => strings.push((key, value)),
+			_ => warn!("state: skipping a string that isn't UTF-8"),
+		}
+	}
+	Ok(strings)
+}
+
+/// Plain value of every parameter, normalized values depend on the curves
+pub fn plain_values(dsp: &OpusDSP) -> Result<EnumMap<Parameter, f64>> {
+	let mut values = EnumMap::<Parameter, f64>::default();
+	for (param, value) in values.iter_mut() {
+		let normalized = param.get_from_dsp(dsp)?;
+		*value = param.normalized_param_to_plain(normalized, dsp.curves[param]);
+	}
+	Ok(values)
+}
+
+/// Plain default of every parameter
+pub fn plain_defaults() -> EnumMap<Parameter, f64> {
+	let mut values = EnumMap::<Parameter, f64>::default();
+	for (param, value) in values.iter_mut() {
+		*value = param.normalized_param_to_plain(param.default_normalized(), Curve::Linear);
+	}
+	values
+}
+
+/// Saved parameters that differ, like "complexity 9 → 5", for telling why a session sounds different
+pub fn diff(before: &EnumMap<Parameter, f64>, after: &EnumMap<Parameter, f64>) -> Vec<String> {
+	after
+		.iter()
+		.filter(|(param, value)| param.is_saved() && (before[*param] - **value).abs() > 1e-9)
+		.map(|(param, value)| {
+			let old = show(param, before[param]);
+			format!("{} {} → {}", param.key(), old, show(param, *value))
+		})
+		.collect()
+}
+
+/// Every saved parameter as a "key: value" line, for recording how a render was made
+pub fn snapshot(values: &EnumMap<Parameter, f64>) -> String {
+	let lines: Vec<String> = values
+		.iter()
+		.filter(|(param, _)| param.is_saved())
+		.map(|(param, value)| format!("{}: {}", param.key(), show(param, *value)))
+		.collect();
+	lines.join("\n")
+}
+
+fn show(param: Parameter, plain: f64) -> String {
+	let normalized = param.plain_param_to_normalized(plain, Curve::Linear);
+	param
+		.get_param_string_by_value(normalized, Locale::English, Curve::Linear)
+		.unwrap_or_else(|| plain.to_string())
+}
+
+/// Normalized f64 in enum order, without unsaved parameters
+#[cfg(feature = "vst3")]
+unsafe fn read_legacy(stream: &ComPtr<dyn IBStream>) -> Result<Values> {
+	let mut values = Values::default();
+	for (param, value) in values.iter_mut().filter(|(param, _)| param.is_saved()) {
+		let mut bytes = [0; size_of::<f64>()];
+		if read_bytes(stream, &mut bytes) != bytes.len() {
+			break;
+		}
+		*value = validate(param, f64::from_ne_bytes(bytes));
+	}
+	Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn diff_lists_changed_saved_values() {
+		let before = plain_defaults();
+		let mut after = before;
+		after[Parameter::Complexity] = 5.0;
+		after[Parameter::LevelWarning] = 1.0;
+		assert_eq!(diff(&before, &after), vec!["complexity 9 → 5".to_string()]);
+	}
+
+	#[test]
+	fn snapshot_has_a_line_per_saved_value() {
+		let text = snapshot(&plain_defaults());
+		assert!(text.lines().any(|line| line == "complexity: 9"));
+		assert!(!text.contains(Parameter::LevelWarning.key()));
+	}
+
+	#[test]
+	fn refuses_huge_strings() {
+		let values = EnumMap::<Parameter, f64>::default();
+		let mut bytes = encode(&values, &[("key", "v")]);
+		let len = bytes.len();
+		bytes[len - 5..len - 1].copy_from_slice(&u32::MAX.to_le_bytes());
+		assert!(matches!(decode(&bytes), Err(Error::StateFormat(_))));
+	}
+}
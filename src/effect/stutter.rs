@@ -0,0 +1,122 @@
+use super::dsp::MAX_PACKET_BYTES;
+
+/// Note values freeze can snap to, in parameter order
+pub const SYNC: [&str; 3] = ["Off", "1/16", "1/8"];
+
+/// Length of each SYNC division in quarter notes, zero for free
+const SYNC_QUARTERS: [f64; 3] = [0.0, 0.25, 0.5];
+
+/// Captures a packet when freeze engages and replays it in place of new packets
+pub struct Stutter {
+	captured: Vec<u8>,
+	/// Replays left before normal decoding resumes, None loops until released
+	remaining: Option<u32>,
+	engaged: bool,
+}
+
+impl Default for Stutter {
+	fn default() -> Self {
+		Self {
+			captured: Vec::with_capacity(MAX_PACKET_BYTES),
+			remaining: None,
+			engaged: false,
+		}
+	}
+}
+
+impl Stutter {
+	/// Returns the packet to decode in place of `packet`.
+	/// A `repeat` of 0 loops the captured packet for as long as freeze is held.
+	pub fn next<'a>(&'a mut self, packet: &'a [u8], freeze: bool, repeat: u32) -> &'a [u8] {
+		if !freeze {
+			self.engaged = false;
+			return packet;
+		}
+
+		if !self.engaged {
+			// Rising edge, capture and pass through once
+			self.engaged = true;
+			self.captured.clear();
+			self.captured
+				.extend_from_slice(&packet[..packet.len().min(MAX_PACKET_BYTES)]);
+			self.remaining = if repeat == 0 { None } else { Some(repeat) };
+			return packet;
+		}
+
+		match &mut self.remaining {
+			None => &self.captured,
+			Some(0) => packet,
+			Some(n) => {
+				*n -= 1;
+				&self.captured
+			}
+		}
+	}
+
+	/// Release the captured packet and stop repeating
+	pub fn reset(&mut self) {
+		self.engaged = false;
+		self.remaining = None;
+		self.captured.clear();
+	}
+}
+
+/// Moves freeze changes to the nearest grid line, so performed glitches land on the beat.
+/// A change just after a line takes effect right away, otherwise it waits for the next one
+#[derive(Default)]
+pub struct FreezeSync {
+	/// Index into SYNC
+	pub sync: usize,
+	frozen: bool,
+}
+
+impl FreezeSync {
+	/// Freeze for a packet spanning `span`, its start and length in quarter notes.
+	/// Without a span, like when the host is stopped or has no tempo, changes aren't held
+	pub fn update(&mut self, freeze: bool, span: Option<(f64, f64)>) -> bool {
+		let division = SYNC_QUARTERS[self.sync.min(SYNC.len() - 1)];
+		let snapped = match span {
+			_ if freeze == self.frozen => true,
+			Some((start, len)) if division > 0.0 => {
+				let phase = start.rem_euclid(division);
+				phase < division / 2.0 || phase + len >= division
+			}
+			_ => true,
+		};
+		if snapped {
+			self.frozen = freeze;
+		}
+		self.frozen
+	}
+
+	/// Freeze as of the last update
+	pub fn frozen(&self) -> bool {
+		self.frozen
+	}
+
+	/// Let go of the freeze
+	pub fn reset(&mut self) {
+		self.frozen = false;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn freeze_waits_for_the_grid() {
+		let mut sync = FreezeSync {
+			sync: 1,
+			frozen: false,
+		};
+		// Late in a sixteenth, held until the packet that crosses the line
+		assert!(!sync.update(true, Some((0.2, 0.02))));
+		assert!(sync.update(true, Some((0.24, 0.02))));
+		// Just after a line, released right away
+		assert!(!sync.update(false, Some((0.26, 0.02))));
+		// No tempo, no waiting
+		sync.sync = 2;
+		assert!(sync.update(true, None));
+	}
+}
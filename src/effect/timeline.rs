@@ -0,0 +1,105 @@
+use crate::sys::ProcessContext;
+
+/// ProcessContext::kPlaying
+const KPLAYING: u32 = 1 << 1;
+
+/// ProcessContext::kProjectTimeMusicValid
+const KPROJECT_TIME_MUSIC_VALID: u32 = 1 << 9;
+
+/// ProcessContext::kTempoValid
+const KTEMPO_VALID: u32 = 1 << 10;
+
+/// ProcessContext::kContTimeValid
+const KCONT_TIME_VALID: u32 = 1 << 17;
+
+/// Host time of the block being processed, so packets and events line up with the project
+pub struct Timeline {
+	/// Frame at the start of the block, at the host rate
+	start: i64,
+	/// Where the next block starts when the host doesn't say
+	next: i64,
+	rate: f64,
+	/// Transport was playing in the last block
+	playing: bool,
+	/// Quarter notes at the start of the block and per frame, while playing with a tempo
+	music: Option<(f64, f64)>,
+}
+
+impl Default for Timeline {
+	fn default() -> Self {
+		Self {
+			start: 0,
+			next: 0,
+			rate: 48000.0,
+			playing: false,
+			music: None,
+		}
+	}
+}
+
+impl Timeline {
+	/// Host rate the frame counts are in
+	pub fn set_rate(&mut self, rate: f64) {
+		self.rate = rate;
+	}
+
+	/// Project time while playing, continuous time while stopped since the project
+	/// position stands still, and a running count of frames without a context.
+	/// Returns the project frame when the transport started or jumped, like at a loop
+	pub fn advance(&mut self, context: Option<&ProcessContext>, frames: usize) -> Option<i64> {
+		let playing = context.is_some_and(|context| context.state & KPLAYING != 0);
+		let expected = self.next;
+		self.start = match context {
+			Some(context) if context.state & KPLAYING != 0 => context.project_time_samples,
+			Some(context) if context.state & KCONT_TIME_VALID != 0 => {
+				context.continous_time_samples
+			}
+			Some(context) => context.project_time_samples,
+			None => self.next,
+		};
+		self.next = self.start + frames as i64;
+
+		let musical = KPLAYING | KPROJECT_TIME_MUSIC_VALID | KTEMPO_VALID;
+		self.music = match context {
+			Some(context) if context.state & musical == musical && context.tempo > 0.0 => {
+				Some((context.project_time_music, context.tempo / 60.0 / self.rate))
+			}
+			_ => None,
+		};
+
+		let started = playing && !self.playing;
+		self.playing = playing;
+		if started || (playing && self.start != expected) {
+			Some(self.start)
+		} else {
+			None
+		}
+	}
+
+	/// Packets of `packet_secs` from the start of the project to a project frame
+	pub fn packets_before(&self, frame: i64, packet_secs: f64) -> u64 {
+		(frame.max(0) as f64 / self.rate / packet_secs) as u64
+	}
+
+	/// Start and length in quarter notes of `frames` from a frame offset into the block,
+	/// None unless the transport is playing with a tempo
+	pub fn quarters(&self, offset: i64, frames: usize) -> Option<(f64, f64)> {
+		self.music.map(|(start, per_frame)| {
+			(start + offset as f64 * per_frame, frames as f64 * per_frame)
+		})
+	}
+
+	/// Project frame at a frame offset into the block, None while the transport is stopped
+	pub fn project_frame(&self, offset: i64) -> Option<i64> {
+		if self.playing {
+			Some(self.start + offset)
+		} else {
+			None
+		}
+	}
+
+	/// Seconds at a frame offset into the block, negative offsets reach into earlier blocks
+	pub fn seconds(&self, offset: i64) -> f64 {
+		(self.start + offset) as f64 / self.rate
+	}
+}
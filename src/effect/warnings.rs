@@ -0,0 +1,220 @@
+use log::*;
+#[cfg(feature = "vst3")]
+use std::ffi::CStr;
+#[cfg(feature = "vst3")]
+use std::os::raw::c_void;
+#[cfg(feature = "vst3")]
+use std::ptr::null_mut;
+#[cfg(feature = "vst3")]
+use std::slice;
+#[cfg(feature = "vst3")]
+use vst3_com::{ComInterface, ComPtr};
+#[cfg(feature = "vst3")]
+use vst3_sys::base::{kResultFalse, kResultOk, tresult};
+#[cfg(feature = "vst3")]
+use vst3_sys::vst::{IAttributeList, IConnectionPoint, IHostApplication, IMessage};
+
+/// The host sample rate needs attention
+pub const RATE: &str = "rate";
+
+/// The impulse response didn't load
+pub const IMPULSE: &str = "impulse";
+
+/// The null reference didn't load or doesn't fit the host
+pub const NULL: &str = "null";
+
+const SOURCES: usize = 3;
+
+/// Problems the user should see instead of finding them in the log, one per source.
+/// Raised and resolved off the audio thread, since they allocate
+pub struct Warnings {
+	lines: Vec<(&'static str, String)>,
+	/// Lines changed since they were last sent to the controller
+	unsent: bool,
+	/// Flag last written to the host
+	reported: bool,
+}
+
+impl Default for Warnings {
+	fn default() -> Self {
+		Self {
+			lines: Vec::with_capacity(SOURCES),
+			unsent: false,
+			reported: false,
+		}
+	}
+}
+
+impl Warnings {
+	/// Set or clear the warning from a source with a line made elsewhere, giving back
+	/// the line it replaced. Doesn't allocate, so process() can hand over a file's outcome
+	pub fn replace(&mut self, source: &'static str, line: Option<String>) -> Option<String> {
+		let index = self.lines.iter().position(|(s, _)| *s == source);
+		let old = match (index, line) {
+			(Some(index), Some(line)) => Some(std::mem::replace(&mut self.lines[index].1, line)),
+			(Some(index), None) => Some(self.lines.remove(index).1),
+			(None, Some(line)) => {
+				self.lines.push((source, line));
+				None
+			}
+			(None, None) => return None,
+		};
+		self.unsent = true;
+		old
+	}
+
+	/// Show the same lines as `other`
+	pub fn copy_from(&mut self, other: &Warnings) {
+		if self.lines != other.lines {
+			self.lines.clone_from(&other.lines);
+			self.unsent = true;
+		}
+	}
+
+	/// Replace the warning from a source
+	pub fn raise(&mut self, source: &'static str, line: String) {
+		match self.lines.iter_mut().find(|(s, _)| *s == source) {
+			Some((_, old)) if *old == line => return,
+			Some((_, old)) => *old = line.clone(),
+			None => self.lines.push((source, line.clone())),
+		}
+		warn!("warning: {}", line);
+		self.unsent = true;
+	}
+
+	/// The problem from a source went away
+	pub fn resolve(&mut self, source: &'static str) {
+		let len = self.lines.len();
+		self.lines.retain(|(s, _)| *s != source);
+		self.unsent |= self.lines.len() != len;
+	}
+
+	/// Any warning is shown
+	pub fn is_active(&self) -> bool {
+		!self.lines.is_empty()
+	}
+
+	/// Every warning, a line each
+	pub fn text(&self) -> String {
+		let lines: Vec<&str> = self.lines.iter().map(|(_, line)| line.as_str()).collect();
+		lines.join("\n")
+	}
+
+	/// Text if it changed since it was last sent
+	pub fn take_unsent(&mut self) -> Option<String> {
+		if !self.unsent {
+			return None;
+		}
+		self.unsent = false;
+		Some(self.text())
+	}
+
+	/// Flag if it changed since it was last reported
+	pub fn take_flag_change(&mut self) -> Option<bool> {
+		if self.is_active() == self.reported {
+			return None;
+		}
+		self.reported = self.is_active();
+		Some(self.reported)
+	}
+}
+
+const MESSAGE_ID: &[u8] = b"OpusWarnings\0";
+const ATTRIBUTE_ID: &[u8] = b"text\0";
+
+/// Send the processor's warnings to the controller, empty text clears them
+#[cfg(feature = "vst3")]
+pub unsafe fn send(context: *mut c_void, peer: *mut c_void, text: &str) -> tresult {
+	if context.is_null() || peer.is_null() {
+		return kResultFalse;
+	}
+
+	let host: ComPtr<dyn IHostApplication> = ComPtr::new(context as *mut *mut _);
+	let mut cid = <dyn IMessage as ComInterface>::IID;
+	let mut iid = <dyn IMessage as ComInterface>::IID;
+	let mut message = null_mut();
+	let result = host.create_instance(&mut cid, &mut iid, &mut message);
+	if result != kResultOk || message.is_null() {
+		warn!("warnings: host can't create messages");
+		return result;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	message.set_message_id(MESSAGE_ID.as_ptr() as *const _);
+
+	let attributes = message.get_attributes();
+	if !attributes.is_null() {
+		let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+		attributes.set_binary(
+			ATTRIBUTE_ID.as_ptr() as *const _,
+			text.as_ptr() as *const c_void,
+			text.len() as u32,
+		);
+	}
+
+	let peer: ComPtr<dyn IConnectionPoint> = ComPtr::new(peer as *mut *mut _);
+	let result = peer.notify(message.as_raw() as *mut c_void);
+	message.release();
+	result
+}
+
+/// The text from a warnings message, a UTF-8 binary attribute, None for any other message
+#[cfg(feature = "vst3")]
+pub unsafe fn receive(message: *mut c_void) -> Option<String> {
+	if message.is_null() {
+		return None;
+	}
+
+	let message: ComPtr<dyn IMessage> = ComPtr::new(message as *mut *mut _);
+	let id = message.get_message_id();
+	if id.is_null() || CStr::from_ptr(id).to_bytes_with_nul() != MESSAGE_ID {
+		return None;
+	}
+
+	let attributes = message.get_attributes();
+	if attributes.is_null() {
+		return None;
+	}
+	let attributes: ComPtr<dyn IAttributeList> = ComPtr::new(attributes as *mut *mut _);
+	let mut data = std::ptr::null();
+	let mut size = 0;
+	let id = ATTRIBUTE_ID.as_ptr() as *const _;
+	if attributes.get_binary(id, &mut data, &mut size) != kResultOk {
+		return None;
+	}
+	if data.is_null() {
+		return Some(String::new());
+	}
+
+	let bytes = slice::from_raw_parts(data as *const u8, size as usize);
+	Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sources_replace_and_resolve() {
+		let mut warnings = Warnings::default();
+		warnings.raise(RATE, "first".to_string());
+		warnings.raise(IMPULSE, "missing".to_string());
+		warnings.raise(RATE, "second".to_string());
+		assert_eq!(warnings.take_unsent().as_deref(), Some("second\nmissing"));
+		assert_eq!(warnings.take_unsent(), None);
+		assert_eq!(warnings.take_flag_change(), Some(true));
+
+		warnings.resolve(RATE);
+		warnings.resolve(IMPULSE);
+		assert_eq!(warnings.take_unsent().as_deref(), Some(""));
+		assert_eq!(warnings.take_flag_change(), Some(false));
+	}
+	#[test]
+	fn replace_hands_back_the_old_line() {
+		let mut warnings = Warnings::default();
+		assert_eq!(warnings.replace(NULL, Some("missing".to_string())), None);
+		assert_eq!(warnings.take_unsent().as_deref(), Some("missing"));
+		assert_eq!(warnings.replace(NULL, None).as_deref(), Some("missing"));
+		assert!(!warnings.is_active());
+	}
+}
@@ -0,0 +1,109 @@
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+
+/// Most the last packet is slowed down by, in percent
+pub const MAX_WARP_PERCENT: f64 = 50.0;
+
+fn similarity(a: &[[f32; 2]], b: &[[f32; 2]]) -> f32 {
+	a.iter()
+		.zip(b)
+		.map(|(x, y)| x[0] * y[0] + x[1] * y[1])
+		.sum()
+}
+
+/// Start between `from` and `to` where `source` looks most like `template`
+fn best_offset(source: &[[f32; 2]], template: &[[f32; 2]], from: usize, to: usize) -> usize {
+	let len = template.len();
+	let score = |start: &usize| similarity(&source[*start..*start + len], template);
+	(from..=to.min(source.len() - len))
+		.max_by(|a, b| score(a).partial_cmp(&score(b)).unwrap_or(Ordering::Equal))
+		.unwrap_or(from)
+}
+
+/// Receiver that covers a lost packet by stretching the one before it, the drawl of
+/// adaptive playout, instead of the decoder's concealment
+#[derive(Default)]
+pub struct TimeWarp {
+	/// Slowdown of the last packet, in percent, none leaves concealment to the decoder
+	pub max_percent: f64,
+	/// Last packet played
+	history: Vec<[f32; 2]>,
+}
+
+impl TimeWarp {
+	/// Concealment may stretch the audio
+	pub fn is_enabled(&self) -> bool {
+		self.max_percent > 0.0
+	}
+
+	/// Room for a packet, which allocates
+	pub fn set_len(&mut self, len: usize) {
+		self.history = vec![[0.0; 2]; len];
+	}
+
+	/// Remember a packet that played
+	pub fn record(&mut self, packet: &[[f32; 2]]) {
+		let len = packet.len().min(self.history.len());
+		self.history[..len].copy_from_slice(&packet[..len]);
+	}
+
+	/// Fill a packet from the last one read more slowly, in overlapping grains
+	/// placed where they line up best with the grain before (WSOLA)
+	pub fn conceal(&self, packet: &mut [[f32; 2]]) {
+		let len = packet.len().min(self.history.len());
+		let grain = (len / 4) & !1;
+		if grain < 4 {
+			return;
+		}
+		let hop = grain / 2;
+		let source = &self.history[..len];
+		let speed = 1.0 - self.max_percent.min(MAX_WARP_PERCENT) / 100.0;
+		let last = len - grain;
+
+		// The first grain carries on from the end of the last packet
+		let tail = &source[len - hop..];
+		let first = best_offset(source, tail, 0, last.saturating_sub(hop)) + hop;
+
+		packet[..len].fill([0.0; 2]);
+		let mut read = first.min(last);
+		for (k, write) in (0..len).step_by(hop).enumerate() {
+			if k > 0 {
+				let nominal = (first as f64 + (k * hop) as f64 * speed) as usize;
+				let template = &source[read + hop..read + grain];
+				let from = nominal.saturating_sub(hop / 2).min(last);
+				read = best_offset(source, template, from, (nominal + hop / 2).min(last));
+			}
+			for i in 0..grain.min(len - write) {
+				let gain = if k == 0 && i < hop {
+					1.0
+				} else {
+					0.5 - 0.5 * (2.0 * PI * i as f32 / grain as f32).cos()
+				};
+				let [l, r] = source[read + i];
+				packet[write + i][0] += l * gain;
+				packet[write + i][1] += r * gain;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn conceals_with_the_last_packet() {
+		let mut warp = TimeWarp {
+			max_percent: 20.0,
+			..TimeWarp::default()
+		};
+		warp.set_len(960);
+		let tone: Vec<[f32; 2]> = (0..960).map(|i| [(i as f32 * 0.1).sin(); 2]).collect();
+		warp.record(&tone);
+
+		let mut packet = [[0f32; 2]; 960];
+		warp.conceal(&mut packet);
+		let peak = packet.iter().fold(0f32, |peak, [l, _]| peak.max(l.abs()));
+		assert!(peak > 0.5 && peak < 1.5);
+	}
+}
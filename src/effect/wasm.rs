@@ -0,0 +1,86 @@
+use super::dsp::AuxInputs;
+use super::dsp::OpusDSP;
+use super::dsp::ParamQueueMap;
+use super::params::Parameter;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sys::ProcessSetup;
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+
+fn js_error(err: Error) -> JsValue {
+	JsValue::from_str(&err.to_string())
+}
+
+fn parameter(id: u32) -> Result<Parameter> {
+	Parameter::try_from(id).map_err(|_| Error::OutOfRange(format!("no parameter {}", id)))
+}
+
+/// The plugin's codec and network pipeline, for hearing a file over a bad connection in a browser
+#[wasm_bindgen]
+pub struct Pipeline {
+	dsp: Box<OpusDSP>,
+}
+
+#[wasm_bindgen]
+impl Pipeline {
+	/// Rendering a file is offline, but the loss stays random like a live call
+	#[wasm_bindgen(constructor)]
+	pub fn new(sample_rate: f64, max_block: u32) -> std::result::Result<Pipeline, JsValue> {
+		let mut dsp = Box::new(OpusDSP::default());
+		let setup = ProcessSetup {
+			process_mode: 2,
+			symbolic_sample_size: 0,
+			max_samples_per_block: max_block as i32,
+			sample_rate,
+		};
+		dsp.setup(&setup).map_err(js_error)?;
+		Ok(Self { dsp })
+	}
+
+	/// Parameter ids and normalized values are the same as the plugin's
+	#[wasm_bindgen(js_name = setParam)]
+	pub fn set_param(&mut self, id: u32, value: f64) -> std::result::Result<(), JsValue> {
+		let param = parameter(id).map_err(js_error)?;
+		param.set_to_dsp(&mut self.dsp, value).map_err(js_error)
+	}
+
+	/// Normalized value of a parameter by id, including the read-only counters
+	#[wasm_bindgen(js_name = getParam)]
+	pub fn get_param(&self, id: u32) -> std::result::Result<f64, JsValue> {
+		let param = parameter(id).map_err(js_error)?;
+		param.get_from_dsp(&self.dsp).map_err(js_error)
+	}
+
+	/// Frames the output lags the input
+	pub fn latency(&self) -> u32 {
+		self.dsp.latency() as u32
+	}
+
+	/// One block of stereo audio, the outputs are copied back into the Float32Arrays
+	pub fn process(
+		&mut self,
+		left: &[f32],
+		right: &[f32],
+		left_out: &mut [f32],
+		right_out: &mut [f32],
+	) -> std::result::Result<(), JsValue> {
+		let frames = left
+			.len()
+			.min(right.len())
+			.min(left_out.len())
+			.min(right_out.len());
+		let input = [&left[..frames], &right[..frames]];
+		let output = [&mut left_out[..frames], &mut right_out[..frames]];
+
+		// Parameters are set between blocks, so there are never queued changes.
+		// Without spares, the coders are rebuilt here for the settings they changed
+		let params = ParamQueueMap::default();
+		self.dsp.rebuild().map_err(js_error)?;
+		self.dsp.advance_timeline(None, frames);
+		self.dsp
+			.process(&params, input, AuxInputs::default(), output, false)
+			.map_err(js_error)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+/// Overruns in a row before the watchdog trips
+const TRIP_AFTER: u32 = 8;
+
+/// Blocks the load is averaged over, so the meter is readable
+const LOAD_AVERAGE: f64 = 32.0;
+
+/// Highest load shown, in percent of the budget
+pub const MAX_LOAD: f64 = 200.0;
+
+/// Compares processing time with the real-time budget of each block
+#[derive(Default)]
+pub struct Watchdog {
+	/// Blocks that took longer than their budget
+	pub xruns: u32,
+	reported_xruns: u32,
+	in_a_row: u32,
+	/// Smoothed processing time, in percent of the block's duration
+	load: f64,
+	/// Whole percent last reported
+	reported_load: f64,
+}
+
+impl Watchdog {
+	/// Record one block, true when overruns keep repeating
+	pub fn record(&mut self, elapsed: Duration, budget: Duration) -> bool {
+		if budget > Duration::ZERO {
+			let load = (100.0 * elapsed.as_secs_f64() / budget.as_secs_f64()).min(MAX_LOAD);
+			self.load += (load - self.load) / LOAD_AVERAGE;
+		}
+
+		if elapsed <= budget {
+			self.in_a_row = 0;
+			return false;
+		}
+
+		self.xruns = self.xruns.saturating_add(1);
+		self.in_a_row += 1;
+		if self.in_a_row < TRIP_AFTER {
+			return false;
+		}
+
+		self.in_a_row = 0;
+		true
+	}
+
+	/// Xrun count if it moved since it was last reported
+	pub fn take_xrun_change(&mut self) -> Option<u32> {
+		if self.xruns == self.reported_xruns {
+			return None;
+		}
+		self.reported_xruns = self.xruns;
+		Some(self.xruns)
+	}
+
+	/// Smoothed processing time, in percent of the block's duration
+	pub fn load(&self) -> f64 {
+		self.load
+	}
+
+	/// Load to the whole percent if it moved since it was last reported
+	pub fn take_load_change(&mut self) -> Option<f64> {
+		let load = self.load.round();
+		if load == self.reported_load {
+			return None;
+		}
+		self.reported_load = load;
+		Some(load)
+	}
+
+	/// Start counting again, the cleared count is still reported
+	pub fn reset(&mut self) {
+		self.xruns = 0;
+		self.in_a_row = 0;
+		self.load = 0.0;
+	}
+}
@@ -0,0 +1,39 @@
+use std::path::Path;
+
+/// Stereo frames read from a WAV file, at the rate of the file
+pub struct Wav {
+	pub frames: Vec<[f32; 2]>,
+	pub rate: f64,
+}
+
+/// Read up to `max_secs` of a WAV file, mono is used for both channels and channels
+/// past the second are ignored. Reads the file, so stay off the audio thread
+pub fn read(path: &str, max_secs: Option<f64>) -> hound::Result<Wav> {
+	let mut reader = hound::WavReader::open(Path::new(path))?;
+	let spec = reader.spec();
+	let channels = spec.channels as usize;
+	let rate = f64::from(spec.sample_rate);
+	let max_samples = max_secs.map_or(usize::MAX, |secs| (rate * secs).ceil() as usize * channels);
+
+	let samples: Vec<f32> = match spec.sample_format {
+		hound::SampleFormat::Float => reader
+			.samples::<f32>()
+			.take(max_samples)
+			.collect::<hound::Result<_>>()?,
+		hound::SampleFormat::Int => {
+			let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+			reader
+				.samples::<i32>()
+				.take(max_samples)
+				.map(|sample| sample.map(|sample| sample as f32 / scale))
+				.collect::<hound::Result<_>>()?
+		}
+	};
+
+	let frames = samples
+		.chunks(channels.max(1))
+		.map(|frame| [frame[0], frame[frame.len().min(2) - 1]])
+		.collect();
+
+	Ok(Wav { frames, rate })
+}
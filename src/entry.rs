@@ -0,0 +1,106 @@
+use log::*;
+use simple_logger::SimpleLogger;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+use vst3_com::c_void;
+
+/// Module entries without a matching exit, hosts that scan may load the binary many times
+static ENTRIES: AtomicUsize = AtomicUsize::new(0);
+
+/// The logger and panic hook are process-wide, so they're installed once and outlive exits
+static GLOBALS: Once = Once::new();
+
+fn init() {
+	if ENTRIES.fetch_add(1, Ordering::AcqRel) > 0 {
+		return;
+	}
+
+	GLOBALS.call_once(|| {
+		if let Err(err) = SimpleLogger::new().init() {
+			eprintln!("opus-parvulum: logger already installed, {}", err);
+		}
+
+		// Panics end up in the log instead of only on a stderr the host may not show
+		let previous = panic::take_hook();
+		panic::set_hook(Box::new(move |info| {
+			if ENTRIES.load(Ordering::Acquire) > 0 {
+				error!("{}", info);
+			}
+			previous(info);
+		}));
+	});
+
+	// The Debug Verbosity parameter raises this for a session
+	log::set_max_level(LevelFilter::Info);
+	info!("{}", crate::about::build_info());
+}
+
+fn exit() {
+	match ENTRIES.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1)) {
+		Ok(1) => {}
+		Ok(_) => return,
+		Err(_) => {
+			warn!("module exit without an entry");
+			return;
+		}
+	}
+
+	unsafe { crate::factory::Factory::shutdown() };
+	log::set_max_level(LevelFilter::Off);
+}
+
+#[allow(clippy::missing_safety_doc)]
+#[no_mangle]
+pub unsafe extern "system" fn GetPluginFactory() -> *mut c_void {
+	info!("GetPluginFactory()");
+	crate::factory::Factory::instance()
+}
+
+#[cfg(target_os = "linux")]
+#[no_mangle]
+pub extern "system" fn ModuleEntry(_: *mut c_void) -> bool {
+	init();
+	info!("ModuleEntry()");
+	true
+}
+
+#[cfg(target_os = "linux")]
+#[no_mangle]
+pub extern "system" fn ModuleExit() -> bool {
+	info!("ModuleExit()");
+	exit();
+	true
+}
+
+#[cfg(target_os = "macos")]
+#[no_mangle]
+pub extern "system" fn bundleEntry() -> bool {
+	init();
+	info!("bundleEntry()");
+	true
+}
+
+#[cfg(target_os = "macos")]
+#[no_mangle]
+pub extern "system" fn bundleExit() -> bool {
+	info!("bundleExit()");
+	exit();
+	true
+}
+
+#[cfg(target_os = "windows")]
+#[no_mangle]
+pub extern "system" fn InitDll() -> bool {
+	init();
+	info!("InitDll()");
+	true
+}
+
+#[cfg(target_os = "windows")]
+#[no_mangle]
+pub extern "system" fn ExitDll() -> bool {
+	info!("ExitDll()");
+	exit();
+	true
+}
@@ -0,0 +1,99 @@
+use crate::sys::{
+	kInternalError, kInvalidArgument, kNotInitialized, kOutOfMemory, kResultFalse, tresult,
+};
+use std::cell::BorrowError;
+use std::cell::BorrowMutError;
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What can go wrong setting up or running the DSP
+#[derive(Debug)]
+pub enum Error {
+	/// The Opus encoder or decoder failed
+	Codec(audiopus::Error),
+	/// Sample rate conversion can't be set up for these rates
+	Resample(f64, f64),
+	/// Saved state is malformed, or from a newer version
+	StateFormat(String),
+	/// Saved state ended before the chunk did
+	Truncated,
+	/// The host stream refused a read, write, or seek
+	Stream(&'static str),
+	/// A value outside what the parameter or host call accepts
+	OutOfRange(String),
+	/// Called before setup, or with buses the effect can't process
+	NotInitialized(&'static str),
+	/// An impulse response file that can't be read
+	Impulse(String),
+	/// A null reference file that can't be read or doesn't fit the host
+	NullReference(String),
+	/// Reentrant call while the same state is in use
+	Busy,
+}
+
+impl Error {
+	/// The closest VST result for the host
+	pub fn result(&self) -> tresult {
+		match self {
+			Self::Codec(audiopus::Error::Opus(audiopus::ErrorCode::AllocFail)) => kOutOfMemory,
+			Self::Codec(audiopus::Error::Opus(audiopus::ErrorCode::InvalidState)) => {
+				kNotInitialized
+			}
+			Self::Codec(_) => kInternalError,
+			Self::Resample(..) => kInvalidArgument,
+			Self::StateFormat(_) => kInvalidArgument,
+			Self::Truncated => kInvalidArgument,
+			Self::Stream(_) => kResultFalse,
+			Self::OutOfRange(_) => kInvalidArgument,
+			Self::NotInitialized(_) => kNotInitialized,
+			Self::Impulse(_) => kInvalidArgument,
+			Self::NullReference(_) => kInvalidArgument,
+			Self::Busy => kInternalError,
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Codec(err) => write!(f, "codec: {}", err),
+			Self::Resample(from, to) => write!(f, "can't resample {} Hz to {} Hz", from, to),
+			Self::StateFormat(msg) => write!(f, "state format: {}", msg),
+			Self::Truncated => f.write_str("state is truncated"),
+			Self::Stream(msg) => write!(f, "stream: {}", msg),
+			Self::OutOfRange(msg) => write!(f, "out of range: {}", msg),
+			Self::NotInitialized(msg) => write!(f, "not initialized: {}", msg),
+			Self::Impulse(msg) => write!(f, "impulse response: {}", msg),
+			Self::NullReference(msg) => write!(f, "null reference: {}", msg),
+			Self::Busy => f.write_str("already in use"),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Self::Codec(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<audiopus::Error> for Error {
+	fn from(err: audiopus::Error) -> Self {
+		Self::Codec(err)
+	}
+}
+
+impl From<BorrowError> for Error {
+	fn from(_: BorrowError) -> Self {
+		Self::Busy
+	}
+}
+
+impl From<BorrowMutError> for Error {
+	fn from(_: BorrowMutError) -> Self {
+		Self::Busy
+	}
+}
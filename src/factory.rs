@@ -1,19 +1,75 @@
 use crate::effect::OpusController;
 use crate::effect::OpusProcessor;
+#[cfg(feature = "single-component")]
+use crate::effect::OpusSingle;
 use crate::effect::VstClassInfo;
+use log::*;
+use std::cell::RefCell;
 use std::os::raw::c_void;
-use vst3_com::IID;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use vst3_com::{ComPtr, IID};
 use vst3_sys::base::IPluginFactory;
 use vst3_sys::base::IPluginFactory2;
 use vst3_sys::base::IPluginFactory3;
+use vst3_sys::base::IUnknown;
 use vst3_sys::VST3;
 
+/// The factory handed out by GetPluginFactory, null until the first call or after the final release
+static INSTANCE: AtomicPtr<Factory> = AtomicPtr::new(null_mut());
+
+struct HostContext(*mut c_void);
+
 #[VST3(implements(IPluginFactory, IPluginFactory2, IPluginFactory3))]
-pub struct Factory {}
+pub struct Factory {
+	context: RefCell<HostContext>,
+}
 
 impl Factory {
 	pub fn new() -> Box<Self> {
-		Self::allocate()
+		let context = RefCell::new(HostContext(null_mut()));
+		Self::allocate(context)
+	}
+
+	/// The one factory, with a reference for the caller
+	pub unsafe fn instance() -> *mut c_void {
+		let current = INSTANCE.load(Ordering::Acquire);
+		if !current.is_null() {
+			(*current).add_ref();
+			return current as *mut c_void;
+		}
+
+		let created = Box::into_raw(Self::new());
+		match INSTANCE.compare_exchange(null_mut(), created, Ordering::AcqRel, Ordering::Acquire) {
+			Ok(_) => created as *mut c_void,
+			Err(current) => {
+				// Another thread got there first
+				drop(Box::from_raw(created));
+				(*current).add_ref();
+				current as *mut c_void
+			}
+		}
+	}
+
+	/// Drop the host context when the module unloads, in case the host never released the factory
+	pub unsafe fn shutdown() {
+		let current = INSTANCE.load(Ordering::Acquire);
+		if !current.is_null() {
+			warn!("factory still referenced at module exit");
+			(*current).set_context(null_mut());
+		}
+	}
+
+	unsafe fn set_context(&self, context: *mut c_void) {
+		let previous = std::mem::replace(&mut self.context.borrow_mut().0, context);
+		if !previous.is_null() {
+			let previous: ComPtr<dyn IUnknown> = ComPtr::new(previous as *mut *mut _);
+			previous.release();
+		}
+		if !context.is_null() {
+			let context: ComPtr<dyn IUnknown> = ComPtr::new(context as *mut *mut _);
+			context.add_ref();
+		}
 	}
 
 	pub const VENDOR_NAME: &'static str = "astra137";
@@ -22,12 +78,19 @@ impl Factory {
 	pub const COMPONENT_VERSION: &'static str = env!("CARGO_PKG_VERSION");
 	pub const COMPONENT_SDK_VERSION: &'static str = "VST 3.6.13";
 
-	pub const CLASSES: i32 = 2;
+	#[cfg(not(feature = "single-component"))]
+	pub const CLASSES: i32 = 4;
+	#[cfg(feature = "single-component")]
+	pub const CLASSES: i32 = 5;
 
 	pub fn get_class(index: i32) -> Option<VstClassInfo> {
 		match index {
 			0 => Some(OpusProcessor::INFO),
 			1 => Some(OpusController::INFO),
+			2 => Some(OpusProcessor::CODEC_INFO),
+			3 => Some(OpusController::CODEC_INFO),
+			#[cfg(feature = "single-component")]
+			4 => Some(OpusSingle::INFO),
 			_ => None,
 		}
 	}
@@ -36,11 +99,23 @@ impl Factory {
 		match *cid {
 			OpusProcessor::CID => Some(OpusProcessor::create_instance()),
 			OpusController::CID => Some(OpusController::create_instance()),
+			OpusProcessor::CODEC_CID => Some(OpusProcessor::create_codec_instance()),
+			OpusController::CODEC_CID => Some(OpusController::create_codec_instance()),
+			#[cfg(feature = "single-component")]
+			OpusSingle::CID => Some(OpusSingle::create_instance()),
 			_ => None,
 		}
 	}
 }
 
+impl Drop for Factory {
+	fn drop(&mut self) {
+		unsafe { self.set_context(null_mut()) };
+		let this = self as *mut Self;
+		let _ = INSTANCE.compare_exchange(this, null_mut(), Ordering::AcqRel, Ordering::Acquire);
+	}
+}
+
 mod vst {
 	use super::Factory;
 	use crate::effect::VstClassInfo;
@@ -56,7 +131,7 @@ mod vst {
 	use vst3_sys::base::PClassInfo2;
 	use vst3_sys::base::PClassInfoW;
 	use vst3_sys::base::PFactoryInfo;
-	use vst3_sys::base::{kInvalidArgument, kResultFalse, kResultOk, tresult};
+	use vst3_sys::base::{kInvalidArgument, kResultOk, tresult};
 
 	impl IPluginFactory for Factory {
 		unsafe fn get_factory_info(&self, info: *mut PFactoryInfo) -> tresult {
@@ -195,9 +270,10 @@ mod vst {
 			}
 		}
 
-		unsafe fn set_host_context(&self, _: *mut c_void) -> tresult {
+		unsafe fn set_host_context(&self, context: *mut c_void) -> tresult {
 			info!("set_host_context()");
-			kResultFalse
+			self.set_context(context);
+			kResultOk
 		}
 	}
 
@@ -209,6 +285,7 @@ mod vst {
 		use vst3_sys::base::IPluginFactory;
 		use vst3_sys::base::IPluginFactory2;
 		use vst3_sys::base::IPluginFactory3;
+		use vst3_sys::base::IUnknown;
 		use vst3_sys::base::PClassInfo;
 		use vst3_sys::base::PClassInfo2;
 		use vst3_sys::base::PClassInfoW;
@@ -219,6 +296,17 @@ mod vst {
 			assert_eq!(Factory::COMPONENT_SDK_VERSION, c_str.to_str().unwrap());
 		}
 
+		#[test]
+		fn instance_is_shared_until_released() {
+			unsafe {
+				let a = Factory::instance() as *mut Factory;
+				let b = Factory::instance() as *mut Factory;
+				assert_eq!(a, b);
+				(*b).release();
+				(*a).release();
+			}
+		}
+
 		#[test]
 		fn component_infos_dont_panic() {
 			let mut a = unsafe { MaybeUninit::zeroed().assume_init() };
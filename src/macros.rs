@@ -4,8 +4,48 @@ macro_rules! vst_result {
 		match $expr {
 			Ok(x) => x,
 			Err(err) => {
+				let err = $crate::error::Error::from(err);
 				error!("{}", err);
-				return vst3_sys::base::kInternalError;
+				return err.result();
+			}
+		}
+	};
+}
+
+/// Declares the `Parameter` enum and its descriptor table, one entry per parameter:
+/// `Variant = "key" => descriptor,`. Ids follow entry order, so only append new
+/// entries. Keys name the values in saved state and must never change.
+#[macro_export]
+macro_rules! parameters {
+	($($variant:ident = $key:literal => $descriptor:expr,)*) => {
+		/// Every parameter, in the order of their ids
+		#[derive(Copy, Clone, Debug, Enum, IntoPrimitive, TryFromPrimitive, VariantCount)]
+		#[repr(u32)]
+		pub enum Parameter {
+			$($variant,)*
+		}
+
+		impl Parameter {
+			/// The parameter table
+			pub fn descriptor(self) -> Descriptor {
+				match self {
+					$(Self::$variant => $descriptor,)*
+				}
+			}
+
+			/// Stable name for saved state
+			pub fn key(self) -> &'static str {
+				match self {
+					$(Self::$variant => $key,)*
+				}
+			}
+
+			/// Parameter saved under a state key
+			pub fn from_key(key: &str) -> Option<Self> {
+				match key {
+					$($key => Some(Self::$variant),)*
+					_ => None,
+				}
 			}
 		}
 	};
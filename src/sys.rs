@@ -0,0 +1,67 @@
+//! The parts of the VST3 API the DSP shares with the C API and wasm. Those build
+//! without the vst3 feature, and so without vst3-sys, against the stand-ins below
+#![allow(non_camel_case_types, non_upper_case_globals)]
+
+#[cfg(feature = "vst3")]
+pub use vst3_sys::base::{
+	kInternalError, kInvalidArgument, kNotInitialized, kOutOfMemory, kResultFalse, kResultOk,
+	kResultTrue, tresult,
+};
+#[cfg(feature = "vst3")]
+pub use vst3_sys::vst::{
+	kRootUnitId, IParamValueQueue, ParameterFlags, ProcessContext, ProcessSetup,
+};
+
+#[cfg(not(feature = "vst3"))]
+pub use standalone::*;
+
+/// Values from the SDK outside of Windows, where the C header documents them
+#[cfg(not(feature = "vst3"))]
+mod standalone {
+	pub type tresult = i32;
+	pub const kResultOk: tresult = 0;
+	pub const kResultTrue: tresult = kResultOk;
+	pub const kResultFalse: tresult = 1;
+	pub const kInvalidArgument: tresult = 2;
+	pub const kInternalError: tresult = 4;
+	pub const kNotInitialized: tresult = 5;
+	pub const kOutOfMemory: tresult = 6;
+
+	pub const kRootUnitId: i32 = 0;
+
+	/// The flags the parameter table uses, named like the SDK's
+	#[allow(clippy::enum_variant_names)]
+	pub enum ParameterFlags {
+		kCanAutomate = 1,
+		kIsReadOnly = 1 << 1,
+		kIsBypass = 1 << 16,
+	}
+
+	/// Settings the host processes with
+	#[repr(C)]
+	#[derive(Copy, Clone)]
+	pub struct ProcessSetup {
+		pub process_mode: i32,
+		pub symbolic_sample_size: i32,
+		pub max_samples_per_block: i32,
+		pub sample_rate: f64,
+	}
+
+	/// Without a host there is never a context, so only what the timeline reads
+	pub struct ProcessContext {
+		pub state: u32,
+		pub project_time_samples: i64,
+		pub continous_time_samples: i64,
+		pub project_time_music: f64,
+		pub tempo: f64,
+	}
+
+	/// Parameter changes come from the C API or wasm through their own calls, so only
+	/// the DSP's tests implement this there
+	pub trait IParamValueQueue {
+		unsafe fn get_parameter_id(&self) -> u32;
+		unsafe fn get_point_count(&self) -> i32;
+		unsafe fn get_point(&self, index: i32, offset: *mut i32, value: *mut f64) -> tresult;
+		unsafe fn add_point(&self, offset: i32, value: f64, index: *mut i32) -> tresult;
+	}
+}
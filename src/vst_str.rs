@@ -1,31 +1,96 @@
-use std::ffi::CString;
 use vst3_sys::vst::TChar;
 use widestring::U16CStr;
-use widestring::U16CString;
 
-/// Create an i8 array containing a UTF-8 C string. May panic.
+/// Longest prefix of `from` that fits in `max` bytes
+/// without splitting a character, stopping at any interior nul.
+fn fit_utf8(from: &str, max: usize) -> &str {
+	let from = from.split('\0').next().unwrap_or_default();
+	if from.len() <= max {
+		return from;
+	}
+	let mut end = max;
+	while !from.is_char_boundary(end) {
+		end -= 1;
+	}
+	&from[..end]
+}
+
+/// Create an i8 array containing a UTF-8 C string, truncated to fit.
 pub fn str_8<const N: usize>(from: &str) -> [i8; N] {
 	let mut to = [0i8; N];
-	let c_str = CString::new(from).unwrap();
-	let from = c_str.to_bytes_with_nul();
-	for i in 0..from.len() {
-		to[i] = from[i] as i8;
+	let from = fit_utf8(from, N.saturating_sub(1));
+	for (to, from) in to.iter_mut().zip(from.bytes()) {
+		*to = from as i8;
 	}
 	to
 }
 
-/// Create an i16 array containing a UTF-16 C string. May panic.
+/// Create an i16 array containing a UTF-16 C string, truncated to fit.
 pub fn str_16<const N: usize>(from: &str) -> [i16; N] {
-	let mut to = [0; N];
-	let wc_str = U16CString::from_str(from).unwrap();
-	let from = wc_str.as_slice_with_nul();
-	for i in 0..from.len() {
-		to[i] = from[i] as i16;
+	let mut to = [0i16; N];
+	let max = N.saturating_sub(1);
+	let mut len = 0;
+	for c in from.chars().take_while(|&c| c != '\0') {
+		let mut units = [0u16; 2];
+		let units = c.encode_utf16(&mut units);
+		// Never split a surrogate pair
+		if len + units.len() > max {
+			break;
+		}
+		for unit in units.iter() {
+			to[len] = *unit as i16;
+			len += 1;
+		}
 	}
 	to
 }
 
+/// Like `str_8`, but None if the string would be truncated or contains a nul.
+pub fn try_str_8<const N: usize>(from: &str) -> Option<[i8; N]> {
+	if from.contains('\0') || from.len() >= N {
+		return None;
+	}
+	Some(str_8(from))
+}
+
+/// Like `str_16`, but None if the string would be truncated or contains a nul.
+pub fn try_str_16<const N: usize>(from: &str) -> Option<[i16; N]> {
+	if from.contains('\0') || from.encode_utf16().count() >= N {
+		return None;
+	}
+	Some(str_16(from))
+}
+
+/// Copy a UTF-16 C string, replacing invalid code units. Null becomes empty.
 pub unsafe fn wcstr_to_str(from: *const TChar) -> String {
+	if from.is_null() {
+		return String::new();
+	}
 	let wc_str = U16CStr::from_ptr_str(from as *const u16);
-	wc_str.to_string().unwrap()
+	wc_str.to_string_lossy()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn truncates_utf8_on_char_boundary() {
+		let a: [i8; 4] = str_8("abcdef");
+		assert_eq!(a, [97, 98, 99, 0]);
+		let b: [i8; 4] = str_8("aé€");
+		assert_eq!(b, [97, 0xc3u8 as i8, 0xa9u8 as i8, 0]);
+		assert!(try_str_8::<4>("abcd").is_none());
+		assert!(try_str_8::<4>("abc").is_some());
+	}
+
+	#[test]
+	fn truncates_utf16_on_surrogate_pair() {
+		let a: [i16; 3] = str_16("a\u{1F600}");
+		assert_eq!(a, [97, 0, 0]);
+		let b: [i16; 4] = str_16("a\u{1F600}");
+		assert_eq!(b, [97, 0xd83du16 as i16, 0xde00u16 as i16, 0]);
+		assert!(try_str_16::<3>("a\u{1F600}").is_none());
+		assert!(try_str_16::<3>("a\0").is_none());
+	}
 }